@@ -1,43 +1,68 @@
-use std::{env, error::Error, fs, path::PathBuf};
+use std::{
+    env,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
 
-use serde::Deserialize;
+use gpui::{Global, Hsla, Rgba, layer_shell::Layer};
+use serde::{Deserialize, Serialize};
 
-use crate::widget::{WidgetOption, clock::ClockConfig};
+use crate::{
+    power_menu::PowerMenuConfig,
+    widget::{
+        WidgetOption, WidgetPlacement, battery_percent::BatteryPercentConfig,
+        bluetooth::BluetoothConfig, brightness::BrightnessConfig, clock::ClockConfig,
+        display::DisplayConfig, do_not_disturb::DoNotDisturbConfig, host::HostConfig,
+        ip_address::IpAddressConfig, load_avg::LoadAvgConfig, power::PowerConfig,
+        power_profile::PowerProfileConfig, task_list::TaskListConfig, timer::TimerConfig,
+        volume::VolumeConfig, vpn::VpnConfig, workspaces::WorkspacesConfig,
+    },
+};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct Config {
     #[serde(default)]
-    pub left: Vec<WidgetOption>,
+    pub left: Vec<WidgetPlacement>,
     #[serde(default)]
-    pub middle: Vec<WidgetOption>,
+    pub middle: Vec<WidgetPlacement>,
     #[serde(default)]
-    pub right: Vec<WidgetOption>,
+    pub right: Vec<WidgetPlacement>,
+    #[serde(default)]
+    pub bar: BarConfig,
     #[serde(default)]
     pub widget: WidgetConfig,
+    #[serde(default)]
+    pub theme: ThemeConfig,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             left: vec![
-                WidgetOption::PowerMenu,
-                WidgetOption::Power,
-                WidgetOption::Clock,
-                WidgetOption::Display,
+                WidgetOption::PowerMenu.into(),
+                WidgetOption::Power.into(),
+                WidgetOption::Clock.into(),
+                WidgetOption::Display.into(),
             ],
-            middle: vec![WidgetOption::Workspaces],
+            middle: vec![WidgetOption::Workspaces.into()],
             right: vec![
-                WidgetOption::Volume,
-                WidgetOption::Bluetooth,
-                WidgetOption::PowerProfile,
+                WidgetOption::Volume.into(),
+                WidgetOption::Bluetooth.into(),
+                WidgetOption::PowerProfile.into(),
             ],
+            bar: BarConfig::default(),
             widget: WidgetConfig::default(),
+            theme: ThemeConfig::default(),
         }
     }
 }
 
 impl Config {
-    pub fn load() -> Result<Self, Box<dyn Error>> {
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::load_from(&Self::default_path()?)
+    }
+    pub fn default_path() -> Result<PathBuf, ConfigError> {
         let path = if let Some(config_home) = env::var_os("XDG_CONFIG_HOME")
             && !config_home.is_empty()
         {
@@ -56,15 +81,787 @@ impl Config {
             .iter()
             .collect()
         } else {
-            return Err("Failed to get home directory".into());
+            return Err(ConfigError::NoHomeDir);
         };
-        let config_content = fs::read(path)?;
-        Ok(toml::from_slice(&config_content)?)
+        Ok(path)
+    }
+    /// Load the config at `path` with its `include`s, see [`read_table`].
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let table = read_table(path, &mut Vec::new())?;
+        toml::Value::Table(table)
+            .try_into()
+            .map(Self::clamped)
+            .map_err(|e| ConfigError::ParseFile {
+                path: path.to_owned(),
+                source: e,
+            })
+    }
+    /// The config as TOML, e.g. to write the default config to disk.
+    // TODO: nothing writes the config yet
+    #[allow(dead_code)]
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string_pretty(self)
+    }
+    /// Read a TOML config from stdin, for `--config -`. `include` is ignored, and empty input is
+    /// the default config.
+    pub fn load_stdin() -> Result<Self, ConfigError> {
+        let content = io::read_to_string(io::stdin()).map_err(ConfigError::ReadStdin)?;
+        if content.trim().is_empty() {
+            tracing::warn!("Config from stdin is empty, fallback to default");
+            return Ok(Self::default());
+        }
+        Self::from_toml(&content)
+    }
+    /// Parse a config without touching the filesystem, `include` is ignored.
+    pub fn from_toml(content: &str) -> Result<Self, ConfigError> {
+        toml::from_str(content)
+            .map(Self::clamped)
+            .map_err(ConfigError::Parse)
+    }
+    /// Replace the values that would break the bar, with a warning.
+    fn clamped(mut self) -> Self {
+        self.bar.clamp();
+        self
+    }
+}
+
+/// Where the config is read from, also available as a global to reload it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigSource {
+    Default,
+    /// `--config <path>`
+    Path(PathBuf),
+    /// `--config -`
+    Stdin,
+}
+
+impl Global for ConfigSource {}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => match Config::default_path() {
+                Ok(path) => write!(f, "{}", path.display()),
+                Err(e) => write!(f, "{e}"),
+            },
+            Self::Path(path) => write!(f, "{}", path.display()),
+            Self::Stdin => write!(f, "stdin"),
+        }
+    }
+}
+
+impl ConfigSource {
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        match self {
+            Self::Default => Config::load(),
+            Self::Path(path) => Config::load_from(path),
+            Self::Stdin => Config::load_stdin(),
+        }
+    }
+}
+
+/// Read a config file (TOML, or JSON with a `.json` extension where `null` means unset) as a
+/// table, merged with the files it includes. `include = ["a.toml"]`
+/// is resolved against the directory of the file, later includes override earlier ones and the
+/// file itself overrides all of them. `stack` is the chain of files including this one.
+fn read_table(path: &Path, stack: &mut Vec<PathBuf>) -> Result<toml::Table, ConfigError> {
+    let read_error = |e| ConfigError::Read {
+        path: path.to_owned(),
+        source: e,
+    };
+    let canonical_path = path.canonicalize().map_err(read_error)?;
+    if stack.contains(&canonical_path) {
+        let mut cycle = stack.clone();
+        cycle.push(canonical_path);
+        return Err(ConfigError::IncludeCycle(cycle));
+    }
+    let mut table = read_file(path)?;
+    let includes = match table.remove("include") {
+        None => Vec::new(),
+        Some(toml::Value::Array(includes)) => includes
+            .into_iter()
+            .map(|x| match x {
+                toml::Value::String(x) => Ok(x),
+                _ => Err(ConfigError::InvalidInclude(path.to_owned())),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err(ConfigError::InvalidInclude(path.to_owned())),
+    };
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut merged = toml::Table::new();
+    stack.push(canonical_path);
+    for include in includes {
+        merge(&mut merged, read_table(&dir.join(include), stack)?);
+    }
+    stack.pop();
+    merge(&mut merged, table);
+    Ok(merged)
+}
+
+/// Read a single config file as a table, without resolving its `include`.
+pub fn read_file(path: &Path) -> Result<toml::Table, ConfigError> {
+    let content = fs::read_to_string(path).map_err(|e| ConfigError::Read {
+        path: path.to_owned(),
+        source: e,
+    })?;
+    // both are read as a toml table, so they can include each other
+    if path.extension().is_some_and(|x| x == "json") {
+        let parse_error = |e| ConfigError::ParseJson {
+            path: path.to_owned(),
+            source: e,
+        };
+        let mut value = serde_json::from_str::<serde_json::Value>(&content).map_err(parse_error)?;
+        remove_nulls(&mut value);
+        serde_json::from_value::<toml::Table>(value).map_err(parse_error)
+    } else {
+        toml::from_str::<toml::Table>(&content).map_err(|e| ConfigError::ParseFile {
+            path: path.to_owned(),
+            source: e,
+        })
+    }
+}
+
+/// TOML has no null, a key set to `null` is the same as leaving it out. A `null` in an array is
+/// still an error.
+fn remove_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|_, x| !x.is_null());
+            map.values_mut().for_each(remove_nulls);
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(remove_nulls),
+        _ => (),
+    }
+}
+
+/// Merge `other` into `base` recursively, the values of `other` win.
+fn merge(base: &mut toml::Table, other: toml::Table) {
+    for (key, value) in other {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(other)) => merge(base, other),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    NoHomeDir,
+    Read {
+        path: PathBuf,
+        source: io::Error,
+    },
+    ReadStdin(io::Error),
+    Parse(toml::de::Error),
+    ParseFile {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    ParseJson {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// `include` is not an array of strings
+    InvalidInclude(PathBuf),
+    /// The files including each other, the first one is the same as the last one
+    IncludeCycle(Vec<PathBuf>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoHomeDir => write!(f, "Failed to get home directory"),
+            Self::Read { path, source } => write!(f, "Failed to read {}: {source}", path.display()),
+            Self::ReadStdin(e) => write!(f, "Failed to read config from stdin: {e}"),
+            Self::Parse(e) => write!(f, "Failed to parse config: {e}"),
+            Self::ParseFile { path, source } => {
+                write!(f, "Failed to parse {}: {source}", path.display())
+            }
+            Self::ParseJson { path, source } => {
+                write!(f, "Failed to parse {}: {source}", path.display())
+            }
+            Self::InvalidInclude(path) => {
+                write!(
+                    f,
+                    "`include` in {} is not an array of paths",
+                    path.display()
+                )
+            }
+            Self::IncludeCycle(cycle) => {
+                write!(f, "Include cycle: ")?;
+                for (index, path) in cycle.iter().enumerate() {
+                    if index != 0 {
+                        write!(f, " -> ")?;
+                    }
+                    write!(f, "{}", path.display())?;
+                }
+                Ok(())
+            }
+        }
     }
 }
 
-#[derive(Deserialize, Default)]
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::NoHomeDir => None,
+            Self::Read { source, .. } => Some(source),
+            Self::ReadStdin(e) => Some(e),
+            Self::Parse(e) => Some(e),
+            Self::ParseFile { source, .. } => Some(source),
+            Self::ParseJson { source, .. } => Some(source),
+            Self::InvalidInclude(_) | Self::IncludeCycle(_) => None,
+        }
+    }
+}
+
+/// Also available as a global for widgets to read bar-wide settings.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct BarConfig {
+    /// Height of the bar in pixels, at least 1, the space reserved for it follows
+    #[serde(default = "default_height")]
+    pub height: f32,
+    /// Slide the bar in from the top edge on startup
+    #[serde(default)]
+    pub animate: bool,
+    /// Fade widget content in when it changes
+    #[serde(default)]
+    pub transitions: bool,
+    #[serde(default)]
+    pub widget_style: WidgetStyle,
+    /// Corner radius of the widget pills, in rems
+    #[serde(default = "default_widget_rounding")]
+    pub widget_rounding: f32,
+    /// Horizontal padding inside each widget, in rems
+    #[serde(default = "default_widget_padding_x")]
+    pub widget_padding_x: f32,
+    /// Vertical padding inside each widget, in rems
+    #[serde(default = "default_widget_padding_y")]
+    pub widget_padding_y: f32,
+    /// Seconds between updates of the widgets that poll, unless they set their own
+    /// `poll_interval`, at least 0.1. Most widgets are event-based and don't poll at all, `Clock`
+    /// and `Timer` wake up on the next minute/second instead.
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval: f64,
+    /// Reserve space for the bar so windows don't overlap it, when false the bar floats over
+    /// other windows
+    #[serde(default = "default_reserve_space")]
+    pub reserve_space: bool,
+    /// The layer-shell layer of the bar, `overlay` stays above fullscreen windows
+    #[serde(default)]
+    pub layer: BarLayer,
+    /// Gaps around the bar, in pixels
+    #[serde(default)]
+    pub margin: BarMargin,
+    /// Text put between adjacent widgets and between the sections, e.g. `"|"`
+    #[serde(default)]
+    pub separator: Option<String>,
+    /// Dim the bars on the monitors that are not focused, only hyprland is supported for now
+    #[serde(default)]
+    pub highlight_focused: bool,
+    /// Error messages longer than this many characters are cut off in the bar, the full message
+    /// is shown when hovering them
+    #[serde(default = "default_max_error_length")]
+    pub max_error_length: usize,
+    /// Show a handle at the start of the bar to drag it up and down, it snaps to the top and
+    /// bottom edges of the monitor when dropped near them. Space is only reserved at the edges,
+    /// not while the bar floats in between. The new position lasts until a restart. Not shown in
+    /// `--preview`.
+    #[serde(default)]
+    pub draggable: bool,
+    /// Flip the scroll direction of the widgets adjusted by scrolling (brightness, keyboard
+    /// backlight and workspaces) and of the `on_scroll_up`/`on_scroll_down` of placements, so
+    /// scrolling down increases instead
+    #[serde(default)]
+    pub natural_scroll: bool,
+    /// Let `eucalyptus-twig msg focus` give the keyboard to the bar, to select a widget with the
+    /// arrow keys (or `h`/`l`, Tab) and activate it with Enter, widgets that don't do anything on
+    /// a click are skipped. Escape stops selecting, the keyboard goes back once another window is
+    /// focused. The bar only asks the compositor for the keyboard, some compositors only give it
+    /// after a click on the bar. Otherwise the bar never takes the keyboard
+    #[serde(default)]
+    pub keyboard_focus: bool,
+}
+
+impl Default for BarConfig {
+    fn default() -> Self {
+        Self {
+            height: default_height(),
+            animate: false,
+            transitions: false,
+            widget_style: WidgetStyle::default(),
+            widget_rounding: default_widget_rounding(),
+            widget_padding_x: default_widget_padding_x(),
+            widget_padding_y: default_widget_padding_y(),
+            poll_interval: default_poll_interval(),
+            reserve_space: default_reserve_space(),
+            layer: BarLayer::default(),
+            margin: BarMargin::default(),
+            separator: None,
+            highlight_focused: false,
+            max_error_length: default_max_error_length(),
+            draggable: false,
+            natural_scroll: false,
+            keyboard_focus: false,
+        }
+    }
+}
+
+impl Global for BarConfig {}
+
+impl BarConfig {
+    /// A layer surface of height 0 is a protocol error, the compositor would disconnect the bar.
+    fn clamp(&mut self) {
+        if !self.height.is_finite() || self.height < 1.0 {
+            tracing::warn!(
+                height = self.height,
+                default = default_height(),
+                "`bar.height` must be at least 1, using the default"
+            );
+            self.height = default_height();
+        }
+    }
+    /// Whether the layer-shell surface needs to be created again for `new`, as its options can't
+    /// be changed after it's created.
+    pub fn surface_changed(&self, new: &Self) -> bool {
+        self.height != new.height
+            || self.margin != new.margin
+            || self.layer != new.layer
+            || self.reserve_space != new.reserve_space
+            || self.keyboard_focus != new.keyboard_focus
+    }
+}
+
+fn default_height() -> f32 {
+    40.0
+}
+
+fn default_max_error_length() -> usize {
+    80
+}
+
+// same as `rounded_lg`
+fn default_widget_rounding() -> f32 {
+    0.5
+}
+
+// same as `px_2`
+fn default_widget_padding_x() -> f32 {
+    0.5
+}
+
+// same as `py_0p5`
+fn default_widget_padding_y() -> f32 {
+    0.125
+}
+
+fn default_poll_interval() -> f64 {
+    5.0
+}
+
+fn default_reserve_space() -> bool {
+    true
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum WidgetStyle {
+    /// Each widget has its own rounded background
+    #[default]
+    Pill,
+    /// A single background for the whole bar
+    Flat,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BarLayer {
+    Background,
+    Bottom,
+    #[default]
+    Top,
+    Overlay,
+}
+
+impl From<BarLayer> for Layer {
+    fn from(value: BarLayer) -> Self {
+        match value {
+            BarLayer::Background => Self::Background,
+            BarLayer::Bottom => Self::Bottom,
+            BarLayer::Top => Self::Top,
+            BarLayer::Overlay => Self::Overlay,
+        }
+    }
+}
+
+/// The bar is only anchored to the top edge, so `left` and `right` have no effect for now.
+#[derive(Deserialize, Serialize, Default, Clone, Copy, Debug, PartialEq)]
+pub struct BarMargin {
+    #[serde(default)]
+    pub top: f32,
+    #[serde(default)]
+    pub right: f32,
+    #[serde(default)]
+    pub bottom: f32,
+    #[serde(default)]
+    pub left: f32,
+}
+
+/// Colors shared by the widgets, also available as a global.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ThemeConfig {
+    /// Active workspaces and hovered items
+    #[serde(default = "default_accent")]
+    pub accent: Color,
+    /// Urgent workspaces and warnings
+    #[serde(default = "default_urgent")]
+    pub urgent: Color,
+    /// Background of the widgets in the `pill` style, e.g. `"#000000aa"` for a translucent bar
+    #[serde(default = "default_pill_background")]
+    pub pill_background: Color,
+    /// The icon theme for app icons, e.g. `"Papirus"`, `hicolor` is always tried after it
+    #[serde(default)]
+    pub icon_theme: Option<String>,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            accent: default_accent(),
+            urgent: default_urgent(),
+            pill_background: default_pill_background(),
+            icon_theme: None,
+        }
+    }
+}
+
+impl Global for ThemeConfig {}
+
+impl ThemeConfig {
+    pub fn accent(&self) -> Hsla {
+        self.accent.0.into()
+    }
+    pub fn urgent(&self) -> Hsla {
+        self.urgent.0.into()
+    }
+    pub fn pill_background(&self) -> Hsla {
+        self.pill_background.0.into()
+    }
+}
+
+// same as `opaque_grey(1.0, 0.75)`
+fn default_accent() -> Color {
+    Color(Rgba {
+        r: 1.0,
+        g: 1.0,
+        b: 1.0,
+        a: 0.75,
+    })
+}
+
+// same as `black()`
+fn default_pill_background() -> Color {
+    Color(Rgba {
+        r: 0.0,
+        g: 0.0,
+        b: 0.0,
+        a: 1.0,
+    })
+}
+
+// same as `red()`
+fn default_urgent() -> Color {
+    Color(Rgba {
+        r: 1.0,
+        g: 0.0,
+        b: 0.0,
+        a: 1.0,
+    })
+}
+
+/// A color written as `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color(pub Rgba);
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Rgba::try_from(value.as_str())
+            .map(Self)
+            .map_err(|e| format!("invalid color `{value}`: {e}"))
+    }
+}
+
+impl From<Color> for String {
+    fn from(Color(color): Color) -> Self {
+        let [r, g, b, a] = [color.r, color.g, color.b, color.a].map(|x| (x * 255.0).round() as u8);
+        format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Debug, PartialEq)]
 pub struct WidgetConfig {
+    #[serde(default)]
+    pub battery_percent: BatteryPercentConfig,
+    #[serde(default)]
+    pub bluetooth: BluetoothConfig,
+    #[serde(default)]
+    pub brightness: BrightnessConfig,
     #[serde(default)]
     pub clock: ClockConfig,
+    #[serde(default)]
+    pub display: DisplayConfig,
+    #[serde(default)]
+    pub do_not_disturb: DoNotDisturbConfig,
+    #[serde(default)]
+    pub host: HostConfig,
+    #[serde(default)]
+    pub ip_address: IpAddressConfig,
+    #[serde(default)]
+    pub load_avg: LoadAvgConfig,
+    #[serde(default)]
+    pub power: PowerConfig,
+    #[serde(default)]
+    pub power_menu: PowerMenuConfig,
+    #[serde(default)]
+    pub power_profile: PowerProfileConfig,
+    #[serde(default)]
+    pub task_list: TaskListConfig,
+    #[serde(default)]
+    pub timer: TimerConfig,
+    #[serde(default)]
+    pub volume: VolumeConfig,
+    #[serde(default)]
+    pub vpn: VpnConfig,
+    /// Used by `Workspaces`, `HyprlandWorkspace` and `SwayWorkspace`
+    #[serde(default)]
+    pub workspaces: WorkspacesConfig,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_round_trips() {
+        let content = Config::default().to_toml().unwrap();
+        assert_eq!(Config::from_toml(&content).unwrap(), Config::default());
+    }
+
+    #[test]
+    fn toml_and_json_parse_the_same() {
+        let dir = env::temp_dir().join(format!("eucalyptus-twig-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let toml_path = dir.join("config.toml");
+        let json_path = dir.join("config.json");
+        fs::write(
+            &toml_path,
+            r#"
+                left = ["Clock", { widget = "Volume", on_click = "pavucontrol" }]
+                right = [{ Label = { text = "work" } }]
+
+                [bar]
+                height = 32.0
+                natural_scroll = true
+
+                [widget.clock]
+                format = "[hour]:[minute]"
+            "#,
+        )
+        .unwrap();
+        fs::write(
+            &json_path,
+            r#"{
+                "left": ["Clock", { "widget": "Volume", "on_click": "pavucontrol" }],
+                "right": [{ "Label": { "text": "work" } }],
+                "bar": { "height": 32.0, "natural_scroll": true, "margin": null },
+                "widget": { "clock": { "format": "[hour]:[minute]", "secondary_format": null } }
+            }"#,
+        )
+        .unwrap();
+        let from_toml = Config::load_from(&toml_path);
+        let from_json = Config::load_from(&json_path);
+        fs::remove_dir_all(&dir).unwrap();
+        let from_toml = from_toml.unwrap_or_else(|e| panic!("{e:?}"));
+        assert_eq!(from_json.unwrap_or_else(|e| panic!("{e:?}")), from_toml);
+        assert_ne!(from_toml, Config::default());
+    }
+
+    #[test]
+    fn nested_includes() {
+        let dir = env::temp_dir().join(format!("eucalyptus-twig-include-{}", std::process::id()));
+        fs::create_dir_all(dir.join("widgets")).unwrap();
+        let files = [
+            (
+                "config.toml",
+                "include = [\"widgets/bar.toml\", \"theme.json\"]\n[bar]\nheight = 40.0\n",
+            ),
+            // relative to the including file, not to the main config
+            (
+                "widgets/bar.toml",
+                "include = [\"left.toml\"]\n[bar]\nheight = 30.0\nanimate = false\n",
+            ),
+            (
+                "widgets/left.toml",
+                "left = [\"Clock\"]\n[bar]\nanimate = true\nreserve_space = false\n",
+            ),
+            ("theme.json", r#"{ "bar": { "reserve_space": true } }"#),
+        ];
+        for (name, content) in files {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        let table = read_table(&dir.join("config.toml"), &mut Vec::new());
+        fs::remove_dir_all(&dir).unwrap();
+        let table = table.unwrap_or_else(|e| panic!("{e:?}"));
+        let bar = &table["bar"];
+        // the including file overrides its includes, later includes override earlier ones
+        assert_eq!(bar["height"].as_float(), Some(40.0));
+        assert_eq!(bar["animate"].as_bool(), Some(false));
+        assert_eq!(bar["reserve_space"].as_bool(), Some(true));
+        assert_eq!(table["left"].as_array().map(Vec::len), Some(1));
+        assert!(!table.contains_key("include"));
+    }
+
+    #[test]
+    fn include_cycle() {
+        let dir = env::temp_dir().join(format!("eucalyptus-twig-cycle-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("config.toml"), "include = [\"a.toml\"]\n").unwrap();
+        fs::write(dir.join("a.toml"), "include = [\"b.toml\"]\n").unwrap();
+        fs::write(dir.join("b.toml"), "include = [\"./a.toml\"]\n").unwrap();
+        fs::write(dir.join("self.toml"), "include = [\"self.toml\"]\n").unwrap();
+        let cycle = read_table(&dir.join("config.toml"), &mut Vec::new());
+        let self_include = read_table(&dir.join("self.toml"), &mut Vec::new());
+        let names = |paths: &[PathBuf]| {
+            paths
+                .iter()
+                .map(|x| x.file_name().unwrap().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        fs::remove_dir_all(&dir).unwrap();
+        match cycle {
+            Err(ConfigError::IncludeCycle(paths)) => {
+                assert_eq!(names(&paths), ["config.toml", "a.toml", "b.toml", "a.toml"]);
+            }
+            x => panic!("expected an include cycle, got {x:?}"),
+        }
+        match self_include {
+            Err(ConfigError::IncludeCycle(paths)) => {
+                assert_eq!(names(&paths), ["self.toml", "self.toml"]);
+            }
+            x => panic!("expected an include cycle, got {x:?}"),
+        }
+    }
+
+    #[test]
+    fn every_widget_name_parses() {
+        let names = [
+            "BatteryPercent",
+            "Bluetooth",
+            "Brightness",
+            "Clock",
+            "Display",
+            "DoNotDisturb",
+            "Host",
+            "HyprlandWorkspace",
+            "Idle",
+            "IpAddress",
+            "KbdBacklight",
+            "LoadAvg",
+            "MicInUse",
+            "Power",
+            "PowerMenu",
+            "PowerProfile",
+            "Quit",
+            "Recording",
+            "Submap",
+            "SwayWorkspace",
+            "TaskList",
+            "Timer",
+            "Volume",
+            "Vpn",
+            "WindowState",
+            "Workspaces",
+        ];
+        let placements = names
+            .iter()
+            .map(|name| format!("\"{name}\""))
+            .chain(["{ Label = { text = \"work\" } }".to_owned()])
+            .collect::<Vec<_>>()
+            .join(", ");
+        let config = Config::from_toml(&format!("left = [{placements}]"))
+            .unwrap_or_else(|e| panic!("{e:?}"));
+        let parsed = config
+            .left
+            .iter()
+            // not exhaustive once a widget is added, which should be added to `names` too
+            .map(|x| match x.widget {
+                WidgetOption::BatteryPercent => "BatteryPercent",
+                WidgetOption::Bluetooth => "Bluetooth",
+                WidgetOption::Brightness => "Brightness",
+                WidgetOption::Clock => "Clock",
+                WidgetOption::Display => "Display",
+                WidgetOption::DoNotDisturb => "DoNotDisturb",
+                WidgetOption::Host => "Host",
+                WidgetOption::HyprlandWorkspace => "HyprlandWorkspace",
+                WidgetOption::Idle => "Idle",
+                WidgetOption::IpAddress => "IpAddress",
+                WidgetOption::KbdBacklight => "KbdBacklight",
+                WidgetOption::Label(_) => "Label",
+                WidgetOption::LoadAvg => "LoadAvg",
+                WidgetOption::MicInUse => "MicInUse",
+                WidgetOption::Power => "Power",
+                WidgetOption::PowerMenu => "PowerMenu",
+                WidgetOption::PowerProfile => "PowerProfile",
+                WidgetOption::Quit => "Quit",
+                WidgetOption::Recording => "Recording",
+                WidgetOption::Submap => "Submap",
+                WidgetOption::SwayWorkspace => "SwayWorkspace",
+                WidgetOption::TaskList => "TaskList",
+                WidgetOption::Timer => "Timer",
+                WidgetOption::Volume => "Volume",
+                WidgetOption::Vpn => "Vpn",
+                WidgetOption::WindowState => "WindowState",
+                WidgetOption::Workspaces => "Workspaces",
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            parsed,
+            names.iter().copied().chain(["Label"]).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn surface_changes() {
+        let config = BarConfig::default();
+        assert!(!config.surface_changed(&config.clone()));
+        // applied without a new surface
+        let new = BarConfig {
+            separator: Some("|".to_owned()),
+            draggable: true,
+            ..config.clone()
+        };
+        assert!(!config.surface_changed(&new));
+
+        let new = BarConfig {
+            height: 30.0,
+            ..config.clone()
+        };
+        assert!(config.surface_changed(&new));
+        let new = BarConfig {
+            layer: BarLayer::Overlay,
+            ..config.clone()
+        };
+        assert!(config.surface_changed(&new));
+        let new = BarConfig {
+            keyboard_focus: true,
+            ..config.clone()
+        };
+        assert!(config.surface_changed(&new));
+    }
 }
@@ -0,0 +1,308 @@
+//! A unix socket to control the running bar, e.g. from a keybind of the compositor:
+//! `eucalyptus-twig msg open_power_menu`.
+//!
+//! Each connection sends one command as a line, and gets `ok` or `error: <message>` back. See
+//! [`Command`] for the commands.
+//!
+//! `save_layout` writes the sections only, next to the config that was loaded (`--config` or the
+//! default path). Include it from that config with `include = ["layout.toml"]` after removing the
+//! sections from the config itself, which would override the included ones.
+
+use std::{
+    env, fs,
+    io::{self, BufRead, Write},
+    os::unix,
+    path::Path,
+};
+
+use futures::{
+    AsyncBufReadExt, AsyncWriteExt, StreamExt,
+    io::{BufReader, BufWriter},
+};
+use gpui::{App, AsyncApp, WindowHandle};
+use gpui_net::async_net::{UnixListener, UnixStream};
+
+use crate::{
+    Bar, MoveTarget, Section,
+    config::{self, Config, ConfigSource, ThemeConfig},
+    power_menu::{self, PowerMenu},
+    widget::app_icon,
+};
+
+/// Written by `save_layout` next to the config.
+const LAYOUT_FILE: &str = "layout.toml";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Open the power menu on the focused monitor
+    OpenPowerMenu,
+    /// `move <id> left|right` within its section, or `move <id> to left|middle|right`. The id is
+    /// the snake_case name of the widget, numbered from the second one of the same widget, e.g.
+    /// `clock_2`
+    MoveWidget { id: String, to: MoveTarget },
+    /// Write the current order of the widgets to `layout.toml` next to the config, an error tells
+    /// how to use it if the config doesn't include it yet
+    SaveLayout,
+    /// Read the config again, only the widgets whose placement or config changed are rebuilt
+    Reload,
+    /// Give the keyboard to the bar on the focused monitor to select and activate its widgets,
+    /// needs `bar.keyboard_focus`
+    Focus,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            ["open_power_menu"] => Ok(Self::OpenPowerMenu),
+            ["move", id, direction] => {
+                let to = match *direction {
+                    "left" => MoveTarget::Left,
+                    "right" => MoveTarget::Right,
+                    _ => return Err(format!("unknown direction `{direction}`")),
+                };
+                Ok(Self::MoveWidget {
+                    id: (*id).to_owned(),
+                    to,
+                })
+            }
+            ["move", id, "to", section] => {
+                let section = match *section {
+                    "left" => Section::Left,
+                    "middle" => Section::Middle,
+                    "right" => Section::Right,
+                    _ => return Err(format!("unknown section `{section}`")),
+                };
+                Ok(Self::MoveWidget {
+                    id: (*id).to_owned(),
+                    to: MoveTarget::Section(section),
+                })
+            }
+            ["save_layout"] => Ok(Self::SaveLayout),
+            ["reload"] => Ok(Self::Reload),
+            ["focus"] => Ok(Self::Focus),
+            _ => Err(format!("unknown command `{}`", line.trim())),
+        }
+    }
+}
+
+pub fn socket_path() -> Result<String, String> {
+    let xdg_runtime_dir = env::var("XDG_RUNTIME_DIR")
+        .map_err(|e| format!("error while getting XDG_RUNTIME_DIR: {e}"))?;
+    Ok(format!("{xdg_runtime_dir}/eucalyptus-twig.sock"))
+}
+
+/// Listen on the socket for the lifetime of the app.
+pub fn start(cx: &mut App) {
+    cx.spawn(async move |cx| {
+        let listener = match bind().await {
+            Ok(x) => x,
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to start the ipc socket");
+                return;
+            }
+        };
+        let mut incoming = listener.incoming();
+        while let Some(stream) = incoming.next().await {
+            match stream {
+                Ok(stream) => {
+                    cx.spawn(async move |cx| {
+                        if let Err(e) = handle_connection(stream, cx).await {
+                            tracing::warn!(error = %e, "Failed to handle an ipc connection");
+                        }
+                    })
+                    .detach();
+                }
+                Err(e) => tracing::warn!(error = %e, "Failed to accept an ipc connection"),
+            }
+        }
+    })
+    .detach();
+}
+
+async fn bind() -> Result<UnixListener, String> {
+    let path = socket_path()?;
+    if UnixStream::connect(&path).await.is_ok() {
+        return Err(format!("another instance is already listening on {path}"));
+    }
+    // a socket left behind by an instance that didn't exit cleanly
+    match fs::remove_file(&path) {
+        Ok(()) => (),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+        Err(e) => return Err(format!("error while removing the old socket ({path}): {e}")),
+    }
+    UnixListener::bind(&path).map_err(|e| format!("error while binding to {path}: {e}"))
+}
+
+async fn handle_connection(stream: UnixStream, cx: &mut AsyncApp) -> Result<(), String> {
+    let mut reader = BufReader::new(stream.clone());
+    let mut writer = BufWriter::new(stream);
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .await
+        .map_err(|e| format!("read_line error: {e}"))?;
+
+    let reply = match Command::parse(&line) {
+        Ok(command) => match run(command, cx).await {
+            Ok(()) => "ok\n".to_owned(),
+            Err(e) => format!("error: {e}\n"),
+        },
+        Err(e) => format!("error: {e}\n"),
+    };
+    writer
+        .write_all(reply.as_bytes())
+        .await
+        .map_err(|e| format!("write_all error: {e}"))?;
+    writer
+        .flush()
+        .await
+        .map_err(|e| format!("flush error: {e}"))
+}
+
+async fn run(command: Command, cx: &mut AsyncApp) -> Result<(), String> {
+    match command {
+        Command::OpenPowerMenu => {
+            let display = power_menu::focused_display(cx).await;
+            cx.update(|cx| PowerMenu::open(display, cx));
+            Ok(())
+        }
+        Command::MoveWidget { id, to } => cx.update(|cx| {
+            // every display has its own bar with the same widgets
+            let bars = bars(cx);
+            if bars.is_empty() {
+                return Err("there is no bar".to_owned());
+            }
+            for bar in bars {
+                bar.update(cx, |bar, _, cx| {
+                    let result = bar.move_widget(&id, to);
+                    cx.notify();
+                    result
+                })
+                .map_err(|e| e.to_string())??;
+            }
+            Ok(())
+        }),
+        Command::SaveLayout => {
+            let (layout, source) = cx.update(|cx| {
+                let bar = bars(cx)
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| "there is no bar".to_owned())?;
+                let layout = bar
+                    .read_with(cx, |bar, _| bar.layout())
+                    .map_err(|e| e.to_string())?;
+                Ok::<_, String>((layout, cx.global::<ConfigSource>().clone()))
+            })?;
+            let config_path = match source {
+                ConfigSource::Default => Config::default_path().map_err(|e| e.to_string())?,
+                ConfigSource::Path(path) => path,
+                ConfigSource::Stdin => {
+                    return Err(
+                        "the config was read from stdin, the layout can't be saved".to_owned()
+                    );
+                }
+            };
+            let content = toml::to_string_pretty(&layout)
+                .map_err(|e| format!("error while serializing the layout: {e}"))?;
+            cx.background_spawn(async move { save_layout(&config_path, content) })
+                .await
+        }
+        Command::Reload => reload(cx).await,
+        Command::Focus => {
+            let display_id = power_menu::focused_display(cx).await.map(|x| x.id());
+            cx.update(|cx| {
+                let bars = bars(cx);
+                // the first bar if the focused monitor is unknown
+                let bar = bars
+                    .iter()
+                    .find(|bar| {
+                        display_id.is_some()
+                            && bar
+                                .update(cx, |_, window, cx| window.display(cx).map(|x| x.id()))
+                                .ok()
+                                .flatten()
+                                == display_id
+                    })
+                    .or(bars.first())
+                    .ok_or_else(|| "there is no bar".to_owned())?;
+                bar.update(cx, |bar, window, cx| bar.grab_keyboard(window, cx))
+                    .map_err(|e| e.to_string())?
+            })
+        }
+    }
+}
+
+/// Write `content` to `layout.toml` next to `config_path`, and check that the config uses it.
+fn save_layout(config_path: &Path, content: String) -> Result<(), String> {
+    let path = config_path.with_file_name(LAYOUT_FILE);
+    fs::write(&path, content)
+        .map_err(|e| format!("error while writing {}: {e}", path.display()))?;
+    tracing::info!(path = %path.display(), "Saved the layout");
+    // the sections of the config itself would override the included ones
+    let uses_layout = config::read_file(config_path).is_ok_and(|table| {
+        let includes_layout = table
+            .get("include")
+            .and_then(|x| x.as_array())
+            .is_some_and(|x| x.iter().any(|x| x.as_str() == Some(LAYOUT_FILE)));
+        includes_layout
+            && !["left", "middle", "right"]
+                .iter()
+                .any(|x| table.contains_key(*x))
+    });
+    if uses_layout {
+        Ok(())
+    } else {
+        Err(format!(
+            "the layout is saved to {}, but {} doesn't use it yet: add \
+             `include = [\"{LAYOUT_FILE}\"]` and remove `left`, `middle` and `right` from it",
+            path.display(),
+            config_path.display(),
+        ))
+    }
+}
+
+/// Read the config again and rebuild the widgets that changed in every bar.
+pub async fn reload(cx: &mut AsyncApp) -> Result<(), String> {
+    let source = cx.update(|cx| cx.global::<ConfigSource>().clone());
+    if source == ConfigSource::Stdin {
+        return Err("the config was read from stdin, it can't be reloaded".to_owned());
+    }
+    let config = cx
+        .background_spawn(async move { source.load() })
+        .await
+        .map_err(|e| e.to_string())?;
+    cx.update(|cx| {
+        if cx.global::<ThemeConfig>().icon_theme != config.theme.icon_theme {
+            app_icon::clear_app_icons(cx);
+        }
+        cx.set_global(config.bar.clone());
+        cx.set_global(config.theme.clone());
+        cx.set_global(config.widget.power_menu.clone());
+        for bar in bars(cx) {
+            bar.update(cx, |bar, window, cx| bar.reload(&config, window, cx))
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    })
+}
+
+fn bars(cx: &App) -> Vec<WindowHandle<Bar>> {
+    cx.windows()
+        .into_iter()
+        .filter_map(|x| x.downcast::<Bar>())
+        .collect()
+}
+
+/// Send a command to the running bar and return its reply, used by the `msg` subcommand.
+pub fn send(command: &str) -> Result<String, String> {
+    let path = socket_path()?;
+    let mut stream = unix::net::UnixStream::connect(&path)
+        .map_err(|e| format!("error while connecting to {path}, is the bar running? {e}"))?;
+    writeln!(stream, "{}", command.trim()).map_err(|e| format!("write error: {e}"))?;
+    let mut reply = String::new();
+    io::BufReader::new(stream)
+        .read_line(&mut reply)
+        .map_err(|e| format!("read_line error: {e}"))?;
+    Ok(reply.trim_end().to_owned())
+}
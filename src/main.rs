@@ -1,25 +1,73 @@
-use std::{ops::Deref, pin::Pin, task::Poll, time::Duration};
+use std::{mem, ops::Deref, pin::Pin, rc::Rc, task::Poll, time::Duration};
 
+use futures::AsyncBufReadExt;
 use gpui::{
-    AnyView, App, Application, Bounds, Context, Entity, Pixels, PlatformDisplay, Size, Window,
-    WindowBackgroundAppearance, WindowBounds, WindowKind, WindowOptions, div,
-    layer_shell::{Anchor, KeyboardInteractivity, Layer, LayerShellOptions},
-    point,
+    Animation, AnimationExt, App, Application, AsyncApp, Bounds, Context, Entity, FocusHandle,
+    KeyBinding, MouseButton, MouseDownEvent, MouseUpEvent, Pixels, PlatformDisplay, Point, Size,
+    Subscription, WeakEntity, Window, WindowBackgroundAppearance, WindowBounds, WindowKind,
+    WindowOptions, actions, black, div, ease_in_out,
+    layer_shell::{Anchor, KeyboardInteractivity, LayerShellOptions},
+    opaque_grey, point,
     prelude::*,
-    px, rems,
+    px, rems, size,
 };
+use serde::Serialize;
 use tracing_subscriber::{field::MakeExt, layer::SubscriberExt, util::SubscriberInitExt};
 
-use crate::config::Config;
+use crate::{
+    config::{BarConfig, Config, ConfigSource, ThemeConfig, WidgetStyle},
+    popup::{Popup, PopupAnchor},
+    widget::{BarWidget, WidgetPlacement, hyprland, is_preview, number_ids},
+};
 
 mod config;
+mod ipc;
+mod popup;
 mod power_menu;
+mod util;
 mod widget;
 
+actions!([
+    SelectPrevious,
+    SelectNext,
+    ActivateSelected,
+    ReleaseKeyboard
+]);
+
 const WIDTH: f32 = 1440.0;
-const HEIGHT: f32 = 40.0;
+/// How close to an edge of the monitor a dragged bar snaps to it, in pixels
+const SNAP_DISTANCE: f32 = 24.0;
+const DISPLAY_RETRIES: u32 = 10;
+const DISPLAY_RETRY_INTERVAL: Duration = Duration::from_millis(500);
 
 fn main() {
+    // `eucalyptus-twig msg <command>` talks to the running bar instead of starting one
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("msg") {
+        let command = args.collect::<Vec<_>>().join(" ");
+        match ipc::send(&command) {
+            Ok(reply) => {
+                println!("{reply}");
+                if reply.starts_with("error") {
+                    std::process::exit(1);
+                }
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    // a normal window with fake data, e.g. for screenshots or working on the layout
+    let preview = std::env::args().skip(1).any(|x| x == "--preview");
+    // `--config <path>`, or `--config -` to read it from stdin
+    let config_source = match std::env::args().skip_while(|x| x != "--config").nth(1) {
+        Some(path) if path == "-" => ConfigSource::Stdin,
+        Some(path) => ConfigSource::Path(path.into()),
+        None => ConfigSource::Default,
+    };
+
     tracing_subscriber::registry()
         .with(tracing_subscriber::fmt::layer().map_fmt_fields(|f| f.debug_alt()))
         .with(
@@ -29,7 +77,7 @@ fn main() {
         )
         .init();
 
-    let config = match Config::load() {
+    let config = match config_source.load() {
         Ok(x) => x,
         Err(e) => {
             tracing::error!(error = %e, "Failed to load config, fallback to default");
@@ -39,6 +87,37 @@ fn main() {
 
     Application::new().run(move |cx: &mut App| {
         gpui_tokio::init(cx);
+        cx.set_global(config.bar.clone());
+        cx.set_global(config.theme.clone());
+        cx.set_global(config.widget.power_menu.clone());
+        cx.set_global(config_source);
+        power_menu::check_fonts(cx);
+        // only reachable while a bar has the keyboard, see `bar.keyboard_focus`
+        cx.bind_keys([
+            KeyBinding::new("left", SelectPrevious, Some("bar")),
+            KeyBinding::new("h", SelectPrevious, Some("bar")),
+            KeyBinding::new("shift-tab", SelectPrevious, Some("bar")),
+            KeyBinding::new("right", SelectNext, Some("bar")),
+            KeyBinding::new("l", SelectNext, Some("bar")),
+            KeyBinding::new("tab", SelectNext, Some("bar")),
+            KeyBinding::new("enter", ActivateSelected, Some("bar")),
+            KeyBinding::new("space", ActivateSelected, Some("bar")),
+            KeyBinding::new("escape", ReleaseKeyboard, Some("bar")),
+        ]);
+        if preview {
+            cx.set_global(widget::Preview);
+            if let Err(e) = cx.open_window(Bar::preview_window_options(&config.bar), |window, cx| {
+                Bar::build_root_view(window, cx, &config)
+            }) {
+                tracing::error!(error = %e, "Failed to open the preview window");
+            }
+            return;
+        }
+        ipc::start(cx);
+        cx.background_spawn(widget::dependency::check(widget::dependency::configured(
+            &config,
+        )))
+        .detach();
 
         cx.spawn(async move |cx| {
             // TODO: by default, gpui will not wait for wayland to tell us displays information
@@ -50,19 +129,45 @@ fn main() {
                 .timer(Duration::from_millis(1))
                 .await;
 
-            cx.update(|cx| {
-                let displays = cx.displays();
+            // some compositors report the displays late, so retry for a while
+            let mut displays = cx.update(|cx| cx.displays());
+            for attempt in 1..=DISPLAY_RETRIES {
+                if !displays.is_empty() {
+                    break;
+                }
+                tracing::warn!(
+                    attempt,
+                    "There is no display in gpui context yet, retrying in {DISPLAY_RETRY_INTERVAL:?}"
+                );
+                cx.background_executor()
+                    .timer(DISPLAY_RETRY_INTERVAL)
+                    .await;
+                displays = cx.update(|cx| cx.displays());
+            }
 
+            cx.update(|cx| {
                 tracing::info!(?displays);
 
-                if displays.len() == 0 {
-                    tracing::warn!("there is no display in gpui context");
+                if displays.is_empty() {
+                    tracing::error!(
+                        "There is still no display in gpui context, opening a single bar and letting the compositor choose the output"
+                    );
+                    cx.open_window(
+                        Bar::window_options(
+                            None::<Rc<dyn PlatformDisplay>>,
+                            &config.bar,
+                            BarPosition::Top,
+                        ),
+                        |window, cx| Bar::build_root_view(window, cx, &config),
+                    )
+                    .unwrap();
                 }
 
                 for display in displays {
-                    cx.open_window(Bar::window_options(Some(display)), |window, cx| {
-                        Bar::build_root_view(window, cx, &config)
-                    })
+                    cx.open_window(
+                        Bar::window_options(Some(display), &config.bar, BarPosition::Top),
+                        |window, cx| Bar::build_root_view(window, cx, &config),
+                    )
                     .unwrap();
                 }
             });
@@ -72,22 +177,71 @@ fn main() {
 }
 
 struct Bar {
-    left: Vec<AnyView>,
-    middle: Vec<AnyView>,
-    right: Vec<AnyView>,
+    /// What the widgets were built from, to rebuild only what changed on a reload
+    config: Config,
+    animate: bool,
+    /// Whether the monitor of this bar is focused, `None` unless `bar.highlight_focused` is set
+    focused: Option<bool>,
+    /// Where the pointer was pressed on the drag handle, while dragging
+    drag_start: Option<Point<Pixels>>,
+    /// Where the bar is on the monitor, changed by dragging it
+    position: BarPosition,
+    focus_handle: FocusHandle,
+    /// Index of the selected widget among the selectable ones, while the bar has the keyboard
+    selected: Option<usize>,
+    /// Stop selecting when the current window loses the keyboard
+    keyboard_subscriptions: Vec<Subscription>,
+    left: Vec<BarWidget>,
+    middle: Vec<BarWidget>,
+    right: Vec<BarWidget>,
 }
 
 impl Bar {
-    pub fn build_root_view(_window: &mut Window, cx: &mut App, config: &Config) -> Entity<Self> {
-        cx.new(|cx| Self {
-            left: config.left.iter().map(|x| x.build(cx, config)).collect(),
-            middle: config.middle.iter().map(|x| x.build(cx, config)).collect(),
-            right: config.right.iter().map(|x| x.build(cx, config)).collect(),
+    pub fn build_root_view(window: &mut Window, cx: &mut App, config: &Config) -> Entity<Self> {
+        let display_origin = window.display(cx).map(|x| x.bounds().origin);
+        cx.new(|cx| {
+            let mut this = Self {
+                config: config.clone(),
+                animate: config.bar.animate,
+                focused: if config.bar.highlight_focused
+                    && let Some(display_origin) = display_origin
+                {
+                    cx.spawn(async move |this, cx| track_focus(this, display_origin, cx).await)
+                        .detach();
+                    Some(true)
+                } else {
+                    None
+                },
+                drag_start: None,
+                position: BarPosition::Top,
+                focus_handle: cx.focus_handle(),
+                selected: None,
+                keyboard_subscriptions: Vec::new(),
+                left: config.left.iter().map(|x| x.build(cx, config)).collect(),
+                middle: config.middle.iter().map(|x| x.build(cx, config)).collect(),
+                right: config.right.iter().map(|x| x.build(cx, config)).collect(),
+            };
+            this.number_ids();
+            this.watch_keyboard(window, cx);
+            this
         })
     }
     pub fn window_options(
         display: Option<impl Deref<Target = impl PlatformDisplay + ?Sized>>,
+        config: &BarConfig,
+        position: BarPosition,
     ) -> WindowOptions {
+        let (anchor, margin_top, margin_bottom, reserve_space) = match position {
+            BarPosition::Top => (
+                Anchor::TOP,
+                config.margin.top,
+                config.margin.bottom,
+                config.reserve_space,
+            ),
+            BarPosition::Bottom => (Anchor::BOTTOM, 0.0, 0.0, config.reserve_space),
+            // a zone reserved at the top would also include the margin, i.e. most of the monitor
+            BarPosition::Floating(top) => (Anchor::TOP, top, 0.0, false),
+        };
         WindowOptions {
             window_bounds: Some(WindowBounds::Windowed(
                 // TODO: I want the window height to fit the content, and the width based on screen width
@@ -95,24 +249,39 @@ impl Bar {
                     && false
                 {
                     let mut bounds = display.bounds();
-                    bounds.size.height = px(HEIGHT);
+                    bounds.size.height = px(config.height);
                     bounds
                 } else {
                     Bounds {
                         origin: point(px(0.0), px(0.0)),
-                        size: Size::new(px(WIDTH), px(HEIGHT)),
+                        size: Size::new(px(WIDTH), px(config.height)),
                     }
                 },
             )),
             titlebar: None,
             kind: WindowKind::LayerShell(LayerShellOptions {
                 namespace: "eucalyptus-twig".to_owned(),
-                layer: Layer::Top,
-                anchor: Anchor::TOP,
-                // TODO: this height should also based on the content
-                exclusive_zone: Some(Pixels::from(HEIGHT)),
-                exclusive_edge: Some(Anchor::TOP),
-                keyboard_interactivity: KeyboardInteractivity::None,
+                layer: config.layer.into(),
+                anchor,
+                // the compositor adds the margin of the anchored edge (top) to the exclusive
+                // zone, but the bottom margin needs to be reserved by ourself
+                exclusive_zone: reserve_space.then(|| Pixels::from(config.height + margin_bottom)),
+                exclusive_edge: reserve_space.then_some(anchor),
+                margin: Some((
+                    px(margin_top),
+                    px(config.margin.right),
+                    px(margin_bottom),
+                    px(config.margin.left),
+                )),
+                // only asked for, so other windows can still take the keyboard back
+                keyboard_interactivity: if config.keyboard_focus {
+                    KeyboardInteractivity::OnDemand
+                } else {
+                    KeyboardInteractivity::None
+                },
+                // TODO: the whole surface takes pointer input, including the transparent margin
+                // and the gaps between the widgets. Letting clicks through there needs the input
+                // region of the wl_surface, which gpui doesn't expose yet.
                 ..Default::default()
             }),
             display_id: display.as_ref().map(|x| x.id()),
@@ -120,11 +289,426 @@ impl Bar {
             ..Default::default()
         }
     }
+    /// A normal window of the size of the bar, for `--preview`.
+    pub fn preview_window_options(config: &BarConfig) -> WindowOptions {
+        WindowOptions {
+            window_bounds: Some(WindowBounds::Windowed(Bounds {
+                origin: point(px(0.0), px(0.0)),
+                size: Size::new(px(WIDTH), px(config.height)),
+            })),
+            titlebar: None,
+            kind: WindowKind::Normal,
+            app_id: Some("eucalyptus-twig-preview".to_owned()),
+            ..Default::default()
+        }
+    }
+    fn section_mut(&mut self, section: Section) -> &mut Vec<BarWidget> {
+        match section {
+            Section::Left => &mut self.left,
+            Section::Middle => &mut self.middle,
+            Section::Right => &mut self.right,
+        }
+    }
+    /// Number the ids of repeated widgets in the order of the config, moving a widget keeps its id
+    /// until the next reload.
+    fn number_ids(&mut self) {
+        number_ids(
+            self.left
+                .iter_mut()
+                .chain(&mut self.middle)
+                .chain(&mut self.right),
+        );
+    }
+    /// Move a widget within its section or to the end of another section, `id` is e.g. `clock`, or
+    /// `clock_2` for the second clock.
+    pub fn move_widget(&mut self, id: &str, to: MoveTarget) -> Result<(), String> {
+        let (section, index) = Section::ALL
+            .into_iter()
+            .find_map(|section| {
+                self.section_mut(section)
+                    .iter()
+                    .position(|x| x.id() == id)
+                    .map(|index| (section, index))
+            })
+            .ok_or_else(|| format!("there is no widget `{id}`"))?;
+        match to {
+            MoveTarget::Left => {
+                if index > 0 {
+                    self.section_mut(section).swap(index, index - 1);
+                }
+            }
+            MoveTarget::Right => {
+                let widgets = self.section_mut(section);
+                if index + 1 < widgets.len() {
+                    widgets.swap(index, index + 1);
+                }
+            }
+            MoveTarget::Section(target) => {
+                let widget = self.section_mut(section).remove(index);
+                self.section_mut(target).push(widget);
+            }
+        }
+        Ok(())
+    }
+    /// The placements of the widgets in each section, as they are in the config.
+    pub fn layout(&self) -> Layout {
+        let placements = |widgets: &[BarWidget]| {
+            widgets
+                .iter()
+                .filter_map(|x| x.placement().cloned())
+                .collect()
+        };
+        Layout {
+            left: placements(&self.left),
+            middle: placements(&self.middle),
+            right: placements(&self.right),
+        }
+    }
+    /// Rebuild the widgets for `config`, keeping the ones with the same placement and widget
+    /// config so they keep their state. Widgets read the bar config when they are built, so
+    /// everything is rebuilt if it changed. The window is opened again if e.g. the height or the
+    /// layer changed.
+    pub fn reload(&mut self, config: &Config, window: &mut Window, cx: &mut Context<Self>) {
+        let rebuild_all = self.config.bar != config.bar;
+        let surface_changed = self.config.bar.surface_changed(&config.bar);
+        let mut old = mem::take(&mut self.left)
+            .into_iter()
+            .chain(mem::take(&mut self.middle))
+            .chain(mem::take(&mut self.right))
+            .collect::<Vec<_>>();
+        let mut section = |placements: &[WidgetPlacement], cx: &mut Context<Self>| {
+            placements
+                .iter()
+                .map(|placement| {
+                    let unchanged = old.iter().position(|x| {
+                        !rebuild_all
+                            && x.placement() == Some(placement)
+                            && !placement
+                                .widget
+                                .config_changed(&self.config.widget, &config.widget)
+                    });
+                    match unchanged {
+                        Some(index) => old.remove(index),
+                        None => placement.build(cx, config),
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+        let left = section(&config.left, cx);
+        let middle = section(&config.middle, cx);
+        let right = section(&config.right, cx);
+        tracing::info!(dropped = old.len(), "Reloaded the widgets");
+        self.left = left;
+        self.middle = middle;
+        self.right = right;
+        self.number_ids();
+        self.config = config.clone();
+        // fewer widgets may be selectable now
+        let selectable = self.selectable_widgets(cx).len();
+        if !config.bar.keyboard_focus || selectable == 0 {
+            self.release_keyboard(window, cx);
+        } else if let Some(selected) = &mut self.selected {
+            *selected = (*selected).min(selectable - 1);
+        }
+        if surface_changed {
+            tracing::info!("The bar surface changed, reopening the window");
+            self.reopen(window, cx);
+        }
+        cx.notify();
+    }
+    /// Move the bar by how far the pointer moved since the drag started, snapping to the edges of
+    /// the monitor.
+    fn end_drag(&mut self, position: Point<Pixels>, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(start) = self.drag_start.take() else {
+            return;
+        };
+        let config = cx.global::<BarConfig>();
+        let display_height = window
+            .display(cx)
+            .map(|x| f32::from(x.bounds().size.height));
+        let top =
+            (self.position.top(config, display_height) + f32::from(position.y - start.y)).max(0.0);
+        let new_position = if top < SNAP_DISTANCE {
+            BarPosition::Top
+        } else if let Some(display_height) = display_height
+            && top > (display_height - config.height).max(0.0) - SNAP_DISTANCE
+        {
+            BarPosition::Bottom
+        } else {
+            BarPosition::Floating(top)
+        };
+        if new_position == self.position {
+            return;
+        }
+        tracing::info!(from = ?self.position, to = ?new_position, "Moving the bar");
+        self.position = new_position;
+        self.reopen(window, cx);
+    }
+    /// Move the view to a new window at the current position. The preview is a normal window,
+    /// which has no position.
+    fn reopen(&self, window: &mut Window, cx: &mut Context<Self>) {
+        if is_preview(cx) {
+            return;
+        }
+        let display = window.display(cx);
+        let config = cx.global::<BarConfig>().clone();
+        let position = self.position;
+        let focused = self.selected.is_some();
+        let focus_handle = self.focus_handle.clone();
+        // gpui can't change the options of a layer-shell surface after it's created, so the same
+        // view moves to a new window instead
+        let this = cx.entity();
+        window.remove_window();
+        cx.defer(move |cx| {
+            let options = Bar::window_options(display, &config, position);
+            if let Err(e) = cx.open_window(options, |window, cx| {
+                if focused {
+                    focus_handle.focus(window, cx);
+                }
+                this.update(cx, |this, cx| this.watch_keyboard(window, cx));
+                this
+            }) {
+                tracing::error!(error = %e, "Failed to reopen the bar");
+            }
+        });
+    }
+    /// Take the keyboard to select a widget with the arrow keys and activate it with Enter, until
+    /// Escape. Only with `bar.keyboard_focus`, which lets the bar ask the compositor for the
+    /// keyboard.
+    pub fn grab_keyboard(
+        &mut self,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Result<(), String> {
+        if !cx.global::<BarConfig>().keyboard_focus {
+            return Err("`bar.keyboard_focus` is not enabled".to_owned());
+        }
+        if self.selected.is_some() {
+            return Ok(());
+        }
+        if self.selectable_widgets(cx).is_empty() {
+            return Err("there is no widget to select".to_owned());
+        }
+        tracing::info!("Grabbing the keyboard");
+        self.selected = Some(0);
+        self.focus_handle.focus(window, cx);
+        window.activate_window();
+        cx.notify();
+        Ok(())
+    }
+    /// Stop selecting when the bar loses the keyboard, e.g. once another window is clicked.
+    /// Otherwise the selection stays and `msg focus` can't take the keyboard again.
+    fn watch_keyboard(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.keyboard_subscriptions = vec![
+            cx.on_blur(&self.focus_handle, window, |this, _, cx| {
+                this.lost_keyboard(cx)
+            }),
+            // the focus stays on the bar when the compositor gives the keyboard to another window
+            cx.observe_window_activation(window, |this, window, cx| {
+                if !window.is_window_active() {
+                    this.lost_keyboard(cx);
+                }
+            }),
+        ];
+    }
+    fn lost_keyboard(&mut self, cx: &mut Context<Self>) {
+        if self.selected.take().is_some() {
+            tracing::info!("The bar lost the keyboard, stop selecting");
+            cx.notify();
+        }
+    }
+    fn release_keyboard(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.selected.take().is_none() {
+            return;
+        }
+        tracing::info!("Releasing the keyboard");
+        // the compositor gives the keyboard to another window once it's focused
+        window.blur();
+        cx.notify();
+    }
+    /// Move the selection by `offset` widgets, stopping at the ends.
+    fn select(&mut self, offset: isize, cx: &mut Context<Self>) {
+        let Some(selected) = self.selected else {
+            return;
+        };
+        let last = self.selectable_widgets(cx).len().saturating_sub(1);
+        self.selected = Some(selected.saturating_add_signed(offset).min(last));
+        cx.notify();
+    }
+    fn activate_selected(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(selected) = self.selected else {
+            return;
+        };
+        if let Some(widget) = self.selectable_widgets(cx).get(selected) {
+            tracing::info!(id = %widget.id(), "Activating the selected widget");
+            widget.activate(window, cx);
+        }
+        self.release_keyboard(window, cx);
+    }
+    /// The widgets shown in the bar that do something on Enter, from left to right.
+    fn selectable_widgets(&self, cx: &App) -> Vec<&BarWidget> {
+        self.left
+            .iter()
+            .chain(&self.middle)
+            .chain(&self.right)
+            .filter(|x| x.visible(cx) && x.activatable(cx))
+            .collect()
+    }
+    /// A popup with the version and the config of the bar, and buttons to reload and quit.
+    fn open_menu(&self, x: Pixels, window: &mut Window, cx: &mut Context<Self>) {
+        Popup::open(
+            PopupAnchor::with_position(window, self.position, cx),
+            x,
+            size(px(320.0), px(136.0)),
+            cx,
+            |_, cx| {
+                let accent = cx.global::<ThemeConfig>().accent();
+                let button = |id: &'static str, label: &'static str| {
+                    div()
+                        .id(id)
+                        .px(rems(0.5))
+                        .rounded(rems(0.25))
+                        .border_1()
+                        .border_color(opaque_grey(0.6, 1.0))
+                        .hover(|style| style.bg(accent.opacity(0.3)))
+                        .child(label)
+                };
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap(rems(0.25))
+                    .child(format!("eucalyptus-twig {}", env!("CARGO_PKG_VERSION")))
+                    .child(
+                        div()
+                            .text_color(opaque_grey(0.6, 1.0))
+                            .truncate()
+                            .child(format!("Config: {}", cx.global::<ConfigSource>())),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .gap(rems(0.5))
+                            .pt(rems(0.25))
+                            .child(button("bar-menu-reload", "Reload").on_click(|_, _, cx| {
+                                cx.spawn(async move |cx| {
+                                    if let Err(e) = ipc::reload(cx).await {
+                                        tracing::error!(error = %e, "Failed to reload the config");
+                                    }
+                                })
+                                .detach();
+                            }))
+                            .child(button("bar-menu-quit", "Quit").on_click(|_, _, cx| cx.quit())),
+                    )
+                    .into_any_element()
+            },
+        );
+    }
+    /// Find a widget in any section by its [`Widget::id`](crate::widget::Widget::id).
+    pub fn widget(&self, id: &str) -> Option<&BarWidget> {
+        self.left
+            .iter()
+            .chain(&self.middle)
+            .chain(&self.right)
+            .find(|x| x.id() == id)
+    }
+}
+
+/// Follow the focused monitor of hyprland, until the event socket is closed.
+async fn track_focus(this: WeakEntity<Bar>, display_origin: Point<Pixels>, cx: &mut AsyncApp) {
+    let update = async |this: &WeakEntity<Bar>, cx: &mut AsyncApp| {
+        let focused = match hyprland::focused_monitor().await {
+            Ok(monitor) => point(px(monitor.x as f32), px(monitor.y as f32)) == display_origin,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to get the focused monitor");
+                return;
+            }
+        };
+        let _ = this.update(cx, |this, cx| {
+            this.focused = Some(focused);
+            cx.notify();
+        });
+    };
+
+    let mut event_stream = match hyprland::connect_events().await {
+        Ok(x) => x,
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to follow the focused monitor");
+            return;
+        }
+    };
+    update(&this, cx).await;
+    loop {
+        let mut line = String::new();
+        match event_stream.read_line(&mut line).await {
+            Ok(0) => return,
+            Ok(_) => (),
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to read the hyprland event socket");
+                return;
+            }
+        }
+        if line.starts_with("focusedmon>>") {
+            update(&this, cx).await;
+        }
+    }
 }
 
 impl Render for Bar {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
-        div()
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let separator_text = cx.global::<BarConfig>().separator.clone();
+        let draggable = cx.global::<BarConfig>().draggable && !is_preview(cx);
+        let separator = |separator: &String| {
+            div()
+                .flex()
+                .items_center()
+                .text_color(opaque_grey(0.6, 1.0))
+                .child(separator.clone())
+                .into_any_element()
+        };
+        let accent = cx.global::<ThemeConfig>().accent();
+        // counts the selectable widgets across the sections, to find the selected one
+        let mut index = 0;
+        let mut visible = |widgets: &[BarWidget]| {
+            let mut elements = Vec::new();
+            for widget in widgets.iter().filter(|x| x.visible(cx)) {
+                if let Some(separator_text) = &separator_text
+                    && !elements.is_empty()
+                {
+                    elements.push(separator(separator_text));
+                }
+                let element = widget.render(cx);
+                let selected = widget.activatable(cx) && {
+                    index += 1;
+                    self.selected == Some(index - 1)
+                };
+                elements.push(if selected {
+                    div()
+                        .rounded(rems(0.5))
+                        .border_2()
+                        .border_color(accent)
+                        .child(element)
+                        .into_any_element()
+                } else {
+                    element
+                });
+            }
+            elements
+        };
+        let left = visible(&self.left);
+        let middle = visible(&self.middle);
+        let right = visible(&self.right);
+        // between the sections only if there is something on both sides
+        let left_separator = separator_text
+            .as_ref()
+            .filter(|_| !left.is_empty() && !middle.is_empty())
+            .map(separator);
+        let right_separator = separator_text
+            .as_ref()
+            .filter(|_| !middle.is_empty() && !right.is_empty())
+            .map(separator);
+
+        let bar = div()
+            .relative()
             .size_full()
             .flex()
             .items_center()
@@ -133,8 +717,52 @@ impl Render for Bar {
             // .font_weight(FontWeight::EXTRA_BOLD)
             // .text_color(white())
             // .bg(rgba(0x0000044))
+            .when(
+                cx.global::<BarConfig>().widget_style == WidgetStyle::Flat,
+                |this| this.bg(black()),
+            )
             .rounded_xl()
             .p_1()
+            // dim the bars on the other monitors
+            .when(self.focused == Some(false), |this| this.opacity(0.6))
+            .key_context("bar")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(|this, _: &SelectPrevious, _, cx| this.select(-1, cx)))
+            .on_action(cx.listener(|this, _: &SelectNext, _, cx| this.select(1, cx)))
+            .on_action(cx.listener(|this, _: &ActivateSelected, window, cx| {
+                this.activate_selected(window, cx);
+            }))
+            .on_action(cx.listener(|this, _: &ReleaseKeyboard, window, cx| {
+                this.release_keyboard(window, cx);
+            }))
+            .on_mouse_down(
+                MouseButton::Right,
+                cx.listener(|this, event: &MouseDownEvent, window, cx| {
+                    this.open_menu(event.position.x, window, cx);
+                }),
+            )
+            .when(draggable, |this| {
+                this.on_mouse_up(
+                    MouseButton::Left,
+                    cx.listener(|this, event: &MouseUpEvent, window, cx| {
+                        this.end_drag(event.position, window, cx);
+                    }),
+                )
+                .child(
+                    div()
+                        .id("bar-drag-handle")
+                        .px_1()
+                        .cursor_grab()
+                        .text_color(opaque_grey(0.6, 1.0))
+                        .child("⠿")
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(|this, event: &MouseDownEvent, _, _| {
+                                this.drag_start = Some(event.position);
+                            }),
+                        ),
+                )
+            })
             .child(
                 div()
                     .flex_grow()
@@ -142,9 +770,11 @@ impl Render for Bar {
                     .flex()
                     .justify_start()
                     .gap(rems(0.25))
-                    .children(self.left.clone()),
+                    .children(left),
             )
-            .child(div().flex().gap(rems(0.25)).children(self.middle.clone()))
+            .children(left_separator)
+            .child(div().flex().gap(rems(0.25)).children(middle))
+            .children(right_separator)
             .child(
                 div()
                     .flex_grow()
@@ -152,11 +782,77 @@ impl Render for Bar {
                     .flex()
                     .justify_end()
                     .gap(rems(0.25))
-                    .children(self.right.clone()),
+                    .children(right),
+            );
+
+        if self.animate {
+            let height = cx.global::<BarConfig>().height;
+            // the exclusive zone is part of the window options, so the space is reserved
+            // right away while the content slides in
+            bar.with_animation(
+                "bar-slide-in",
+                Animation::new(Duration::from_millis(400)).with_easing(ease_in_out),
+                move |bar, delta| bar.top(px(-height * (1.0 - delta))).opacity(delta),
             )
+            .into_any_element()
+        } else {
+            bar.into_any_element()
+        }
+    }
+}
+
+/// Where a bar is on its monitor, it's only moved by dragging.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BarPosition {
+    /// At the top edge with the configured margins
+    Top,
+    /// At the bottom edge, reserving space there
+    Bottom,
+    /// This many pixels from the top edge, without reserving space
+    Floating(f32),
+}
+
+impl BarPosition {
+    /// Distance of the top of the bar from the top of the monitor, `display_height` is needed
+    /// for `Bottom`.
+    fn top(self, config: &BarConfig, display_height: Option<f32>) -> f32 {
+        match self {
+            Self::Top => config.margin.top,
+            Self::Bottom => display_height.map_or(0.0, |x| (x - config.height).max(0.0)),
+            Self::Floating(top) => top,
+        }
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Section {
+    Left,
+    Middle,
+    Right,
+}
+
+impl Section {
+    const ALL: [Self; 3] = [Self::Left, Self::Middle, Self::Right];
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveTarget {
+    /// One place to the left within the section
+    Left,
+    /// One place to the right within the section
+    Right,
+    /// To the end of a section
+    Section(Section),
+}
+
+/// The sections of [`Config`], written by the `save_layout` command.
+#[derive(Serialize, Debug)]
+pub struct Layout {
+    pub left: Vec<WidgetPlacement>,
+    pub middle: Vec<WidgetPlacement>,
+    pub right: Vec<WidgetPlacement>,
+}
+
 struct PollCounter {
     count: u32,
     max: u32,
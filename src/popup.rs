@@ -0,0 +1,162 @@
+use std::rc::Rc;
+
+use gpui::{
+    AnyElement, App, Bounds, Context, FocusHandle, Global, KeyBinding, Pixels, PlatformDisplay,
+    Size, Window, WindowBackgroundAppearance, WindowBounds, WindowHandle, WindowKind,
+    WindowOptions, actions, black, div,
+    layer_shell::{Anchor, KeyboardInteractivity, Layer, LayerShellOptions},
+    point,
+    prelude::*,
+    px, rems, white,
+};
+
+use crate::{Bar, BarPosition, config::BarConfig};
+
+actions!([Close]);
+
+/// Where a popup opens, taken from the window of the bar when it's requested so it can be kept
+/// across an await.
+#[derive(Clone)]
+pub struct PopupAnchor {
+    display: Option<Rc<dyn PlatformDisplay>>,
+    /// Distance of the left edge of the bar from the left edge of the display
+    bar_left: f32,
+    /// Distance of the top of the bar from the top of the display
+    bar_top: f32,
+}
+
+impl PopupAnchor {
+    /// The bar in `window`, wherever it was dragged to.
+    pub fn new(window: &Window, cx: &App) -> Self {
+        let position = window
+            .root::<Bar>()
+            .flatten()
+            .map_or(BarPosition::Top, |bar| bar.read(cx).position);
+        Self::with_position(window, position, cx)
+    }
+    /// [`Self::new`] for the bar itself, which can't be read while it's updated.
+    pub fn with_position(window: &Window, position: BarPosition, cx: &App) -> Self {
+        let display = window.display(cx);
+        let display_height = display.as_ref().map(|x| f32::from(x.bounds().size.height));
+        let bar_top = position.top(cx.global::<BarConfig>(), display_height);
+        // the bar is only anchored to one edge, so the compositor centers it horizontally
+        let bar_left = display.as_ref().map_or(0.0, |x| {
+            (f32::from(x.bounds().size.width - window.viewport_size().width) / 2.0).max(0.0)
+        });
+        Self {
+            display,
+            bar_left,
+            bar_top,
+        }
+    }
+    /// Right under the bar, or above it if there is no room under it.
+    fn top(&self, height: Pixels, cx: &App) -> Pixels {
+        let bar_height = cx.global::<BarConfig>().height;
+        let under = px(self.bar_top + bar_height);
+        match &self.display {
+            Some(display) if under + height > display.bounds().size.height => {
+                px((self.bar_top - f32::from(height)).max(0.0))
+            }
+            _ => under,
+        }
+    }
+}
+
+/// The open popup, there is only one at a time.
+#[derive(Default)]
+struct OpenPopup(Option<WindowHandle<Popup>>);
+
+impl Global for OpenPopup {}
+
+type Content = Box<dyn Fn(&mut Window, &mut Context<Popup>) -> AnyElement>;
+
+/// A small window right under the bar, e.g. for the details of a widget. Closed by a click or
+/// escape.
+pub struct Popup {
+    content: Content,
+    focus_handle: FocusHandle,
+}
+
+impl Popup {
+    /// Open a popup of `size` with its left edge at `x` of the bar at `anchor`, closing the one
+    /// that's already open. The content is only rendered again when the popup is notified, e.g.
+    /// by observing the entity it shows.
+    pub fn open(
+        anchor: PopupAnchor,
+        x: Pixels,
+        size: Size<Pixels>,
+        cx: &mut App,
+        content: impl Fn(&mut Window, &mut Context<Popup>) -> AnyElement + 'static,
+    ) -> Option<WindowHandle<Popup>> {
+        let content: Content = Box::new(content);
+        if let Some(open) = cx.default_global::<OpenPopup>().0.take() {
+            // fails if it's already closed
+            let _ = open.update(cx, |_, window, _| window.remove_window());
+        }
+        let top = anchor.top(size.height, cx);
+        let left = px(anchor.bar_left) + x;
+        let options = Self::window_options(anchor.display, left, top, size);
+        let result = cx.open_window(options, |window, cx| {
+            cx.new(|cx| {
+                cx.bind_keys([KeyBinding::new("escape", Close, Some("popup"))]);
+                let focus_handle = cx.focus_handle();
+                focus_handle.focus(window, cx);
+                Self {
+                    content,
+                    focus_handle,
+                }
+            })
+        });
+        let popup = result
+            .inspect_err(|e| tracing::error!(error = %e, "Failed to open popup"))
+            .ok();
+        cx.set_global(OpenPopup(popup));
+        popup
+    }
+    fn window_options(
+        display: Option<Rc<dyn PlatformDisplay>>,
+        x: Pixels,
+        top: Pixels,
+        size: Size<Pixels>,
+    ) -> WindowOptions {
+        WindowOptions {
+            window_bounds: Some(WindowBounds::Windowed(Bounds {
+                origin: point(x, top),
+                size,
+            })),
+            titlebar: None,
+            kind: WindowKind::LayerShell(LayerShellOptions {
+                namespace: "eucalyptus-twig-popup".to_owned(),
+                layer: Layer::Overlay,
+                anchor: Anchor::TOP | Anchor::LEFT,
+                margin: Some((top, px(0.0), px(0.0), x)),
+                keyboard_interactivity: KeyboardInteractivity::OnDemand,
+                ..Default::default()
+            }),
+            display_id: display.as_ref().map(|x| x.id()),
+            window_background: WindowBackgroundAppearance::Transparent,
+            ..Default::default()
+        }
+    }
+}
+
+impl Render for Popup {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .id("popup")
+            .key_context("popup")
+            .track_focus(&self.focus_handle)
+            .on_action(|_close: &Close, window, _cx| {
+                window.remove_window();
+            })
+            .on_click(|_, window, _| {
+                window.remove_window();
+            })
+            .size_full()
+            .p(rems(0.75))
+            .rounded_xl()
+            .bg(black())
+            .text_color(white())
+            .child((self.content)(window, cx))
+    }
+}
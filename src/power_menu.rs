@@ -1,22 +1,50 @@
-use std::{ops::Deref, time::Duration};
+use std::{ops::Deref, rc::Rc, time::Duration};
 
 use gpui::{
-    Animation, AnimationExt, App, Context, Entity, FocusHandle, KeyBinding, PlatformDisplay,
-    StatefulInteractiveElement, Window, WindowBackgroundAppearance, WindowKind, WindowOptions,
-    actions, black, div, ease_in_out,
+    Animation, AnimationExt, App, AsyncApp, Context, Div, Entity, FocusHandle, Global, KeyBinding,
+    PlatformDisplay, SharedString, StatefulInteractiveElement, Window, WindowBackgroundAppearance,
+    WindowKind, WindowOptions, actions, black, div, ease_in_out,
     layer_shell::{KeyboardInteractivity, Layer, LayerShellOptions},
+    opaque_grey,
     prelude::*,
-    relative, rems, white,
+    relative, rems, rgb, white,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::ThemeConfig,
+    util::spawn_shell,
+    widget::{error_element, hyprland},
 };
 
 actions!([Escape]);
 
 pub struct PowerMenu {
+    layout: PowerMenuLayout,
+    backdrop_opacity: f32,
+    hover_labels: bool,
     selected: Option<PowerMenuOption>,
+    hovered: Option<PowerMenuOption>,
+    lock_command: Option<String>,
+    /// `None` when the font isn't installed, the options show their names instead
+    icon_font: Option<SharedString>,
+    /// `None` when the font isn't installed, the default font is used instead
+    label_font: Option<SharedString>,
+    error_message: Option<String>,
     focus_handle: FocusHandle,
 }
 
 impl PowerMenu {
+    /// Open the power menu, unless one is already open.
+    pub fn open(display: Option<Rc<dyn PlatformDisplay>>, cx: &mut App) {
+        if cx.windows().iter().any(|x| x.downcast::<Self>().is_some()) {
+            tracing::info!("The power menu is already open");
+            return;
+        }
+        if let Err(e) = cx.open_window(Self::window_options(display), Self::build_root_view) {
+            tracing::error!(error = %e, "Failed to open power menu");
+        }
+    }
     pub fn build_root_view(window: &mut Window, cx: &mut App) -> Entity<Self> {
         cx.new(|cx| {
             cx.bind_keys([
@@ -29,12 +57,44 @@ impl PowerMenu {
             let focus_handle = cx.focus_handle();
             focus_handle.focus(window, cx);
 
+            let config = cx.global::<PowerMenuConfig>();
+            let fonts = cx.text_system().all_font_names();
+            let installed = |font: &String| {
+                fonts
+                    .contains(font)
+                    .then(|| SharedString::from(font.clone()))
+            };
             Self {
+                layout: config.layout,
+                backdrop_opacity: config.backdrop_opacity,
+                hover_labels: config.hover_labels,
                 selected: None,
+                hovered: None,
+                lock_command: config.lock_command.clone(),
+                icon_font: installed(&config.icon_font),
+                label_font: installed(&config.label_font),
+                error_message: None,
                 focus_handle,
             }
         })
     }
+    fn confirm(&mut self, option: PowerMenuOption, window: &mut Window, cx: &mut Context<Self>) {
+        let result = match option {
+            PowerMenuOption::Lock => match &self.lock_command {
+                Some(command) => spawn_shell(command),
+                None => Err("`widget.power_menu.lock_command` is not set".to_owned()),
+            },
+            // TODO: the other options don't do anything yet
+            _ => Ok(()),
+        };
+        match result {
+            Ok(()) => window.remove_window(),
+            Err(e) => {
+                self.error_message = Some(e);
+                cx.notify();
+            }
+        }
+    }
     pub fn window_options(
         display: Option<impl Deref<Target = impl PlatformDisplay + ?Sized>>,
     ) -> WindowOptions {
@@ -72,80 +132,240 @@ impl Render for PowerMenu {
             })
             .size_full()
             .flex()
-            // .flex_col()
+            .when(self.layout == PowerMenuLayout::Column, |this| {
+                this.flex_col()
+            })
             .items_center()
             .justify_center()
-            .gap(rems(0.5));
-        // .bg(opaque_grey(0.2, 0.8));
-
-        let button = || {
-            div()
-                .flex()
-                .items_center()
-                .justify_center()
-                .rounded_xl()
-                .text_size(rems(5.0))
-                .text_color(white())
-                .font_family("Material Symbols Rounded")
-                .bg(black())
+            .gap(rems(0.5))
+            // the backdrop is the wrapper itself, so clicking it still closes the menu
+            .when(self.backdrop_opacity > 0.0, |this| {
+                this.bg(opaque_grey(0.2, self.backdrop_opacity))
+            });
+
+        let accent = cx.global::<ThemeConfig>().accent();
+        let label =
+            || div().when_some(self.label_font.clone(), |this, font| this.font_family(font));
+        // the icon, or the name when the icon font is missing
+        let button = |icon: &'static str, name: &'static str| {
+            let content = match &self.icon_font {
+                Some(font) => div()
+                    .text_size(rems(5.0))
+                    .font_family(font.clone())
+                    .child(icon),
+                None => label().py(rems(1.0)).text_size(rems(1.5)).child(name),
+            };
+            button_wrapper().child(content)
         };
 
         if let Some(selected_option) = self.selected {
+            let error = self.error_message.as_deref().map(|e| error_element(cx, e));
             wrapper
                 .child(
-                    button()
+                    button("", "Back")
                         .id("power-menu-back")
                         .on_click(cx.listener(|this, _, _, cx| {
                             this.selected = None;
+                            this.error_message = None;
                             cx.stop_propagation();
                         }))
-                        .px(rems(0.6))
-                        .child(""), // .with_animation(
-                                     //     "power-menu-back-name-animation",
-                                     //     Animation::new(Duration::from_millis(1500))
-                                     //         .with_easing(ease_in_out),
-                                     //     |element, delta| element.w(relative(delta)),
-                                     // ),
+                        .px(rems(0.6)), // .with_animation(
+                                        //     "power-menu-back-name-animation",
+                                        //     Animation::new(Duration::from_millis(1500))
+                                        //         .with_easing(ease_in_out),
+                                        //     |element, delta| element.w(relative(delta)),
+                                        // ),
                 )
                 .child(
-                    button()
+                    button_wrapper()
                         .id("power-menu-real")
-                        .on_click(|_, window, cx| {
-                            window.remove_window();
+                        .on_click(cx.listener(move |this, _, window, cx| {
+                            this.confirm(selected_option, window, cx);
                             cx.stop_propagation();
-                        })
+                        }))
                         .gap(rems(2.0))
                         .px(rems(2.0))
-                        .child(selected_option.icon())
+                        // the name is shown next to it anyway
+                        .when_some(self.icon_font.clone(), |this, font| {
+                            this.child(
+                                div()
+                                    .text_size(rems(5.0))
+                                    .font_family(font)
+                                    .child(selected_option.icon()),
+                            )
+                        })
                         .child(
-                            div()
-                                .text_size(rems(3.6))
-                                .font_family("Noto Sans")
-                                .child(selected_option.name())
-                                .with_animation(
-                                    "power-menu-real-name",
-                                    Animation::new(Duration::from_millis(1500))
-                                        .with_easing(ease_in_out),
-                                    |element, delta| element.w(relative(delta)),
-                                ),
+                            label()
+                                .flex()
+                                .flex_col()
+                                .child(
+                                    div()
+                                        .text_size(rems(3.6))
+                                        .child(selected_option.name())
+                                        .with_animation(
+                                            "power-menu-real-name",
+                                            Animation::new(Duration::from_millis(1500))
+                                                .with_easing(ease_in_out),
+                                            |element, delta| element.w(relative(delta)),
+                                        ),
+                                )
+                                .when_some(error, |this, error| {
+                                    this.child(
+                                        div()
+                                            .pb(rems(0.5))
+                                            .text_size(rems(1.0))
+                                            .text_color(rgb(0xff6666))
+                                            .child(error),
+                                    )
+                                }),
                         ),
                 )
         } else {
             wrapper.children(PowerMenuOption::ALL.map(|option| {
-                button()
+                button(option.icon(), option.name())
                     .id(format!("power-menu-option-{}", option.id()))
                     .on_click(cx.listener(move |this, _, _, cx| {
                         this.selected = Some(option);
                         cx.stop_propagation();
                     }))
+                    .on_hover(cx.listener(move |this, hovered: &bool, _, cx| {
+                        if *hovered {
+                            this.hovered = Some(option);
+                        } else if this.hovered == Some(option) {
+                            this.hovered = None;
+                        }
+                        cx.notify();
+                    }))
                     .w(rems(8.0))
-                    .child(option.icon())
+                    .flex_col()
+                    .when(self.hovered == Some(option), |this| this.text_color(accent))
+                    .when(self.hover_labels && self.icon_font.is_some(), |this| {
+                        // always laid out so that hovering doesn't move the options
+                        this.child(
+                            label()
+                                .pb(rems(0.5))
+                                .text_size(rems(1.0))
+                                .child(option.name())
+                                .when(self.hovered != Some(option), |this| this.invisible()),
+                        )
+                    })
             }))
         }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+/// The display of the monitor focused in the compositor, only hyprland is supported for now.
+pub async fn focused_display(cx: &mut AsyncApp) -> Option<Rc<dyn PlatformDisplay>> {
+    match hyprland::focused_monitor().await {
+        Ok(monitor) => {
+            let display = cx.update(|cx| hyprland::monitor_display(&monitor, cx));
+            if display.is_none() {
+                tracing::warn!(monitor = monitor.name, "No display at the focused monitor");
+            }
+            display
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to get the focused monitor");
+            None
+        }
+    }
+}
+
+/// Also available as a global, the power menu can be opened without the widget.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct PowerMenuConfig {
+    /// Open on the focused monitor instead of the monitor of the clicked bar
+    #[serde(default)]
+    pub focused_monitor: bool,
+    #[serde(default)]
+    pub layout: PowerMenuLayout,
+    /// Opacity of the backdrop dimming the desktop behind the menu, `0.0` to disable
+    #[serde(default = "default_backdrop_opacity")]
+    pub backdrop_opacity: f32,
+    /// Show the name of an option under its icon when hovered
+    #[serde(default = "default_hover_labels")]
+    pub hover_labels: bool,
+    /// Command run by `Lock` (e.g. `hyprlock`), through `sh -c`
+    #[serde(default)]
+    pub lock_command: Option<String>,
+    /// The options show their names when this font isn't installed
+    #[serde(default = "default_icon_font")]
+    pub icon_font: String,
+    #[serde(default = "default_label_font")]
+    pub label_font: String,
+}
+
+impl Default for PowerMenuConfig {
+    fn default() -> Self {
+        Self {
+            focused_monitor: false,
+            layout: PowerMenuLayout::default(),
+            backdrop_opacity: default_backdrop_opacity(),
+            hover_labels: default_hover_labels(),
+            lock_command: None,
+            icon_font: default_icon_font(),
+            label_font: default_label_font(),
+        }
+    }
+}
+
+impl Global for PowerMenuConfig {}
+
+/// The background and layout shared by the buttons of the menu, see
+/// [`crate::widget::widget_wrapper`] for the widgets.
+fn button_wrapper() -> Div {
+    div()
+        .flex()
+        .items_center()
+        .justify_center()
+        .rounded_xl()
+        .text_color(white())
+        .bg(black())
+}
+
+fn default_backdrop_opacity() -> f32 {
+    0.3
+}
+
+fn default_hover_labels() -> bool {
+    true
+}
+
+fn default_icon_font() -> String {
+    "Material Symbols Rounded".to_owned()
+}
+
+fn default_label_font() -> String {
+    "Noto Sans".to_owned()
+}
+
+/// Warn at startup about the fonts of the power menu that aren't installed.
+pub fn check_fonts(cx: &App) {
+    let config = cx.global::<PowerMenuConfig>();
+    let fonts = cx.text_system().all_font_names();
+    if !fonts.contains(&config.icon_font) {
+        tracing::warn!(
+            font = config.icon_font,
+            "The icon font of the power menu is not installed, showing the names of the options instead"
+        );
+    }
+    if !fonts.contains(&config.label_font) {
+        tracing::warn!(
+            font = config.label_font,
+            "The label font of the power menu is not installed, using the default font instead"
+        );
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerMenuLayout {
+    #[default]
+    Row,
+    Column,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum PowerMenuOption {
     Lock,
     Suspend,
@@ -0,0 +1,234 @@
+use std::{
+    future::Future,
+    os::unix::process::CommandExt,
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use gpui::AsyncApp;
+
+/// Run a command without waiting for it to finish, e.g. `hyprlock --immediate`.
+///
+/// The command is split into arguments like a shell would with simple quoting, but without any
+/// other shell features, use [`spawn_shell`] for those.
+pub fn spawn_detached(command: &str) -> Result<(), String> {
+    let args = split_args(command)?;
+    let Some((program, args)) = args.split_first() else {
+        return Err("empty command".to_owned());
+    };
+    let mut process = Command::new(program);
+    process.args(args);
+    spawn(process, command)
+}
+
+/// Run a shell command (through `sh -c`) without waiting for it to finish.
+pub fn spawn_shell(command: &str) -> Result<(), String> {
+    let mut process = Command::new("sh");
+    process.arg("-c").arg(command);
+    spawn(process, command)
+}
+
+fn spawn(mut process: Command, command: &str) -> Result<(), String> {
+    tracing::info!(command, "Spawning command");
+    // in its own process group, so it's not killed together with the bar by a ctrl-c
+    process
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0);
+    match process.spawn() {
+        Ok(mut child) => {
+            // reap the child so it doesn't stay as a zombie
+            thread::spawn(move || child.wait());
+            Ok(())
+        }
+        Err(e) => {
+            tracing::error!(command, error = %e, "Failed to spawn command");
+            Err(format!("{command}: {e}"))
+        }
+    }
+}
+
+/// Split a command into arguments at whitespace. Single quotes keep everything literally, double
+/// quotes and a backslash outside of quotes only escape the next character.
+pub fn split_args(command: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    // `None` between arguments, so `''` is still an (empty) argument
+    let mut current: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(arg) = current.take() {
+                    args.push(arg);
+                }
+            }
+            '\'' => {
+                let arg = current.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err(format!("unterminated `'` in `{command}`")),
+                    }
+                }
+            }
+            '"' => {
+                let arg = current.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => arg.push(c),
+                            None => return Err(format!("unterminated `\"` in `{command}`")),
+                        },
+                        Some(c) => arg.push(c),
+                        None => return Err(format!("unterminated `\"` in `{command}`")),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => current.get_or_insert_default().push(c),
+                None => return Err(format!("trailing `\\` in `{command}`")),
+            },
+            c => current.get_or_insert_default().push(c),
+        }
+    }
+    args.extend(current);
+    Ok(args)
+}
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Run `connect` until it succeeds, waiting twice as long after each failure (up to a minute).
+/// `on_error` gets each error with how long until the next attempt, e.g. to show the error in the
+/// widget meanwhile, and returns whether to keep trying (e.g. `false` once the widget is dropped).
+pub async fn retry_with_backoff<T, E>(
+    cx: &mut AsyncApp,
+    connect: impl AsyncFnMut(&mut AsyncApp) -> Result<T, E>,
+    on_error: impl FnMut(&mut AsyncApp, E, Duration) -> bool,
+) -> Option<T> {
+    let executor = cx.background_executor().clone();
+    retry(cx, connect, on_error, |backoff| executor.timer(backoff)).await
+}
+
+/// [`retry_with_backoff`] with any context and way to wait.
+async fn retry<C, T, E, S: Future<Output = ()>>(
+    cx: &mut C,
+    mut connect: impl AsyncFnMut(&mut C) -> Result<T, E>,
+    mut on_error: impl FnMut(&mut C, E, Duration) -> bool,
+    mut sleep: impl FnMut(Duration) -> S,
+) -> Option<T> {
+    let mut backoff = INITIAL_BACKOFF;
+    loop {
+        match connect(cx).await {
+            Ok(x) => return Some(x),
+            Err(e) => {
+                if !on_error(cx, e, backoff) {
+                    return None;
+                }
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{executor::block_on, future};
+
+    use super::*;
+
+    #[test]
+    fn split_at_whitespace() {
+        assert_eq!(
+            split_args("  hyprlock   --immediate\t-q ").unwrap(),
+            ["hyprlock", "--immediate", "-q"]
+        );
+        assert!(split_args("").unwrap().is_empty());
+        assert!(split_args("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn split_quoted_args() {
+        assert_eq!(
+            split_args(r#"notify-send 'hello world' "a \"b\"""#).unwrap(),
+            ["notify-send", "hello world", r#"a "b""#]
+        );
+        // no escapes in single quotes
+        assert_eq!(split_args(r"echo 'a\b'").unwrap(), ["echo", r"a\b"]);
+        // quotes join with the text around them
+        assert_eq!(split_args("--name='a b'c").unwrap(), ["--name=a bc"]);
+        assert_eq!(split_args(r#"echo '' """#).unwrap(), ["echo", "", ""]);
+        assert_eq!(split_args(r"a\ b").unwrap(), ["a b"]);
+    }
+
+    #[test]
+    fn split_unterminated() {
+        assert!(split_args("echo 'a").is_err());
+        assert!(split_args(r#"echo "a"#).is_err());
+        assert!(split_args(r#"echo "a\"#).is_err());
+        assert!(split_args(r"echo a\").is_err());
+    }
+
+    /// Fails `failures` times, then connects.
+    async fn connect(attempts: &mut u32, failures: u32) -> Result<&'static str, u32> {
+        *attempts += 1;
+        if *attempts > failures {
+            Ok("connected")
+        } else {
+            Err(*attempts)
+        }
+    }
+
+    #[test]
+    fn retry_until_connected() {
+        let mut attempts = 0;
+        let mut errors = Vec::new();
+        let result = block_on(retry(
+            &mut attempts,
+            async |attempts| connect(attempts, 2).await,
+            |_, e, retry_in| {
+                errors.push((e, retry_in));
+                true
+            },
+            |_| future::ready(()),
+        ));
+        assert_eq!(result, Some("connected"));
+        assert_eq!(attempts, 3);
+        assert_eq!(errors, [(1, INITIAL_BACKOFF), (2, INITIAL_BACKOFF * 2)]);
+    }
+
+    #[test]
+    fn stop_retrying() {
+        let mut attempts = 0;
+        let result = block_on(retry(
+            &mut attempts,
+            async |attempts| connect(attempts, 2).await,
+            |_, _, _| false,
+            |_| future::ready(()),
+        ));
+        assert_eq!(result, None);
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let mut attempts = 0;
+        let mut waits = Vec::new();
+        block_on(retry(
+            &mut attempts,
+            async |attempts| connect(attempts, 10).await,
+            |_, _, _| true,
+            |backoff| {
+                waits.push(backoff);
+                future::ready(())
+            },
+        ));
+        assert_eq!(waits.len(), 10);
+        assert_eq!(waits.last(), Some(&MAX_BACKOFF));
+    }
+}
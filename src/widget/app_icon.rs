@@ -0,0 +1,287 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use gpui::{AnyElement, App, Global, IntoElement, ParentElement, Styled, div, img, rems};
+
+use crate::config::ThemeConfig;
+
+/// The icon files of the app ids looked up so far, `None` for those without one.
+#[derive(Default)]
+struct AppIcons(HashMap<String, Option<PathBuf>>);
+
+impl Global for AppIcons {}
+
+/// The icon of an app (e.g. of a window from its app id), or a generic glyph if it has none.
+///
+/// The first time an app id is seen it's looked up in the background, the glyph is shown
+/// meanwhile and the windows are refreshed once it's found.
+pub fn app_icon(app_id: &str, cx: &mut App) -> AnyElement {
+    let icons = cx.default_global::<AppIcons>();
+    match icons.0.get(app_id) {
+        Some(Some(path)) => img(path.clone()).size(rems(1.0)).into_any_element(),
+        Some(None) => fallback_icon(),
+        None => {
+            // cache the miss right away, so it's looked up only once
+            icons.0.insert(app_id.to_owned(), None);
+            let app_id = app_id.to_owned();
+            let theme = cx.global::<ThemeConfig>().icon_theme.clone();
+            cx.spawn(async move |cx| {
+                let path = cx
+                    .background_executor()
+                    .spawn({
+                        let app_id = app_id.clone();
+                        let theme = theme.clone();
+                        async move { find_app_icon(&app_id, theme.as_deref()) }
+                    })
+                    .await;
+                match &path {
+                    Some(path) => tracing::info!(app_id, ?path, "Found app icon"),
+                    None => tracing::info!(app_id, "No app icon found"),
+                }
+                cx.update(|cx| {
+                    // looked up in a theme that was changed meanwhile
+                    if cx.global::<ThemeConfig>().icon_theme != theme {
+                        return;
+                    }
+                    cx.default_global::<AppIcons>().0.insert(app_id, path);
+                    cx.refresh_windows();
+                });
+            })
+            .detach();
+            fallback_icon()
+        }
+    }
+}
+
+/// Forget the icons found so far, e.g. after `icon_theme` changed, so they're looked up again.
+pub fn clear_app_icons(cx: &mut App) {
+    cx.default_global::<AppIcons>().0.clear();
+}
+
+fn fallback_icon() -> AnyElement {
+    div()
+        .font_family("Material Symbols Rounded")
+        .child("")
+        .into_any_element()
+}
+
+/// The icon file of an app id, from the `Icon` of its desktop entry and the icon theme, or the
+/// app id itself as the icon name if it has no desktop entry.
+fn find_app_icon(app_id: &str, theme: Option<&str>) -> Option<PathBuf> {
+    let data_dirs = data_dirs();
+    let icon = desktop_entry(app_id, &data_dirs)
+        .and_then(|entry| desktop_entry_icon(&entry))
+        .unwrap_or_else(|| app_id.to_owned());
+    if icon.starts_with('/') {
+        return Path::new(&icon).is_file().then(|| PathBuf::from(icon));
+    }
+    find_icon(&icon, theme, &data_dirs)
+        .or_else(|| find_icon(&icon.to_lowercase(), theme, &data_dirs))
+}
+
+/// `$XDG_DATA_HOME` and `$XDG_DATA_DIRS` with their defaults, most important first.
+fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    match env::var_os("XDG_DATA_HOME").filter(|x| !x.is_empty()) {
+        Some(x) => dirs.push(PathBuf::from(x)),
+        None => {
+            if let Some(home) = env::var_os("HOME") {
+                dirs.push(Path::new(&home).join(".local/share"));
+            }
+        }
+    }
+    let data_dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|x| !x.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_owned());
+    dirs.extend(data_dirs.split(':').map(PathBuf::from));
+    dirs
+}
+
+/// The desktop entry named after the app id, ignoring case, or the one with it as
+/// `StartupWMClass`.
+fn desktop_entry(app_id: &str, data_dirs: &[PathBuf]) -> Option<String> {
+    let file_name = format!("{app_id}.desktop");
+    let mut by_wm_class = None;
+    for dir in data_dirs {
+        let Ok(entries) = fs::read_dir(dir.join("applications")) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            if !name.ends_with(".desktop") {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            if name.eq_ignore_ascii_case(&file_name) {
+                return Some(content);
+            }
+            if by_wm_class.is_none()
+                && desktop_entry_value(&content, "StartupWMClass")
+                    .is_some_and(|x| x.eq_ignore_ascii_case(app_id))
+            {
+                by_wm_class = Some(content);
+            }
+        }
+    }
+    by_wm_class
+}
+
+fn desktop_entry_icon(entry: &str) -> Option<String> {
+    desktop_entry_value(entry, "Icon").map(str::to_owned)
+}
+
+/// A key of the `[Desktop Entry]` group, other groups like actions have their own icons.
+fn desktop_entry_value<'a>(entry: &'a str, key: &str) -> Option<&'a str> {
+    let mut in_group = false;
+    for line in entry.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_group = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_group {
+            continue;
+        }
+        if let Some((k, v)) = line.split_once('=')
+            && k.trim() == key
+        {
+            return Some(v.trim()).filter(|x| !x.is_empty());
+        }
+    }
+    None
+}
+
+/// The sizes tried in each theme, the bar is small so the ones close to it come first.
+const SIZES: &[&str] = &[
+    "scalable", "48x48", "64x64", "32x32", "128x128", "256x256", "24x24", "22x22", "16x16",
+    "512x512",
+];
+
+/// Look up an icon name in the configured theme, then `hicolor` (the fallback of every theme),
+/// then `pixmaps`. This only tries the usual `<size>/apps` layout instead of reading
+/// `index.theme`.
+fn find_icon(name: &str, theme: Option<&str>, data_dirs: &[PathBuf]) -> Option<PathBuf> {
+    let mut icon_dirs = Vec::new();
+    if let Some(home) = env::var_os("HOME") {
+        icon_dirs.push(Path::new(&home).join(".icons"));
+    }
+    icon_dirs.extend(data_dirs.iter().map(|x| x.join("icons")));
+
+    let themes = theme.into_iter().chain(["hicolor"]);
+    for theme in themes {
+        for size in SIZES {
+            for dir in &icon_dirs {
+                let apps = dir.join(theme).join(size).join("apps");
+                if let Some(path) = with_extension(&apps, name) {
+                    return Some(path);
+                }
+            }
+        }
+    }
+    data_dirs
+        .iter()
+        .chain([&PathBuf::from("/usr/share")])
+        .find_map(|x| with_extension(&x.join("pixmaps"), name))
+}
+
+fn with_extension(dir: &Path, name: &str) -> Option<PathBuf> {
+    ["svg", "png"]
+        .into_iter()
+        .map(|extension| dir.join(format!("{name}.{extension}")))
+        .find(|x| x.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn desktop_entry_lookup() {
+        let dir = env::temp_dir().join(format!("eucalyptus-twig-entries-{}", std::process::id()));
+        let (home, system) = (dir.join("home"), dir.join("system"));
+        write(
+            &home.join("applications/org.example.Editor.desktop"),
+            "[Desktop Entry]\nName=Editor\nIcon=editor-home\n",
+        );
+        write(
+            &system.join("applications/org.example.Editor.desktop"),
+            "[Desktop Entry]\nName=Editor\nIcon=editor-system\n",
+        );
+        write(
+            &system.join("applications/browser.desktop"),
+            "[Desktop Entry]\nStartupWMClass=Browser-Nightly\nIcon=browser\n\n\
+             [Desktop Action private]\nIcon=browser-private\n",
+        );
+        write(
+            &system.join("applications/empty.desktop"),
+            "[Desktop Entry]\nIcon=\n",
+        );
+        let data_dirs = [home, system];
+
+        let icon = |app_id| desktop_entry(app_id, &data_dirs).and_then(|x| desktop_entry_icon(&x));
+        let icons = [
+            // the first data dir wins, and the case of the file name doesn't matter
+            icon("org.example.editor"),
+            // by `StartupWMClass`, not from the action
+            icon("browser-nightly"),
+            icon("empty"),
+            icon("missing"),
+        ];
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            icons,
+            [
+                Some("editor-home".to_owned()),
+                Some("browser".to_owned()),
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn icon_name_resolution() {
+        let dir = env::temp_dir().join(format!("eucalyptus-twig-icons-{}", std::process::id()));
+        let icons = dir.join("icons");
+        let theme = "eucalyptus-twig-test-theme";
+        let themed = icons
+            .join(theme)
+            .join("scalable/apps/eucalyptus-twig-editor.svg");
+        let hicolor_small = icons.join("hicolor/16x16/apps/eucalyptus-twig-terminal.png");
+        let hicolor = icons.join("hicolor/48x48/apps/eucalyptus-twig-terminal.png");
+        let pixmap = dir.join("pixmaps/eucalyptus-twig-browser.png");
+        for path in [&themed, &hicolor_small, &hicolor, &pixmap] {
+            write(path, "");
+        }
+        write(
+            &icons.join("hicolor/scalable/apps/eucalyptus-twig-editor.svg"),
+            "",
+        );
+        let data_dirs = [dir.clone()];
+
+        let found = [
+            // the theme before hicolor
+            find_icon("eucalyptus-twig-editor", Some(theme), &data_dirs),
+            // a size close to the bar first
+            find_icon("eucalyptus-twig-terminal", Some(theme), &data_dirs),
+            find_icon("eucalyptus-twig-browser", None, &data_dirs),
+            find_icon("eucalyptus-twig-missing", Some(theme), &data_dirs),
+        ];
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(found, [Some(themed), Some(hicolor), Some(pixmap), None]);
+    }
+}
@@ -0,0 +1,143 @@
+use futures::{StreamExt, channel::mpsc, join};
+use gpui::{
+    AsyncApp, Context, IntoElement, ParentElement, Render, Styled, WeakEntity, Window,
+    prelude::FluentBuilder,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Color, ThemeConfig},
+    widget::{
+        Widget,
+        battery_source::{
+            BatterySource, BatteryUpdate, FakeBatterySource, UpowerBatterySource, is_battery,
+        },
+        dependency::Dependency,
+        error::WidgetError,
+        error_element, is_preview, spawn_task, spawn_watched_task, widget_wrapper,
+    },
+};
+
+/// Only the battery percentage as a number, for a battery icon elsewhere. Hidden without a
+/// battery.
+pub struct BatteryPercent {
+    config: BatteryPercentConfig,
+    error: Option<WidgetError>,
+    type_: Option<u32>,
+    is_present: Option<bool>,
+    percentage: Option<f64>,
+}
+
+impl Widget for BatteryPercent {
+    type Config = BatteryPercentConfig;
+
+    fn dependencies() -> &'static [Dependency] {
+        &[Dependency::SystemDbus("org.freedesktop.UPower")]
+    }
+
+    fn new(cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        if is_preview(cx) {
+            let source = FakeBatterySource::discharging(42.0);
+            spawn_task(cx, async move |this, cx| task(this, cx, source).await).detach();
+        } else {
+            let source = UpowerBatterySource {
+                device: config.device.clone(),
+            };
+            spawn_watched_task(cx, async move |this, cx| task(this, cx, source).await).detach();
+        }
+
+        Self {
+            config: config.clone(),
+            error: None,
+            type_: None,
+            is_present: None,
+            percentage: None,
+        }
+    }
+
+    fn visible(&self) -> bool {
+        if let Some(e) = &self.error {
+            return !e.hides_widget();
+        }
+        self.type_
+            .is_some_and(|x| is_battery(x, self.config.device.is_some()))
+            && self.is_present != Some(false)
+            && self.percentage.is_some()
+    }
+}
+
+impl BatteryPercent {
+    /// Only the updates the number needs, the rest is for the `Power` widget.
+    fn apply(&mut self, update: BatteryUpdate) {
+        match update {
+            BatteryUpdate::Error(x) => self.error = x,
+            BatteryUpdate::Type(x) => self.type_ = Some(x),
+            BatteryUpdate::IsPresent(x) => self.is_present = Some(x),
+            BatteryUpdate::Percentage(x) => self.percentage = Some(x),
+            _ => (),
+        }
+    }
+}
+
+impl Render for BatteryPercent {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if let Some(e) = &self.error {
+            return widget_wrapper(cx).child(error_element(cx, &e.to_string()));
+        }
+        let Some(percentage) = self.percentage else {
+            return widget_wrapper(cx);
+        };
+        let low = self.config.low_percentage > 0.0 && percentage <= self.config.low_percentage;
+        let urgent = cx.global::<ThemeConfig>().urgent();
+        widget_wrapper(cx)
+            .when_some(self.config.color, |this, Color(color)| {
+                this.text_color(color)
+            })
+            .when(low, |this| this.text_color(urgent))
+            .child(format!("{percentage:.0}%"))
+    }
+}
+
+async fn task(this: WeakEntity<BatteryPercent>, cx: &mut AsyncApp, source: impl BatterySource) {
+    let (tx, mut rx) = mpsc::unbounded();
+    let mut source_cx = cx.clone();
+    join!(source.run(tx, &mut source_cx), async move {
+        while let Some(update) = rx.next().await {
+            let result = this.update(cx, |this, cx| {
+                this.apply(update);
+                cx.notify();
+            });
+            if result.is_err() {
+                // dropping `rx` stops the source
+                break;
+            }
+        }
+    });
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct BatteryPercentConfig {
+    /// Native path of the UPower device to show (see `upower --dump`), the display device if unset
+    #[serde(default)]
+    pub device: Option<String>,
+    /// Color of the number, the bar's text color if unset
+    #[serde(default)]
+    pub color: Option<Color>,
+    /// At or below this the number is shown in the urgent color of the theme, `0` to never
+    #[serde(default = "default_low_percentage")]
+    pub low_percentage: f64,
+}
+
+impl Default for BatteryPercentConfig {
+    fn default() -> Self {
+        Self {
+            device: None,
+            color: None,
+            low_percentage: default_low_percentage(),
+        }
+    }
+}
+
+fn default_low_percentage() -> f64 {
+    20.0
+}
@@ -0,0 +1,318 @@
+use std::{future::Future, time::Duration};
+
+use futures::{StreamExt, channel::mpsc::UnboundedSender, join};
+use gpui::AsyncApp;
+use zbus::Connection;
+
+use crate::{
+    util::retry_with_backoff,
+    widget::{
+        error::WidgetError,
+        power::{UpowerDeviceProxy, UpowerProxy},
+    },
+};
+
+/// Where the `Power` and `BatteryPercent` widgets get the state of the battery from, so it can
+/// be driven by fixed values instead of UPower.
+pub trait BatterySource: 'static {
+    /// Send the state of the battery and every change of it to `tx`, until `tx` is closed because
+    /// the widget is dropped.
+    fn run(self, tx: UnboundedSender<BatteryUpdate>, cx: &mut AsyncApp)
+    -> impl Future<Output = ()>;
+}
+
+/// A change of one property of the battery, named after the UPower properties.
+#[derive(Clone)]
+pub enum BatteryUpdate {
+    /// Shown instead of the battery (or hiding it), `None` to clear it
+    Error(Option<WidgetError>),
+    /// The device, for what is only read on demand
+    Device(BatteryDevice),
+    OnBattery(bool),
+    Type(u32),
+    IsPresent(bool),
+    State(u32),
+    Percentage(f64),
+    TimeToEmpty(Option<Duration>),
+    TimeToFull(Option<Duration>),
+    EnergyRate(f64),
+}
+
+/// The device behind the updates, for the health popup and refreshing.
+#[derive(Clone)]
+pub enum BatteryDevice {
+    Upower(UpowerDeviceProxy<'static>),
+    /// Fixed health and charge cycles
+    Fake {
+        health: f64,
+        charge_cycles: i32,
+    },
+}
+
+impl BatteryDevice {
+    /// The health and the charge cycles of the battery, as lines for the popup.
+    pub async fn health(&self) -> Vec<String> {
+        let (health, charge_cycles) = match self {
+            Self::Upower(proxy) => upower_health(proxy).await,
+            Self::Fake {
+                health,
+                charge_cycles,
+            } => (format!("{health:.0}%"), charge_cycles.to_string()),
+        };
+        vec![
+            format!("Health: {health}"),
+            format!("Charge cycles: {charge_cycles}"),
+        ]
+    }
+    /// Ask for the battery to be read again, the new values come as updates.
+    pub async fn refresh(&self) {
+        if let Self::Upower(proxy) = self
+            && let Err(e) = proxy.refresh().await
+        {
+            tracing::error!(error = %e, "Failed to refresh the battery");
+        }
+    }
+}
+
+async fn upower_health(proxy: &UpowerDeviceProxy<'_>) -> (String, String) {
+    let health = match join!(proxy.energy_full(), proxy.energy_full_design()) {
+        // some devices report 0 when they don't know the design capacity
+        (Ok(full), Ok(design)) if design > 0.0 => format!("{:.0}%", full / design * 100.0),
+        (Ok(_), Ok(_)) => "unknown".to_owned(),
+        (Err(e), _) | (_, Err(e)) => {
+            tracing::error!(error = %e, "Failed to get battery energy");
+            "unknown".to_owned()
+        }
+    };
+    let charge_cycles = match proxy.charge_cycles().await {
+        // -1 if unknown
+        Ok(x) if x >= 0 => x.to_string(),
+        Ok(_) => "unknown".to_owned(),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to get charge cycles");
+            "unknown".to_owned()
+        }
+    };
+    (health, charge_cycles)
+}
+
+/// Whether a UPower device of `type_` has a battery to show. The display device is a battery (2)
+/// on laptops, a configured `device` can also be e.g. a mouse (5) or a headset (17).
+pub fn is_battery(type_: u32, configured_device: bool) -> bool {
+    match type_ {
+        2 => true,
+        // unknown or line power
+        0 | 1 => false,
+        _ => configured_device,
+    }
+}
+
+pub struct UpowerBatterySource {
+    /// Native path of the device to show, the display device if unset
+    pub device: Option<String>,
+}
+
+impl BatterySource for UpowerBatterySource {
+    async fn run(self, tx: UnboundedSender<BatteryUpdate>, cx: &mut AsyncApp) {
+        let Some((upower_proxy, device_proxy)) = retry_with_backoff(
+            cx,
+            async |_| connect(self.device.as_deref()).await,
+            |_, e, retry_in| {
+                tracing::error!(error = %e, ?retry_in, "Failed to connect to UPower");
+                tx.unbounded_send(BatteryUpdate::Error(Some(e))).is_ok()
+            },
+        )
+        .await
+        else {
+            return;
+        };
+        let _ = tx.unbounded_send(BatteryUpdate::Error(None));
+        let mut on_battery_stream = upower_proxy.receive_on_battery_changed().await;
+        let mut type_stream = device_proxy.receive_type__changed().await;
+        let mut is_present_stream = device_proxy.receive_is_present_changed().await;
+        let mut state_stream = device_proxy.receive_state_changed().await;
+        let mut percentage_stream = device_proxy.receive_percentage_changed().await;
+        let mut time_to_empty_stream = device_proxy.receive_time_to_empty_changed().await;
+        let mut time_to_full_stream = device_proxy.receive_time_to_full_changed().await;
+        let mut energy_rate_stream = device_proxy.receive_energy_rate_changed().await;
+        let _ = tx.unbounded_send(BatteryUpdate::Device(BatteryDevice::Upower(
+            device_proxy.clone(),
+        )));
+        macro_rules! handle_stream {
+            ($stream:expr, $field:ident, $variant:path, $name:literal $(, $map:expr)?) => {
+                {
+                    let tx = &tx;
+                    async move {
+                        while let Some($field) = $stream.next().await {
+                            match $field.get().await {
+                                Ok($field) => {
+                                    tracing::info!($field, concat!($name, " changed"));
+                                    $(let $field = ($map)($field);)?
+                                    if tx.unbounded_send($variant($field)).is_err() {
+                                        return;
+                                    }
+                                }
+                                Err(e) => {
+                                    tracing::error!(error = %e, concat!("Failed to get new ", $name));
+                                }
+                            }
+                        }
+                        tracing::warn!(concat!("Receive ", $name ," stream ended"));
+                    }
+                }
+            };
+        }
+        join!(
+            handle_stream!(
+                on_battery_stream,
+                on_battery,
+                BatteryUpdate::OnBattery,
+                "OnBattery"
+            ),
+            handle_stream!(type_stream, type_, BatteryUpdate::Type, "Type"),
+            handle_stream!(
+                is_present_stream,
+                is_present,
+                BatteryUpdate::IsPresent,
+                "IsPresent"
+            ),
+            handle_stream!(state_stream, state, BatteryUpdate::State, "State"),
+            handle_stream!(
+                percentage_stream,
+                percentage,
+                BatteryUpdate::Percentage,
+                "Percentage"
+            ),
+            handle_stream!(
+                time_to_empty_stream,
+                time_to_empty,
+                BatteryUpdate::TimeToEmpty,
+                "TimeToEmpty",
+                seconds_to_duration
+            ),
+            handle_stream!(
+                time_to_full_stream,
+                time_to_full,
+                BatteryUpdate::TimeToFull,
+                "TimeToFull",
+                seconds_to_duration
+            ),
+            handle_stream!(
+                energy_rate_stream,
+                energy_rate,
+                BatteryUpdate::EnergyRate,
+                "EnergyRate"
+            ),
+        );
+    }
+}
+
+/// UPower reports 0 when the time is unknown.
+fn seconds_to_duration(seconds: i64) -> Option<Duration> {
+    if seconds != 0
+        && let Ok(seconds) = seconds.try_into()
+    {
+        Some(Duration::from_secs(seconds))
+    } else {
+        None
+    }
+}
+
+/// The UPower proxy and the proxy of the device to show, the display device if `device` is unset.
+async fn connect(
+    device: Option<&str>,
+) -> Result<(UpowerProxy<'static>, UpowerDeviceProxy<'static>), WidgetError> {
+    let connection = Connection::system()
+        .await
+        .map_err(|e| upower_error("system bus", e))?;
+    let upower_proxy = UpowerProxy::new(&connection)
+        .await
+        .map_err(|e| upower_error("UPower", e))?;
+    let device_proxy = if let Some(native_path) = device {
+        find_device(&connection, &upower_proxy, native_path).await?
+    } else {
+        UpowerDeviceProxy::new(&connection, "/org/freedesktop/UPower/devices/DisplayDevice")
+            .await
+            .map_err(|e| upower_error("UPower", e))?
+    };
+    Ok((upower_proxy, device_proxy))
+}
+
+fn upower_error(service: &'static str, e: zbus::Error) -> WidgetError {
+    WidgetError::ConnectionFailed {
+        service,
+        message: e.to_string(),
+    }
+}
+
+/// The UPower device with the given native path, e.g. `BAT1` or `hidpp_battery_0`. A missing one
+/// is an error listing the available ones, retried until it shows up, e.g. a wireless mouse that
+/// is turned off.
+async fn find_device(
+    connection: &Connection,
+    upower_proxy: &UpowerProxy<'_>,
+    native_path: &str,
+) -> Result<UpowerDeviceProxy<'static>, WidgetError> {
+    let devices = upower_proxy
+        .enumerate_devices()
+        .await
+        .map_err(|e| upower_error("UPower", e))?;
+    let mut available = Vec::new();
+    for path in devices {
+        let proxy = UpowerDeviceProxy::new(connection, path)
+            .await
+            .map_err(|e| upower_error("UPower", e))?;
+        match proxy.native_path().await {
+            Ok(x) if x == native_path => return Ok(proxy),
+            Ok(x) => available.push(x),
+            Err(e) => {
+                tracing::error!(path = %proxy.inner().path(), error = %e, "Failed to get native path");
+            }
+        }
+    }
+    tracing::warn!(
+        native_path,
+        available = available.join(", "),
+        "No such UPower device"
+    );
+    Err(WidgetError::DeviceNotFound {
+        device: native_path.to_owned(),
+        available,
+    })
+}
+
+/// Sends fixed values and stops, e.g. for `--preview`.
+pub struct FakeBatterySource {
+    pub updates: Vec<BatteryUpdate>,
+}
+
+impl FakeBatterySource {
+    /// A battery discharging at `percentage`, with 3 hours left.
+    pub fn discharging(percentage: f64) -> Self {
+        Self {
+            updates: vec![
+                BatteryUpdate::Type(2),
+                BatteryUpdate::IsPresent(true),
+                BatteryUpdate::OnBattery(true),
+                BatteryUpdate::State(2),
+                BatteryUpdate::Percentage(percentage),
+                BatteryUpdate::TimeToEmpty(Some(Duration::from_secs(3 * 60 * 60))),
+                BatteryUpdate::EnergyRate(8.5),
+                BatteryUpdate::Device(BatteryDevice::Fake {
+                    health: 87.0,
+                    charge_cycles: 120,
+                }),
+            ],
+        }
+    }
+}
+
+impl BatterySource for FakeBatterySource {
+    async fn run(self, tx: UnboundedSender<BatteryUpdate>, _cx: &mut AsyncApp) {
+        for update in self.updates {
+            if tx.unbounded_send(update).is_err() {
+                return;
+            }
+        }
+    }
+}
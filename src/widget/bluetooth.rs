@@ -1,55 +1,387 @@
-use std::collections::HashSet;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    time::Duration,
+};
 
 use bluer::{
     Adapter, AdapterEvent, AdapterProperty, Address, DeviceEvent, DeviceProperty, Session,
 };
 use futures::StreamExt;
-use gpui::{AsyncApp, Context, IntoElement, ParentElement, Render, WeakEntity, Window};
+use gpui::{
+    Animation, AnimationExt, AnyElement, App, AsyncApp, Context, Div, InteractiveElement,
+    IntoElement, MouseButton, MouseDownEvent, ParentElement, Pixels, Render,
+    StatefulInteractiveElement, Styled, Task, WeakEntity, Window, div, prelude::FluentBuilder,
+    pulsating_between, px, rems, size,
+};
 use gpui_tokio::Tokio;
+use serde::{Deserialize, Serialize};
 
-use crate::widget::{Widget, widget_wrapper};
+use crate::{
+    config::ThemeConfig,
+    popup::{Popup, PopupAnchor},
+    util::retry_with_backoff,
+    widget::{
+        Widget, dependency::Dependency, error_element, is_preview, spawn_task, spawn_watched_task,
+        widget_wrapper,
+    },
+};
 
 pub struct Bluetooth {
+    hide_when_off: bool,
+    show_name: bool,
+    show_count: bool,
+    icons: BluetoothIcons,
     error_message: Option<String>,
     powered: Option<bool>,
     discovering: Option<bool>,
     connected_devices: HashSet<Address>,
+    /// Names (aliases) of the paired devices, listed in the popup even when disconnected
+    paired_devices: BTreeMap<Address, String>,
+    connecting: HashSet<Address>,
+    connect_error: Option<String>,
+    adapter: Option<Adapter>,
+    /// The alias of the adapter, for `show_name`
+    alias: Option<String>,
 }
 
 impl Widget for Bluetooth {
-    type Config = ();
+    type Config = BluetoothConfig;
 
-    fn new(cx: &mut Context<Self>, _config: &Self::Config) -> Self {
-        cx.spawn(task).detach();
+    fn dependencies() -> &'static [Dependency] {
+        &[Dependency::SystemDbus("org.bluez")]
+    }
+
+    fn new(cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        if is_preview(cx) {
+            let headphones = Address::new([0x00, 0x1b, 0x66, 0x00, 0x00, 0x01]);
+            let keyboard = Address::new([0x00, 0x1b, 0x66, 0x00, 0x00, 0x02]);
+            return Self {
+                hide_when_off: config.hide_when_off,
+                show_name: config.show_name,
+                show_count: config.show_count,
+                icons: config.icons.clone(),
+                error_message: None,
+                powered: Some(true),
+                discovering: Some(false),
+                connected_devices: HashSet::from([headphones]),
+                paired_devices: BTreeMap::from([
+                    (headphones, "Headphones".to_owned()),
+                    (keyboard, "Keyboard".to_owned()),
+                ]),
+                connecting: HashSet::new(),
+                connect_error: None,
+                adapter: None,
+                alias: Some("preview".to_owned()),
+            };
+        }
+        spawn_watched_task(cx, task).detach();
 
         Self {
+            hide_when_off: config.hide_when_off,
+            show_name: config.show_name,
+            show_count: config.show_count,
+            icons: config.icons.clone(),
             error_message: None,
             powered: None,
             discovering: None,
             connected_devices: HashSet::new(),
+            paired_devices: BTreeMap::new(),
+            connecting: HashSet::new(),
+            connect_error: None,
+            adapter: None,
+            alias: None,
         }
     }
+
+    fn visible(&self) -> bool {
+        self.error_message.is_some() || !(self.hide_when_off && self.powered == Some(false))
+    }
+
+    fn activate(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        // there is no pointer, the popup opens at the start of the bar
+        self.open_devices(px(0.0), window, cx);
+    }
+
+    fn activatable(&self) -> bool {
+        self.adapter.is_some()
+    }
 }
 
-impl Render for Bluetooth {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+#[derive(Deserialize, Serialize, Default, Clone, Debug, PartialEq)]
+pub struct BluetoothConfig {
+    /// Hide the widget while the adapter is powered off
+    #[serde(default)]
+    pub hide_when_off: bool,
+    /// Show the alias of the adapter next to the glyph
+    #[serde(default)]
+    pub show_name: bool,
+    /// Show how many devices are connected next to the glyph, while any are
+    #[serde(default)]
+    pub show_count: bool,
+    #[serde(default)]
+    pub icons: BluetoothIcons,
+}
+
+/// The glyph for each state of the adapter, e.g. to match another icon font.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct BluetoothIcons {
+    /// Powered without connected devices
+    #[serde(default = "default_on_icon")]
+    pub on: String,
+    #[serde(default = "default_connected_icon")]
+    pub connected: String,
+    #[serde(default = "default_discovering_icon")]
+    pub discovering: String,
+    #[serde(default = "default_off_icon")]
+    pub off: String,
+}
+
+impl Default for BluetoothIcons {
+    fn default() -> Self {
+        Self {
+            on: default_on_icon(),
+            connected: default_connected_icon(),
+            discovering: default_discovering_icon(),
+            off: default_off_icon(),
+        }
+    }
+}
+
+fn default_on_icon() -> String {
+    "".to_owned()
+}
+
+fn default_connected_icon() -> String {
+    "".to_owned()
+}
+
+fn default_discovering_icon() -> String {
+    "".to_owned()
+}
+
+fn default_off_icon() -> String {
+    "".to_owned()
+}
+
+/// The devices popup is only made this tall, the rest of the devices scroll.
+const MAX_POPUP_ROWS: usize = 8;
+
+impl Bluetooth {
+    /// List the paired devices in a popup, clicking a disconnected one connects to it.
+    fn open_devices(&self, x: Pixels, window: &mut Window, cx: &mut Context<Self>) {
+        if self.adapter.is_none() {
+            return;
+        }
+        let anchor = PopupAnchor::new(window, cx);
+        // a row for each device, and one for an error. Devices paired later scroll, a popup can't
+        // be resized
+        let rows = (self.paired_devices.len().max(1) + 1).min(MAX_POPUP_ROWS);
+        let height = px(24.0 + 28.0 * rows as f32);
+        let weak = cx.weak_entity();
+        let popup = Popup::open(
+            anchor,
+            x,
+            size(px(280.0), height),
+            cx,
+            move |_, cx| match weak.upgrade() {
+                Some(this) => this.read(cx).devices(weak.clone(), cx),
+                None => div().into_any_element(),
+            },
+        );
+        if let Some(popup) = popup {
+            let this = cx.entity();
+            let _ = popup.update(cx, |_, _, cx| {
+                cx.observe(&this, |_, _, cx| cx.notify()).detach();
+            });
+        }
+    }
+    fn devices(&self, weak: WeakEntity<Self>, cx: &App) -> AnyElement {
+        let theme = cx.global::<ThemeConfig>();
+        let accent = theme.accent();
+        div()
+            .id("bluetooth-devices")
+            .size_full()
+            .overflow_y_scroll()
+            .flex()
+            .flex_col()
+            .gap(rems(0.25))
+            .when(self.paired_devices.is_empty(), |this| {
+                this.child("No paired devices")
+            })
+            .children(self.paired_devices.iter().map(|(&address, name)| {
+                let connected = self.connected_devices.contains(&address);
+                let connecting = self.connecting.contains(&address);
+                let status = if connecting {
+                    div()
+                        .font_family("Material Symbols Rounded")
+                        .child("")
+                        .with_animation(
+                            format!("bluetooth-connecting-{address}"),
+                            Animation::new(Duration::from_secs(1))
+                                .repeat()
+                                .with_easing(pulsating_between(0.4, 1.0)),
+                            |this, delta| this.opacity(delta),
+                        )
+                        .into_any_element()
+                } else if connected {
+                    div()
+                        .font_family("Material Symbols Rounded")
+                        .child("")
+                        .into_any_element()
+                } else {
+                    div().into_any_element()
+                };
+                div()
+                    .id(format!("bluetooth-device-{address}"))
+                    .flex()
+                    .justify_between()
+                    .gap(rems(0.5))
+                    .px(rems(0.25))
+                    .rounded(rems(0.25))
+                    .child(name.clone())
+                    .child(status)
+                    .when(!connected && !connecting, |this| {
+                        this.hover(|style| style.bg(accent.opacity(0.3))).on_click({
+                            let weak = weak.clone();
+                            move |_, _, cx| {
+                                // keep the popup open to show the progress
+                                cx.stop_propagation();
+                                let _ = weak.update(cx, |this, cx| this.connect(address, cx));
+                            }
+                        })
+                    })
+            }))
+            .when_some(self.connect_error.as_ref(), |this, e| {
+                this.child(div().text_color(theme.urgent()).child(error_element(cx, e)))
+            })
+            .into_any_element()
+    }
+    fn icon(&self) -> String {
+        match self.powered {
+            Some(true) => {
+                if self.discovering == Some(true) {
+                    self.icons.discovering.clone()
+                } else if self.connected_devices.len() == 0 {
+                    self.icons.on.clone()
+                } else {
+                    self.icons.connected.clone()
+                }
+            }
+            Some(false) => self.icons.off.clone(),
+            None => "?".to_owned(),
+        }
+    }
+    fn content(&self, cx: &mut Context<Self>) -> Div {
         if let Some(e) = &self.error_message {
-            widget_wrapper().child(e.clone())
+            widget_wrapper(cx).child(error_element(cx, e))
         } else {
-            match self.powered {
-                Some(true) => {
-                    if self.discovering == Some(true) {
-                        widget_wrapper().child("")
-                    } else if self.connected_devices.len() == 0 {
-                        widget_wrapper().child("")
-                    } else {
-                        widget_wrapper().child("")
+            let count = self.connected_devices.len();
+            widget_wrapper(cx)
+                .flex()
+                .gap(rems(0.25))
+                .child(self.icon())
+                .when_some(
+                    self.alias.clone().filter(|_| self.show_name),
+                    |this, alias| this.child(alias),
+                )
+                .when(self.show_count && count > 0, |this| {
+                    this.child(count.to_string())
+                })
+        }
+    }
+    /// Re-read whether the adapter is powered and discovering, e.g. when the icon looks stale.
+    fn refresh(&self, cx: &mut Context<Self>) {
+        let Some(adapter) = self.adapter.clone() else {
+            tracing::info!("No bluetooth adapter yet, nothing to refresh");
+            return;
+        };
+        spawn_task(cx, async move |this, cx| {
+            tracing::info!("Refreshing the adapter");
+            let read = cx.update(|cx| {
+                Tokio::spawn(cx, async move {
+                    (adapter.is_powered().await, adapter.is_discovering().await)
+                })
+            });
+            let (powered, discovering) = match read.await {
+                Ok(x) => x,
+                Err(e) => {
+                    tracing::error!(error = %e, "Failed to refresh the adapter");
+                    return;
+                }
+            };
+            let _ = this.update(cx, |this, cx| {
+                match powered {
+                    Ok(x) => this.powered = Some(x),
+                    Err(e) => {
+                        tracing::error!(error = %e, "Failed to get if default adapter is powered");
                     }
                 }
-                Some(false) => widget_wrapper().child(""),
-                None => widget_wrapper().child("?"),
-            }
+                match discovering {
+                    Ok(x) => this.discovering = Some(x),
+                    Err(e) => {
+                        tracing::error!(error = %e, "Failed to get if the adapter is discovering");
+                    }
+                }
+                cx.notify();
+            });
+        })
+        .detach();
+    }
+    fn connect(&mut self, address: Address, cx: &mut Context<Self>) {
+        let Some(adapter) = self.adapter.clone() else {
+            return;
+        };
+        if !self.connecting.insert(address) {
+            return;
         }
+        self.connect_error = None;
+        cx.notify();
+        spawn_task(cx, async move |this, cx| {
+            tracing::info!(%address, "Connecting to a device");
+            // on the tokio runtime, as entering it here would drop the guards out of order when
+            // connects overlap
+            let result = cx
+                .update(|cx| {
+                    Tokio::spawn(cx, async move { connect_device(&adapter, address).await })
+                })
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|x| x.map_err(|e| e.to_string()));
+            let _ = this.update(cx, |this, cx| {
+                this.connecting.remove(&address);
+                if let Err(e) = result {
+                    tracing::error!(%address, error = %e, "Failed to connect to a device");
+                    let name = this
+                        .paired_devices
+                        .get(&address)
+                        .cloned()
+                        .unwrap_or_else(|| address.to_string());
+                    this.connect_error = Some(format!("Failed to connect to {name}: {e}"));
+                }
+                cx.notify();
+            });
+        })
+        .detach();
+    }
+}
+
+async fn connect_device(adapter: &Adapter, address: Address) -> bluer::Result<()> {
+    adapter.device(address)?.connect().await
+}
+
+impl Render for Bluetooth {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        self.content(cx)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, event: &MouseDownEvent, window, cx| {
+                    this.open_devices(event.position.x, window, cx);
+                }),
+            )
+            .on_mouse_down(
+                MouseButton::Middle,
+                cx.listener(|this, _, _, cx| this.refresh(cx)),
+            )
     }
 }
 
@@ -57,18 +389,40 @@ async fn task(this: WeakEntity<Bluetooth>, cx: &mut AsyncApp) {
     let handle = cx.update(|cx| Tokio::handle(cx));
     let _guard = handle.enter();
 
-    let adapter = match default_adapter().await {
-        Ok(x) => x,
-        Err(e) => {
-            tracing::error!(error = %e, "Failed to get default bluetooth adapter");
-            let _ = this.update(cx, |this, cx| {
+    let Some(adapter) = retry_with_backoff(
+        cx,
+        async |_| default_adapter().await,
+        |cx, e, retry_in| {
+            tracing::error!(error = %e, ?retry_in, "Failed to get default bluetooth adapter");
+            this.update(cx, |this, cx| {
                 this.error_message = Some(format!("Failed to get default bluetooth adapter: {e}"));
                 cx.notify();
-            });
-            return;
-        }
+            })
+            .is_ok()
+        },
+    )
+    .await
+    else {
+        return;
     };
+    let _ = this.update(cx, |this, cx| {
+        this.error_message = None;
+        this.adapter = Some(adapter.clone());
+        cx.notify();
+    });
     tracing::info!(default_adapter_name = adapter.name());
+    match adapter.alias().await {
+        Ok(alias) => {
+            tracing::info!(alias, "Adapter property");
+            let _ = this.update(cx, |this, cx| {
+                this.alias = Some(alias);
+                cx.notify();
+            });
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to get the alias of default adapter");
+        }
+    }
     match adapter.is_powered().await {
         Ok(is_powered) => {
             tracing::info!(is_powered, "Adapter property");
@@ -93,10 +447,14 @@ async fn task(this: WeakEntity<Bluetooth>, cx: &mut AsyncApp) {
             tracing::error!(error = %e, "Failed to get if default adapter is discovering");
         }
     }
+    // dropping a task cancels it, so a device stops being monitored once its entry is removed
+    let mut device_tasks = HashMap::<Address, Task<()>>::new();
     match adapter.device_addresses().await {
         Ok(addresses) => {
             for address in addresses {
-                try_monitor_device(&adapter, address, this.clone(), cx).await;
+                if let Some(task) = try_monitor_device(&adapter, address, this.clone(), cx).await {
+                    device_tasks.insert(address, task);
+                }
             }
         }
         Err(e) => {
@@ -120,11 +478,15 @@ async fn task(this: WeakEntity<Bluetooth>, cx: &mut AsyncApp) {
         tracing::debug!(?event, "Bluetooth event");
         match event {
             AdapterEvent::DeviceAdded(address) => {
-                try_monitor_device(&adapter, address, this.clone(), cx).await;
+                if let Some(task) = try_monitor_device(&adapter, address, this.clone(), cx).await {
+                    device_tasks.insert(address, task);
+                }
             }
             AdapterEvent::DeviceRemoved(address) => {
+                device_tasks.remove(&address);
                 let _ = this.update(cx, |this, cx| {
                     let was_connected = this.connected_devices.remove(&address);
+                    this.paired_devices.remove(&address);
                     tracing::info!(%address, was_connected, "Removed a device");
                     cx.notify();
                 });
@@ -136,6 +498,13 @@ async fn task(this: WeakEntity<Bluetooth>, cx: &mut AsyncApp) {
                     cx.notify();
                 });
             }
+            AdapterEvent::PropertyChanged(AdapterProperty::Alias(alias)) => {
+                tracing::info!(alias, "Adapter property changed");
+                let _ = this.update(cx, |this, cx| {
+                    this.alias = Some(alias);
+                    cx.notify();
+                });
+            }
             AdapterEvent::PropertyChanged(AdapterProperty::Discovering(discovering)) => {
                 tracing::info!(discovering, "Adapter property changed");
                 let _ = this.update(cx, |this, cx| {
@@ -154,12 +523,12 @@ async fn try_monitor_device(
     address: Address,
     entity: WeakEntity<Bluetooth>,
     cx: &mut AsyncApp,
-) {
+) -> Option<Task<()>> {
     let device = match adapter.device(address) {
         Ok(x) => x,
         Err(e) => {
             tracing::error!(%address, error = %e, "Device added, but failed to get the device at that address");
-            return;
+            return None;
         }
     };
     match device.is_connected().await {
@@ -176,15 +545,29 @@ async fn try_monitor_device(
             tracing::error!(%address, name = ?device.name().await, error = %e, "Failed to get if device is connected");
         }
     }
+    // fetched here, bluer needs the tokio runtime of this task
+    let mut alias = device.alias().await.unwrap_or_else(|_| address.to_string());
+    match device.is_paired().await {
+        Ok(true) => {
+            let _ = entity.update(cx, |this, cx| {
+                this.paired_devices.insert(address, alias.clone());
+                cx.notify();
+            });
+        }
+        Ok(false) => (),
+        Err(e) => {
+            tracing::error!(%address, name = ?device.name().await, error = %e, "Failed to get if device is paired");
+        }
+    }
     let mut events = match device.events().await {
         Ok(x) => x,
         Err(e) => {
             tracing::error!(%address, name = ?device.name().await, error = %e, "Failed to get device event stream");
-            return;
+            return None;
         }
     };
     tracing::info!(%address, name = ?device.name().await, "Monitoring a device");
-    cx.spawn(async move |cx| {
+    Some(cx.spawn(async move |cx| {
         while let Some(event) = events.next().await {
             match event {
                 DeviceEvent::PropertyChanged(
@@ -200,14 +583,69 @@ async fn try_monitor_device(
                         cx.notify();
                     });
                 }
+                DeviceEvent::PropertyChanged(DeviceProperty::Paired(paired)) => {
+                    let _ = entity.update(cx, |this, cx| {
+                        tracing::info!(%address, paired, "Device property changed");
+                        if paired {
+                            this.paired_devices.insert(address, alias.clone());
+                        } else {
+                            this.paired_devices.remove(&address);
+                        }
+                        cx.notify();
+                    });
+                }
+                DeviceEvent::PropertyChanged(DeviceProperty::Alias(new_alias)) => {
+                    alias = new_alias;
+                    let _ = entity.update(cx, |this, cx| {
+                        if let Some(name) = this.paired_devices.get_mut(&address) {
+                            name.clone_from(&alias);
+                            cx.notify();
+                        }
+                    });
+                }
                 _ => (),
             }
         }
-    })
-    .detach();
+    }))
 }
 
 async fn default_adapter() -> bluer::Result<Adapter> {
     let session = Session::new().await?;
     session.default_adapter().await
 }
+
+#[cfg(test)]
+mod tests {
+    use gpui::TestAppContext;
+
+    use super::*;
+    use crate::widget::{draw_widget, preview_widget};
+
+    #[gpui::test]
+    fn render_states(cx: &mut TestAppContext) {
+        let config = BluetoothConfig {
+            hide_when_off: true,
+            show_count: true,
+            ..BluetoothConfig::default()
+        };
+        let bluetooth = preview_widget::<Bluetooth>(cx, &config);
+        bluetooth.read_with(cx, |bluetooth, _| {
+            assert!(bluetooth.visible());
+            assert_eq!(bluetooth.icon(), config.icons.connected);
+        });
+
+        bluetooth.update(cx, |bluetooth, _| bluetooth.powered = Some(false));
+        draw_widget(cx, &bluetooth);
+        bluetooth.read_with(cx, |bluetooth, _| {
+            assert!(!bluetooth.visible());
+            assert_eq!(bluetooth.icon(), config.icons.off);
+        });
+
+        // shown even when hidden otherwise
+        bluetooth.update(cx, |bluetooth, _| {
+            bluetooth.error_message = Some("Failed to get default bluetooth adapter".to_owned());
+        });
+        draw_widget(cx, &bluetooth);
+        assert!(bluetooth.read_with(cx, |bluetooth, _| bluetooth.visible()));
+    }
+}
@@ -0,0 +1,371 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    pin::pin,
+    process,
+};
+
+use futures::{
+    StreamExt,
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    future::{self, Either},
+};
+use gpui::{
+    AsyncApp, Context, InteractiveElement, IntoElement, ParentElement, Render, ScrollWheelEvent,
+    Styled, WeakEntity, Window, div, px, rems,
+};
+use serde::{Deserialize, Serialize};
+use zbus::Connection;
+
+use crate::widget::{
+    TaskEnd, Widget, error_element, idle::SessionProxy, poll_interval, scroll_delta,
+    spawn_watched_task, widget_wrapper,
+};
+
+const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+
+/// Screen backlight level from sysfs, scroll to adjust with the backend from the config.
+pub struct Brightness {
+    error_message: Option<String>,
+    brightness: Option<u32>,
+    max_brightness: Option<u32>,
+    /// Hide the widget when there is no backlight, e.g. on desktops
+    unavailable: bool,
+    adjust_tx: UnboundedSender<i32>,
+}
+
+impl Widget for Brightness {
+    type Config = BrightnessConfig;
+
+    fn new(cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        let (adjust_tx, adjust_rx) = mpsc::unbounded();
+        let config = config.clone();
+        spawn_watched_task(cx, async move |this, cx| {
+            task(this.clone(), cx, config, adjust_rx).await;
+            // there is nothing to watch without a backlight
+            match this.read_with(cx, |this, _| this.unavailable) {
+                Ok(true) => TaskEnd::Done,
+                _ => TaskEnd::Unexpected,
+            }
+        })
+        .detach();
+
+        Self {
+            error_message: None,
+            brightness: None,
+            max_brightness: None,
+            unavailable: false,
+            adjust_tx,
+        }
+    }
+
+    fn visible(&self) -> bool {
+        !self.unavailable
+    }
+}
+
+impl Render for Brightness {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if let Some(e) = &self.error_message {
+            return widget_wrapper(cx)
+                .child(error_element(cx, e))
+                .into_any_element();
+        }
+        let percentage = match (self.brightness, self.max_brightness) {
+            (Some(brightness), Some(max)) if max > 0 => format!("{}%", brightness * 100 / max),
+            _ => "?".to_owned(),
+        };
+        widget_wrapper(cx)
+            .on_scroll_wheel({
+                let adjust_tx = self.adjust_tx.clone();
+                move |event: &ScrollWheelEvent, window, cx| {
+                    let delta = scroll_delta(event, window, cx);
+                    let step = if delta > px(0.0) {
+                        1
+                    } else if delta < px(0.0) {
+                        -1
+                    } else {
+                        return;
+                    };
+                    if let Err(e) = adjust_tx.unbounded_send(step) {
+                        tracing::error!(error = %e, "Failed to send brightness adjustment");
+                    }
+                }
+            })
+            .flex()
+            .gap(rems(0.25))
+            .child(div().font_family("Material Symbols Rounded").child(""))
+            .child(percentage)
+            .into_any_element()
+    }
+}
+
+/// How the brightness is set, it's always read from sysfs.
+#[derive(Deserialize, Serialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BrightnessBackend {
+    /// The first of the others that is usable, in order
+    #[default]
+    Auto,
+    /// Write to `/sys/class/backlight`, which usually needs a udev rule
+    Sysfs,
+    /// `SetBrightness` of the logind session
+    Logind,
+    /// Run `brightnessctl`
+    Brightnessctl,
+}
+
+/// A backend that was checked to be usable.
+enum Setter {
+    Sysfs,
+    Logind(SessionProxy<'static>),
+    Brightnessctl,
+}
+
+impl Setter {
+    /// Pick the first usable backend of `backend`, `None` if none is.
+    async fn resolve(backend: BrightnessBackend, device: &Path, cx: &AsyncApp) -> Option<Self> {
+        if matches!(backend, BrightnessBackend::Auto | BrightnessBackend::Sysfs) {
+            if fs::OpenOptions::new()
+                .write(true)
+                .open(device.join("brightness"))
+                .is_ok()
+            {
+                return Some(Self::Sysfs);
+            }
+            tracing::info!(device = %device.display(), "The backlight is not writable");
+        }
+        if matches!(backend, BrightnessBackend::Auto | BrightnessBackend::Logind) {
+            match logind_session().await {
+                Ok(session) => return Some(Self::Logind(session)),
+                Err(e) => tracing::info!(error = %e, "No logind session to set the brightness"),
+            }
+        }
+        if matches!(
+            backend,
+            BrightnessBackend::Auto | BrightnessBackend::Brightnessctl
+        ) {
+            let output = cx
+                .background_spawn(async {
+                    process::Command::new("brightnessctl")
+                        .arg("--version")
+                        .output()
+                })
+                .await;
+            match output {
+                Ok(output) if output.status.success() => return Some(Self::Brightnessctl),
+                Ok(output) => {
+                    tracing::info!(status = %output.status, "brightnessctl is not usable");
+                }
+                Err(e) => tracing::info!(error = %e, "brightnessctl is not usable"),
+            }
+        }
+        None
+    }
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::Sysfs => "sysfs",
+            Self::Logind(_) => "logind",
+            Self::Brightnessctl => "brightnessctl",
+        }
+    }
+    /// The sysfs write and brightnessctl can block, so they run in the background.
+    async fn set(&self, device: &Path, value: u32, cx: &AsyncApp) -> Result<(), String> {
+        let name = device_name(device);
+        match self {
+            Self::Sysfs => {
+                let path = device.join("brightness");
+                cx.background_spawn(async move { fs::write(path, value.to_string()) })
+                    .await
+                    .map_err(|e| format!("error while writing the brightness: {e}"))
+            }
+            Self::Logind(session) => session
+                .set_brightness("backlight", &name, value)
+                .await
+                .map_err(|e| format!("error while setting the brightness with logind: {e}")),
+            Self::Brightnessctl => {
+                let output = cx
+                    .background_spawn(async move {
+                        process::Command::new("brightnessctl")
+                            .arg(format!("--device={name}"))
+                            .args(["set", &value.to_string()])
+                            .output()
+                    })
+                    .await
+                    .map_err(|e| format!("error while running brightnessctl: {e}"))?;
+                if output.status.success() {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "brightnessctl failed ({}): {}",
+                        output.status,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ))
+                }
+            }
+        }
+    }
+}
+
+async fn logind_session() -> zbus::Result<SessionProxy<'static>> {
+    let connection = Connection::system().await?;
+    let session = SessionProxy::new(&connection).await?;
+    // fails without a session
+    session.idle_hint().await?;
+    Ok(session)
+}
+
+fn device_name(device: &Path) -> String {
+    device
+        .file_name()
+        .map(|x| x.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// The configured device, or the first one in [`BACKLIGHT_DIR`].
+fn find_device(name: Option<&str>) -> Result<Option<PathBuf>, String> {
+    if let Some(name) = name {
+        return Ok(Some(Path::new(BACKLIGHT_DIR).join(name)));
+    }
+    let entries = match fs::read_dir(BACKLIGHT_DIR) {
+        Ok(x) => x,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("error while reading {BACKLIGHT_DIR}: {e}")),
+    };
+    let mut devices = entries
+        .filter_map(|x| x.ok().map(|x| x.path()))
+        .collect::<Vec<_>>();
+    devices.sort();
+    Ok(devices.into_iter().next())
+}
+
+fn read_value(device: &Path, file: &str) -> Result<u32, String> {
+    let path = device.join(file);
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("error while reading {}: {e}", path.display()))?;
+    content.trim().parse().map_err(|e| {
+        format!(
+            "error while parsing `{}` from {}: {e}",
+            content.trim(),
+            path.display()
+        )
+    })
+}
+
+async fn task(
+    this: WeakEntity<Brightness>,
+    cx: &mut AsyncApp,
+    config: BrightnessConfig,
+    mut adjust_rx: UnboundedReceiver<i32>,
+) {
+    let device = match find_device(config.device.as_deref()) {
+        Ok(Some(x)) => x,
+        Ok(None) => {
+            tracing::info!("No backlight, hiding the widget");
+            let _ = this.update(cx, |this, cx| {
+                this.unavailable = true;
+                cx.notify();
+            });
+            return;
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to find a backlight");
+            let _ = this.update(cx, |this, cx| {
+                this.error_message = Some(e);
+                cx.notify();
+            });
+            return;
+        }
+    };
+    let max_brightness = match read_value(&device, "max_brightness") {
+        Ok(x) => x,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to get max brightness");
+            let _ = this.update(cx, |this, cx| {
+                this.error_message = Some(e);
+                cx.notify();
+            });
+            return;
+        }
+    };
+    let setter = Setter::resolve(config.backend, &device, cx).await;
+    match &setter {
+        Some(setter) => {
+            tracing::info!(device = %device.display(), backend = setter.name(), "Selected brightness backend");
+        }
+        None => {
+            tracing::warn!(device = %device.display(), backend = ?config.backend, "No usable brightness backend, scrolling won't change the brightness");
+        }
+    }
+    let Ok(interval) = this.read_with(cx, |_, cx| poll_interval(cx, config.poll_interval)) else {
+        return;
+    };
+    let step = (max_brightness * config.step / 100).max(1);
+    let mut brightness = None;
+    loop {
+        match read_value(&device, "brightness") {
+            Ok(value) => {
+                brightness = Some(value);
+                let Ok(()) = this.update(cx, |this, cx| {
+                    this.brightness = Some(value);
+                    this.max_brightness = Some(max_brightness);
+                    this.error_message = None;
+                    cx.notify();
+                }) else {
+                    return;
+                };
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to get brightness");
+            }
+        }
+        let timer = pin!(cx.background_executor().timer(interval));
+        let direction = match future::select(timer, adjust_rx.next()).await {
+            Either::Left(_) => continue,
+            Either::Right((Some(direction), _)) => direction,
+            Either::Right((None, _)) => return,
+        };
+        let (Some(setter), Some(current)) = (&setter, brightness) else {
+            continue;
+        };
+        let value = current
+            .saturating_add_signed(direction * step as i32)
+            .min(max_brightness);
+        if value == current {
+            continue;
+        }
+        if let Err(e) = setter.set(&device, value, cx).await {
+            tracing::error!(value, backend = setter.name(), error = %e, "Failed to set brightness");
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct BrightnessConfig {
+    #[serde(default)]
+    pub backend: BrightnessBackend,
+    /// The name in `/sys/class/backlight`, e.g. `"intel_backlight"`, the first one if unset
+    #[serde(default)]
+    pub device: Option<String>,
+    /// Percentage of the maximum brightness changed by each scroll step
+    #[serde(default = "default_step")]
+    pub step: u32,
+    /// Seconds between reads of the brightness, defaults to `bar.poll_interval`
+    #[serde(default)]
+    pub poll_interval: Option<f64>,
+}
+
+impl Default for BrightnessConfig {
+    fn default() -> Self {
+        Self {
+            backend: BrightnessBackend::default(),
+            device: None,
+            step: default_step(),
+            poll_interval: None,
+        }
+    }
+}
+
+fn default_step() -> u32 {
+    5
+}
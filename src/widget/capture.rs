@@ -0,0 +1,215 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    rc::Rc,
+    thread,
+};
+
+use futures::{
+    StreamExt,
+    channel::mpsc::{self, UnboundedSender},
+};
+use gpui::{AsyncApp, WeakEntity};
+use pipewire::{
+    context::ContextRc,
+    main_loop::MainLoopRc,
+    node::{Node, NodeListener, NodeState},
+    types::ObjectType,
+};
+
+/// Whether any watched node is running, or an error that stops the watching.
+pub type CaptureUpdate = Result<bool, String>;
+
+/// Which PipeWire nodes count as capturing.
+#[derive(Clone, Copy)]
+pub struct CaptureFilter {
+    /// The nodes to watch, they count while running
+    pub media_classes: &'static [&'static str],
+    /// If set, a watched node only counts while it's linked from a node with one of these, e.g. a
+    /// stream recording a microphone but not one recording the monitor of a speaker
+    pub sources: Option<&'static [&'static str]>,
+}
+
+struct WatchedNode {
+    _node: Node,
+    _listener: NodeListener,
+    running: bool,
+}
+
+/// The output and input node of a link.
+struct LinkedNodes {
+    output: u32,
+    input: u32,
+}
+
+/// Run [`watch_running_nodes`] on its own thread and `apply` its updates to `this`, until it stops.
+pub async fn capture_task<W: 'static>(
+    this: WeakEntity<W>,
+    cx: &mut AsyncApp,
+    filter: CaptureFilter,
+    mut apply: impl FnMut(&mut W, CaptureUpdate),
+) {
+    let (tx, mut rx) = mpsc::unbounded();
+    thread::spawn(move || watch_running_nodes(filter, tx));
+    while let Some(update) = rx.next().await {
+        let _ = this.update(cx, |this, cx| {
+            apply(this, update);
+            cx.notify();
+        });
+    }
+    tracing::warn!("No more update from pipewire");
+}
+
+/// Watch the PipeWire nodes matching `filter`, sending whether any of them is running (i.e.
+/// something is capturing from it) every time that changes. Blocks on the PipeWire main loop, so
+/// run it on its own thread.
+fn watch_running_nodes(filter: CaptureFilter, tx: UnboundedSender<CaptureUpdate>) {
+    let main_loop = match MainLoopRc::new(None) {
+        Ok(x) => x,
+        Err(e) => return send_error(&tx, "Failed to get PipeWire main loop", e),
+    };
+    let context = match ContextRc::new(&main_loop, None) {
+        Ok(x) => x,
+        Err(e) => return send_error(&tx, "Failed to get PipeWire context", e),
+    };
+    let core = match context.connect_rc(None) {
+        Ok(x) => x,
+        Err(e) => return send_error(&tx, "Failed to get PipeWire core", e),
+    };
+    let registry = match core.get_registry_rc() {
+        Ok(x) => x,
+        Err(e) => return send_error(&tx, "Failed to get PipeWire registry", e),
+    };
+
+    let nodes = Rc::new(RefCell::new(HashMap::<u32, WatchedNode>::new()));
+    // only filled with `filter.sources`
+    let sources = Rc::new(RefCell::new(HashSet::<u32>::new()));
+    let links = Rc::new(RefCell::new(HashMap::<u32, LinkedNodes>::new()));
+    let update: Rc<dyn Fn()> = {
+        let nodes = nodes.clone();
+        let sources = sources.clone();
+        let links = links.clone();
+        let main_loop = main_loop.clone();
+        let last = Cell::new(None);
+        Rc::new(move || {
+            let sources = sources.borrow();
+            let links = links.borrow();
+            let from_source = |id: &u32| {
+                filter.sources.is_none()
+                    || links
+                        .values()
+                        .any(|link| link.input == *id && sources.contains(&link.output))
+            };
+            let running = nodes
+                .borrow()
+                .iter()
+                .any(|(id, node)| node.running && from_source(id));
+            if last.replace(Some(running)) != Some(running)
+                && let Err(e) = tx.unbounded_send(Ok(running))
+            {
+                tracing::warn!(error = %e, "Failed to send update to ui thread");
+                main_loop.quit();
+            }
+        })
+    };
+
+    let _registry_listener = registry
+        .add_listener_local()
+        .global({
+            let registry = registry.clone();
+            let nodes = nodes.clone();
+            let sources = sources.clone();
+            let links = links.clone();
+            let update = update.clone();
+            move |global| {
+                let Some(props) = global.props else {
+                    return;
+                };
+                if global.type_ == ObjectType::Link && filter.sources.is_some() {
+                    let node_id = |key: &str| props.get(key).and_then(|x: &str| x.parse().ok());
+                    let (Some(output), Some(input)) =
+                        (node_id("link.output.node"), node_id("link.input.node"))
+                    else {
+                        tracing::warn!(id = global.id, "Got a link without its nodes");
+                        return;
+                    };
+                    links
+                        .borrow_mut()
+                        .insert(global.id, LinkedNodes { output, input });
+                    update();
+                    return;
+                }
+                if global.type_ != ObjectType::Node {
+                    return;
+                }
+                let Some(media_class) = props.get("media.class") else {
+                    return;
+                };
+                if filter
+                    .sources
+                    .is_some_and(|classes| classes.contains(&media_class))
+                {
+                    tracing::info!(id = global.id, media_class, "Watching source");
+                    sources.borrow_mut().insert(global.id);
+                    update();
+                    return;
+                }
+                if !filter.media_classes.contains(&media_class) {
+                    return;
+                }
+                let node = match registry.bind::<Node, _>(global) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        tracing::error!(error = %e, "Got a node object but failed to convert it to a real node");
+                        return;
+                    }
+                };
+                let id = global.id;
+                tracing::info!(id, media_class, "Watching node");
+                let listener = node
+                    .add_listener_local()
+                    .info({
+                        let nodes = nodes.clone();
+                        let update = update.clone();
+                        move |info| {
+                            let running = matches!(info.state(), NodeState::Running);
+                            if let Some(node) = nodes.borrow_mut().get_mut(&id) {
+                                node.running = running;
+                            }
+                            update();
+                        }
+                    })
+                    .register();
+                nodes.borrow_mut().insert(
+                    id,
+                    WatchedNode {
+                        _node: node,
+                        _listener: listener,
+                        running: false,
+                    },
+                );
+            }
+        })
+        .global_remove(move |id| {
+            if nodes.borrow_mut().remove(&id).is_some() {
+                tracing::info!(id, "Watched node removed");
+                update();
+            } else if sources.borrow_mut().remove(&id) || links.borrow_mut().remove(&id).is_some()
+            {
+                update();
+            }
+        })
+        .register();
+
+    main_loop.run();
+
+    tracing::warn!("pipewire main loop end");
+}
+
+fn send_error(tx: &UnboundedSender<CaptureUpdate>, message: &str, e: impl Display) {
+    tracing::error!(error = %e, "{message}");
+    if let Err(e) = tx.unbounded_send(Err(format!("{message}: {e}"))) {
+        tracing::error!(error = %e, "Failed to send update to ui thread");
+    }
+}
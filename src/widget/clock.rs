@@ -1,21 +1,55 @@
-use std::time::Duration;
-
 use gpui::{
-    Context, Div, IntoElement, ParentElement, PathBuilder, PathStyle, Render, StrokeOptions,
-    Styled, Window, black, canvas, div, point, px, rems, white,
+    AsyncApp, Context, Div, InteractiveElement, IntoElement, MouseButton, MouseDownEvent,
+    ParentElement, PathBuilder, PathStyle, Render, StrokeOptions, Styled, WeakEntity, Window,
+    black, canvas, div, point, prelude::FluentBuilder, px, rems, white,
 };
 use lyon::path::LineCap;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use time::{
-    OffsetDateTime, Time,
+    Duration, OffsetDateTime,
     error::InvalidFormatDescription,
     format_description::{self, OwnedFormatItem},
 };
 
-use crate::widget::{Widget, widget_wrapper};
+use crate::widget::{
+    Widget, copy_to_clipboard, error_element, is_preview, spawn_watched_task, widget_wrapper,
+};
+
+const MIN_WAIT: std::time::Duration = std::time::Duration::from_millis(10);
+const MAX_WAIT: std::time::Duration = std::time::Duration::from_secs(5);
+/// How late a tick has to be to log it
+const LATE: Duration = Duration::seconds(2);
 
 pub struct Clock {
     format_description: Result<OwnedFormatItem, InvalidFormatDescription>,
+    secondary_format_description: Option<Result<OwnedFormatItem, InvalidFormatDescription>>,
+    secondary_gap: f32,
+    blinking_colon: bool,
+    mode: ClockMode,
+    copy_on_click: bool,
+    copied: bool,
+    /// Shown instead of the current time in `--preview`
+    preview_time: Option<OffsetDateTime>,
+}
+
+/// What the clock shows, cycled by clicking it. Only for this session, it starts as `Both`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum ClockMode {
+    /// The analog face and the text
+    #[default]
+    Both,
+    Text,
+    Analog,
+}
+
+impl ClockMode {
+    const fn next(self) -> Self {
+        match self {
+            Self::Both => Self::Text,
+            Self::Text => Self::Analog,
+            Self::Analog => Self::Both,
+        }
+    }
 }
 
 impl Widget for Clock {
@@ -23,56 +57,173 @@ impl Widget for Clock {
 
     fn new(cx: &mut Context<Self>, config: &Self::Config) -> Self {
         let format_description = format_description::parse_owned::<2>(&config.format);
-        if format_description.is_ok() {
-            cx.spawn(async move |this, cx| {
-                loop {
-                    let _ = this.update(cx, |_, cx| cx.notify());
-                    let now = OffsetDateTime::now_local().unwrap();
-                    let next = Time::from_hms(now.time().hour(), now.time().minute(), 0).unwrap()
-                        + Duration::from_mins(1);
-                    cx.background_executor()
-                        .timer(now.time().duration_until(next).unsigned_abs())
-                        .await;
-                }
+        let secondary_format_description = config
+            .secondary_format
+            .as_ref()
+            .map(|x| format_description::parse_owned::<2>(x));
+        let blinking_colon = config.blinking_colon;
+        // 2024-01-01 10:09, like the clocks in ads
+        let preview_time = is_preview(cx).then(|| {
+            OffsetDateTime::UNIX_EPOCH + Duration::days(19_723) + Duration::minutes(10 * 60 + 9)
+        });
+        if preview_time.is_none() && (format_description.is_ok() || blinking_colon) {
+            spawn_watched_task(cx, async move |this, cx| {
+                tick(this, cx, blinking_colon).await
             })
             .detach();
         }
 
-        Self { format_description }
+        Self {
+            format_description,
+            secondary_format_description,
+            secondary_gap: config.secondary_gap,
+            blinking_colon,
+            mode: ClockMode::default(),
+            copy_on_click: config.copy_on_click,
+            copied: false,
+            preview_time,
+        }
+    }
+
+    fn activate(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.click(cx);
+    }
+
+    fn activatable(&self) -> bool {
+        true
     }
 }
 
 impl Render for Clock {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
-        let format_description = match &self.format_description {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        self.content(cx).on_mouse_down(
+            MouseButton::Left,
+            cx.listener(|this, _: &MouseDownEvent, _, cx| this.click(cx)),
+        )
+    }
+}
+
+impl Clock {
+    /// Switch the mode, or copy the time with `copy_on_click`.
+    fn click(&mut self, cx: &mut Context<Self>) {
+        if !self.copy_on_click {
+            self.mode = self.mode.next();
+            cx.notify();
+        } else if let Some(text) = self.text() {
+            copy_to_clipboard(text, self, |this| &mut this.copied, cx);
+        }
+    }
+    /// The time as shown, with the secondary format after it
+    fn text(&self) -> Option<String> {
+        let (text, secondary) = self.time_text(self.now().ok()?).ok()?;
+        Some(match secondary {
+            Some(secondary) => format!("{text} {secondary}"),
+            None => text,
+        })
+    }
+    /// The time and the secondary time, for both showing and copying them. Only `HH:MM` with
+    /// `blinking_colon`.
+    fn time_text(&self, time: OffsetDateTime) -> Result<(String, Option<String>), String> {
+        if self.blinking_colon {
+            return Ok((format!("{:02}:{:02}", time.hour(), time.minute()), None));
+        }
+        let format_description = self
+            .format_description
+            .as_ref()
+            .map_err(|e| format!("Error while parsing time format description: {e}"))?;
+        let secondary_format_description = self
+            .secondary_format_description
+            .as_ref()
+            .map(|x| {
+                x.as_ref().map_err(|e| {
+                    format!("Error while parsing secondary time format description: {e}")
+                })
+            })
+            .transpose()?;
+        current_time(time, format_description, secondary_format_description)
+    }
+    fn now(&self) -> Result<OffsetDateTime, String> {
+        match self.preview_time {
+            Some(time) => Ok(time),
+            None => OffsetDateTime::now_local()
+                .map_err(|e| format!("Error while getting local time: {e}")),
+        }
+    }
+    fn content(&self, cx: &mut Context<Self>) -> Div {
+        if self.copied {
+            return widget_wrapper(cx).child("Copied");
+        }
+        let time = match self.now() {
+            Ok(x) => x,
+            Err(e) => {
+                return widget_wrapper(cx).child(error_element(cx, &e));
+            }
+        };
+        let (formatted_time, secondary) = match self.time_text(time) {
             Ok(x) => x,
             Err(e) => {
-                return widget_wrapper()
-                    .child(format!("Error while parsing time format description: {e}"));
+                return widget_wrapper(cx).child(error_element(cx, &e));
             }
         };
-        match current_time(format_description) {
-            Ok((clock, formatted_time)) => widget_wrapper()
+        let text = if self.blinking_colon
+            && let Some((hour, minute)) = formatted_time.split_once(':')
+        {
+            div()
                 .flex()
-                .items_center()
-                .gap(rems(0.25))
-                .child(clock)
-                .child(formatted_time),
-            Err(e) => widget_wrapper().child(e),
-        }
+                .child(hour.to_owned())
+                .child(
+                    div()
+                        .child(":")
+                        .when(time.second() % 2 == 1, |this| this.opacity(0.0)),
+                )
+                .child(minute.to_owned())
+        } else {
+            div()
+                .flex()
+                .gap(rems(self.secondary_gap))
+                .child(formatted_time)
+                .when_some(secondary, |this, secondary| {
+                    this.child(div().opacity(0.6).child(secondary))
+                })
+        };
+        widget_wrapper(cx)
+            .flex()
+            .items_center()
+            .gap(rems(0.25))
+            .when(self.mode != ClockMode::Text, |this| {
+                this.child(analog_face(time))
+            })
+            .when(self.mode != ClockMode::Analog, |this| this.child(text))
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct ClockConfig {
     #[serde(default = "default_format_string")]
     format: String,
+    /// Shown dimmed after `format`, e.g. the date with the time in `format`
+    #[serde(default)]
+    secondary_format: Option<String>,
+    /// Gap between `format` and `secondary_format`, in rems
+    #[serde(default = "default_secondary_gap")]
+    secondary_gap: f32,
+    /// Show only `HH:MM` with the colon blinking every second, instead of `format`
+    #[serde(default)]
+    blinking_colon: bool,
+    /// Copy the formatted time to the clipboard on click, instead of switching between the analog
+    /// face and the text
+    #[serde(default)]
+    copy_on_click: bool,
 }
 
 impl Default for ClockConfig {
     fn default() -> Self {
         Self {
             format: default_format_string(),
+            secondary_format: None,
+            secondary_gap: default_secondary_gap(),
+            blinking_colon: false,
+            copy_on_click: false,
         }
     }
 }
@@ -81,11 +232,57 @@ fn default_format_string() -> String {
     "[month padding:none repr:numerical]/[day padding:none] [weekday repr:short] [hour padding:none repr:12]:[minute padding:zero] [period case:upper]".to_owned()
 }
 
-// TODO: maybe we should use icu4x for localized formatting?
-fn current_time(format_description: &OwnedFormatItem) -> Result<(Div, String), String> {
-    let time =
-        OffsetDateTime::now_local().map_err(|e| format!("Error while getting local time: {e}"))?;
-    let clock = div().relative().size_4().rounded_full().bg(white()).child(
+/// Notify the clock on each tick. The timer is monotonic and stops during a suspend, so this
+/// wakes up at least every [`MAX_WAIT`] to notice that the wall clock jumped.
+async fn tick(this: WeakEntity<Clock>, cx: &mut AsyncApp, every_second: bool) {
+    let mut next: Option<OffsetDateTime> = None;
+    let mut warned = false;
+    loop {
+        // the ticks are on whole minutes in UTC too, the clock shows the error itself
+        let now = OffsetDateTime::now_local().unwrap_or_else(|e| {
+            if !warned {
+                tracing::warn!(error = %e, "Failed to get local time, ticking in UTC");
+                warned = true;
+            }
+            OffsetDateTime::now_utc()
+        });
+        let tick_at = match next {
+            Some(next) if now < next => next,
+            _ => {
+                if let Some(next) = next
+                    && now - next > LATE
+                {
+                    tracing::info!(late = %(now - next), "Clock tick is late, e.g. after a suspend");
+                }
+                if this.update(cx, |_, cx| cx.notify()).is_err() {
+                    return;
+                }
+                *next.insert(next_tick(now, every_second))
+            }
+        };
+        // never zero, so waking up a bit early can't spin
+        let wait = (tick_at - now).unsigned_abs().clamp(MIN_WAIT, MAX_WAIT);
+        cx.background_executor().timer(wait).await;
+    }
+}
+
+/// The start of the next second, or of the next minute unless `every_second`. This is on the next
+/// day after 23:59, as it's computed on the whole date and time.
+fn next_tick(now: OffsetDateTime, every_second: bool) -> OffsetDateTime {
+    let second = now - Duration::nanoseconds(now.nanosecond().into());
+    if every_second {
+        second + Duration::SECOND
+    } else {
+        second - Duration::seconds(now.second().into()) + Duration::MINUTE
+    }
+}
+
+fn default_secondary_gap() -> f32 {
+    0.5
+}
+
+fn analog_face(time: OffsetDateTime) -> Div {
+    div().relative().size_4().rounded_full().bg(white()).child(
         canvas(
             |_, _, _| (),
             move |bounds, _, window, _| {
@@ -121,10 +318,112 @@ fn current_time(format_description: &OwnedFormatItem) -> Result<(Div, String), S
             },
         )
         .size_full(),
-    );
+    )
+}
+
+// TODO: maybe we should use icu4x for localized formatting?
+fn current_time(
+    time: OffsetDateTime,
+    format_description: &OwnedFormatItem,
+    secondary_format_description: Option<&OwnedFormatItem>,
+) -> Result<(String, Option<String>), String> {
     let formatted_time = time
         .format(format_description)
         .map_err(|e| format!("Error while formatting time `{time}`: {e}"))?;
+    let secondary = secondary_format_description
+        .map(|x| time.format(x))
+        .transpose()
+        .map_err(|e| format!("Error while formatting time `{time}`: {e}"))?;
 
-    Ok((clock, formatted_time))
+    Ok((formatted_time, secondary))
+}
+
+#[cfg(test)]
+mod tests {
+    use gpui::TestAppContext;
+    use time::{Date, Month};
+
+    use super::*;
+    use crate::widget::{draw_widget, preview_widget};
+
+    fn at(day: u8, hour: u8, minute: u8, second: u8, nanosecond: u32) -> OffsetDateTime {
+        let Ok(date) = Date::from_calendar_date(2024, Month::December, day) else {
+            panic!("invalid date");
+        };
+        let Ok(time) = date.with_hms_nano(hour, minute, second, nanosecond) else {
+            panic!("invalid time");
+        };
+        time.assume_utc()
+    }
+
+    #[test]
+    fn tick_past_midnight() {
+        let now = at(30, 23, 59, 59, 900_000_000);
+        assert_eq!(next_tick(now, true), at(31, 0, 0, 0, 0));
+        assert_eq!(next_tick(now, false), at(31, 0, 0, 0, 0));
+
+        // into the next year
+        let Ok(new_year) = Date::from_calendar_date(2025, Month::January, 1) else {
+            panic!("invalid date");
+        };
+        let new_year = new_year.midnight().assume_utc();
+        assert_eq!(next_tick(at(31, 23, 59, 59, 900_000_000), true), new_year);
+        assert_eq!(next_tick(at(31, 23, 59, 59, 900_000_000), false), new_year);
+    }
+
+    #[test]
+    fn tick_on_second_boundary() {
+        // exactly on a second is the next one, not itself
+        let now = at(1, 10, 9, 30, 0);
+        assert_eq!(next_tick(now, true), at(1, 10, 9, 31, 0));
+        assert_eq!(next_tick(now, false), at(1, 10, 10, 0, 0));
+
+        let now = at(1, 10, 9, 30, 1);
+        assert_eq!(next_tick(now, true), at(1, 10, 9, 31, 0));
+        assert_eq!(next_tick(now, false), at(1, 10, 10, 0, 0));
+    }
+
+    #[test]
+    fn tick_on_minute_boundary() {
+        let now = at(1, 10, 9, 0, 0);
+        assert_eq!(next_tick(now, true), at(1, 10, 9, 1, 0));
+        assert_eq!(next_tick(now, false), at(1, 10, 10, 0, 0));
+
+        let now = at(1, 10, 9, 59, 999_999_999);
+        assert_eq!(next_tick(now, true), at(1, 10, 10, 0, 0));
+        assert_eq!(next_tick(now, false), at(1, 10, 10, 0, 0));
+    }
+
+    #[gpui::test]
+    fn render_states(cx: &mut TestAppContext) {
+        // 2024-01-01 10:09 in `--preview`
+        let clock = preview_widget::<Clock>(cx, &ClockConfig::default());
+        assert_eq!(
+            clock.read_with(cx, |clock, _| clock.text()),
+            Some("1/1 Mon 10:09 AM".to_owned())
+        );
+        for mode in [ClockMode::Text, ClockMode::Analog, ClockMode::Both] {
+            clock.update(cx, |clock, cx| clock.click(cx));
+            draw_widget(cx, &clock);
+            assert_eq!(clock.read_with(cx, |clock, _| clock.mode), mode);
+        }
+
+        let config = ClockConfig {
+            blinking_colon: true,
+            ..ClockConfig::default()
+        };
+        let clock = preview_widget::<Clock>(cx, &config);
+        // copied as shown, without the secondary format
+        assert_eq!(
+            clock.read_with(cx, |clock, _| clock.text()),
+            Some("10:09".to_owned())
+        );
+
+        let config = ClockConfig {
+            format: "[hour".to_owned(),
+            ..ClockConfig::default()
+        };
+        let clock = preview_widget::<Clock>(cx, &config);
+        assert_eq!(clock.read_with(cx, |clock, _| clock.text()), None);
+    }
 }
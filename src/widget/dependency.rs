@@ -0,0 +1,130 @@
+use std::{
+    collections::{HashMap, hash_map},
+    fmt,
+};
+
+use gpui::SharedString;
+use wayland_client::{
+    Connection, Dispatch, QueueHandle,
+    globals::{GlobalListContents, registry_queue_init},
+    protocol::wl_registry::{self, WlRegistry},
+};
+use zbus::{fdo::DBusProxy, names::BusName};
+
+use crate::{config::Config, widget::WidgetPlacement};
+
+/// A service a widget needs at runtime, see [`Widget::dependencies`](super::Widget::dependencies).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Dependency {
+    /// A well-known name on the system bus, e.g. `org.freedesktop.UPower`
+    SystemDbus(&'static str),
+    /// A well-known name on the session bus
+    SessionDbus(&'static str),
+    /// A global advertised by the compositor, e.g. `ext_workspace_manager_v1`
+    Wayland(&'static str),
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SystemDbus(name) => write!(f, "system dbus: {name}"),
+            Self::SessionDbus(name) => write!(f, "session dbus: {name}"),
+            Self::Wayland(interface) => write!(f, "wayland: {interface}"),
+        }
+    }
+}
+
+impl Dependency {
+    /// Whether the service is running or can be activated.
+    pub async fn is_available(&self) -> Result<bool, String> {
+        match self {
+            Self::SystemDbus(name) => dbus_name_available(zbus::Connection::system().await, name)
+                .await
+                .map_err(|e| format!("error while checking {name} on the system bus: {e}")),
+            Self::SessionDbus(name) => dbus_name_available(zbus::Connection::session().await, name)
+                .await
+                .map_err(|e| format!("error while checking {name} on the session bus: {e}")),
+            Self::Wayland(interface) => wayland_global_supported(interface),
+        }
+    }
+}
+
+async fn dbus_name_available(
+    connection: zbus::Result<zbus::Connection>,
+    name: &str,
+) -> zbus::Result<bool> {
+    let proxy = DBusProxy::new(&connection?).await?;
+    let bus_name = BusName::try_from(name)?;
+    if proxy.name_has_owner(bus_name).await? {
+        return Ok(true);
+    }
+    // e.g. upower is started on the first call
+    Ok(proxy
+        .list_activatable_names()
+        .await?
+        .iter()
+        .any(|x| x.as_str() == name))
+}
+
+/// Whether the compositor advertises a global with this interface.
+pub fn wayland_global_supported(interface: &str) -> Result<bool, String> {
+    let connection = Connection::connect_to_env()
+        .map_err(|e| format!("error while connecting to wayland server: {e}"))?;
+    let (globals, _) = registry_queue_init::<RegistryState>(&connection)
+        .map_err(|e| format!("error while listing wayland globals: {e}"))?;
+    Ok(globals
+        .contents()
+        .with_list(|globals| globals.iter().any(|global| global.interface == interface)))
+}
+
+struct RegistryState;
+
+impl Dispatch<WlRegistry, GlobalListContents> for RegistryState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+/// The id and dependencies of each configured widget.
+pub fn configured(config: &Config) -> Vec<(SharedString, &'static [Dependency])> {
+    config
+        .left
+        .iter()
+        .chain(&config.middle)
+        .chain(&config.right)
+        .map(|WidgetPlacement { widget, .. }| (widget.id(), widget.dependencies(config)))
+        .collect()
+}
+
+/// Warn about the widgets that will likely fail because a service is missing.
+pub async fn check(widgets: Vec<(SharedString, &'static [Dependency])>) {
+    let mut checked = HashMap::new();
+    for (widget, dependencies) in widgets {
+        for &dependency in dependencies {
+            let available = match checked.entry(dependency) {
+                hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                hash_map::Entry::Vacant(entry) => entry.insert(dependency.is_available().await),
+            };
+            match available {
+                Ok(true) => (),
+                Ok(false) => tracing::warn!(
+                    %widget,
+                    %dependency,
+                    "A service required by the widget is not available, it will likely fail"
+                ),
+                Err(e) => tracing::warn!(
+                    %widget,
+                    %dependency,
+                    error = %e,
+                    "Failed to check a service required by the widget"
+                ),
+            }
+        }
+    }
+}
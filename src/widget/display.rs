@@ -1,23 +1,100 @@
-use gpui::{Context, IntoElement, ParentElement, Render, Window};
+use gpui::{
+    Context, InteractiveElement, IntoElement, ParentElement, Render, StatefulInteractiveElement,
+    Window, prelude::FluentBuilder,
+};
+use serde::{Deserialize, Serialize};
 
-use crate::widget::{Widget, widget_wrapper};
+use crate::{
+    util::spawn_detached,
+    widget::{Widget, error_element, widget_wrapper},
+};
 
-pub struct Display;
+/// The display the bar is on, for checking the setup.
+pub struct Display {
+    format: String,
+    on_click_command: Option<String>,
+    error_message: Option<String>,
+}
 
 impl Widget for Display {
-    type Config = ();
+    type Config = DisplayConfig;
+
+    fn new(_cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        Self {
+            format: config.format.clone(),
+            on_click_command: config.on_click_command.clone(),
+            error_message: None,
+        }
+    }
 
-    fn new(_cx: &mut Context<Self>, _config: &Self::Config) -> Self {
-        Self
+    fn activate(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        if let Some(command) = &self.on_click_command {
+            self.error_message = spawn_detached(command).err();
+            cx.notify();
+        }
+    }
+
+    fn activatable(&self) -> bool {
+        self.on_click_command.is_some()
     }
 }
 
 impl Render for Display {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let display = match window.display(cx) {
-            Some(display) => format!("display = {:?}", display.id()),
-            None => "display not found".to_owned(),
+        let display = match (&self.error_message, window.display(cx)) {
+            (Some(e), _) => error_element(cx, e).into_any_element(),
+            (None, Some(display)) => {
+                let scale = window.scale_factor();
+                let size = display.bounds().size;
+                self.format
+                    .replace("{id}", &format!("{:?}", display.id()))
+                    .replace(
+                        "{resolution}",
+                        &format!(
+                            "{}x{}",
+                            (f32::from(size.width) * scale).round(),
+                            (f32::from(size.height) * scale).round()
+                        ),
+                    )
+                    // gpui doesn't report the refresh rate of a display
+                    .replace("{refresh}", "")
+                    .replace("{scale}", &format!("{scale}"))
+                    .trim()
+                    .to_owned()
+                    .into_any_element()
+            }
+            (None, None) => "display not found".into_any_element(),
         };
-        widget_wrapper().child(display)
+        widget_wrapper(cx)
+            .id("display")
+            .child(display)
+            .when(self.on_click_command.is_some(), |this| {
+                this.on_click(cx.listener(|this, _, window, cx| this.activate(window, cx)))
+            })
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct DisplayConfig {
+    /// `{id}`, `{resolution}` (in physical pixels) and `{scale}` (e.g. `1.25`) are replaced.
+    /// `{refresh}` is replaced with nothing, the refresh rate isn't reported yet.
+    #[serde(default = "default_format")]
+    pub format: String,
+    /// Run on click, e.g. `"wdisplays"` or `"nwg-displays"` to change the displays. Without it
+    /// the widget isn't clickable.
+    #[serde(default)]
+    pub on_click_command: Option<String>,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            format: default_format(),
+            on_click_command: None,
+        }
     }
 }
+
+fn default_format() -> String {
+    "display = {id}".to_owned()
+}
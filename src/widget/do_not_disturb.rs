@@ -0,0 +1,280 @@
+use std::{io, pin::pin, process::Command};
+
+use futures::{
+    StreamExt,
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    stream,
+};
+use gpui::{
+    AsyncApp, Context, InteractiveElement, IntoElement, ParentElement, Render,
+    StatefulInteractiveElement, Styled, WeakEntity, Window,
+};
+use serde::{Deserialize, Serialize};
+use zbus::{Connection, proxy};
+
+use crate::widget::{Widget, error_element, spawn_watched_task, widget_wrapper};
+
+pub struct DoNotDisturb {
+    error_message: Option<String>,
+    enabled: Option<bool>,
+    toggle_tx: UnboundedSender<()>,
+}
+
+impl Widget for DoNotDisturb {
+    type Config = DoNotDisturbConfig;
+
+    fn new(cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        let (toggle_tx, toggle_rx) = mpsc::unbounded();
+        match config.backend {
+            DoNotDisturbBackend::Swaync => {
+                spawn_watched_task(cx, async move |this, cx| {
+                    swaync_task(this, cx, toggle_rx).await
+                })
+                .detach();
+            }
+            DoNotDisturbBackend::Mako => {
+                let commands = Commands {
+                    toggle: "makoctl mode -t do-not-disturb".to_owned(),
+                    status: "makoctl mode".to_owned(),
+                };
+                spawn_watched_task(cx, async move |this, cx| {
+                    command_task(this, cx, commands, toggle_rx).await
+                })
+                .detach();
+            }
+            DoNotDisturbBackend::Command => {
+                if let (Some(toggle), Some(status)) =
+                    (config.toggle_command.clone(), config.status_command.clone())
+                {
+                    let commands = Commands { toggle, status };
+                    spawn_watched_task(cx, async move |this, cx| {
+                        command_task(this, cx, commands, toggle_rx).await
+                    })
+                    .detach();
+                } else {
+                    return Self {
+                        error_message: Some(
+                            "`toggle_command` and `status_command` are required for the command backend"
+                                .to_owned(),
+                        ),
+                        enabled: None,
+                        toggle_tx,
+                    };
+                }
+            }
+        }
+
+        Self {
+            error_message: None,
+            enabled: None,
+            toggle_tx,
+        }
+    }
+
+    fn activate(&mut self, _window: &mut Window, _cx: &mut Context<Self>) {
+        if let Err(e) = self.toggle_tx.unbounded_send(()) {
+            tracing::error!(error = %e, "Failed to send toggle request");
+        }
+    }
+
+    fn activatable(&self) -> bool {
+        true
+    }
+}
+
+impl Render for DoNotDisturb {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if let Some(e) = &self.error_message {
+            return widget_wrapper(cx)
+                .child(error_element(cx, e))
+                .into_any_element();
+        }
+        let icon = match self.enabled {
+            Some(true) => "",
+            Some(false) => "",
+            None => "?",
+        };
+        widget_wrapper(cx)
+            .id("do-not-disturb")
+            .on_click(cx.listener(|this, _, window, cx| this.activate(window, cx)))
+            .font_family("Material Symbols Rounded")
+            .child(icon)
+            .into_any_element()
+    }
+}
+
+/// Also clears the error, e.g. of a status command that failed before.
+fn set_enabled(this: &WeakEntity<DoNotDisturb>, cx: &mut AsyncApp, enabled: bool) {
+    tracing::info!(enabled, "Do not disturb changed");
+    let _ = this.update(cx, |this, cx| {
+        this.enabled = Some(enabled);
+        this.error_message = None;
+        cx.notify();
+    });
+}
+
+enum Event {
+    Changed(zbus::Result<bool>),
+    Toggle,
+}
+
+async fn swaync_task(
+    this: WeakEntity<DoNotDisturb>,
+    cx: &mut AsyncApp,
+    toggle_rx: UnboundedReceiver<()>,
+) {
+    let connection = match Connection::session().await {
+        Ok(x) => x,
+        Err(e) => {
+            let _ = this.update(cx, |this, cx| {
+                this.error_message = Some(format!("Failed to connect to session bus: {e}"));
+                cx.notify();
+            });
+            tracing::error!(error = %e, "Failed to connect to session bus");
+            return;
+        }
+    };
+    let proxy = match SwayncControlCenterProxy::new(&connection).await {
+        Ok(x) => x,
+        Err(e) => {
+            let _ = this.update(cx, |this, cx| {
+                this.error_message = Some(format!("Failed to create swaync proxy: {e}"));
+                cx.notify();
+            });
+            tracing::error!(error = %e, "Failed to create swaync proxy");
+            return;
+        }
+    };
+    match proxy.get_dnd().await {
+        Ok(enabled) => set_enabled(&this, cx, enabled),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to get do not disturb state from swaync");
+        }
+    }
+    let signals = match proxy.receive_subscribe_v2().await {
+        Ok(x) => x,
+        Err(e) => {
+            let _ = this.update(cx, |this, cx| {
+                this.error_message = Some(format!("Failed to subscribe to swaync: {e}"));
+                cx.notify();
+            });
+            tracing::error!(error = %e, "Failed to subscribe to swaync");
+            return;
+        }
+    };
+    let mut events = pin!(stream::select(
+        signals.map(|signal| Event::Changed(signal.args().map(|args| args.dnd))),
+        toggle_rx.map(|()| Event::Toggle),
+    ));
+    while let Some(event) = events.next().await {
+        match event {
+            Event::Changed(Ok(enabled)) => set_enabled(&this, cx, enabled),
+            Event::Changed(Err(e)) => {
+                tracing::error!(error = %e, "Failed to parse swaync signal");
+            }
+            Event::Toggle => match proxy.toggle_dnd().await {
+                Ok(enabled) => set_enabled(&this, cx, enabled),
+                Err(e) => {
+                    tracing::error!(error = %e, "Failed to toggle do not disturb");
+                }
+            },
+        }
+    }
+    tracing::warn!("swaync event stream ended");
+}
+
+struct Commands {
+    toggle: String,
+    status: String,
+}
+
+async fn command_task(
+    this: WeakEntity<DoNotDisturb>,
+    cx: &mut AsyncApp,
+    commands: Commands,
+    mut toggle_rx: UnboundedReceiver<()>,
+) {
+    refresh_with_command(&this, cx, &commands.status).await;
+    while let Some(()) = toggle_rx.next().await {
+        if let Err(e) = run_command(cx, &commands.toggle).await {
+            tracing::error!(command = commands.toggle, error = %e, "Failed to run toggle command");
+        }
+        refresh_with_command(&this, cx, &commands.status).await;
+    }
+}
+
+/// The status command is expected to print `true` or `do-not-disturb` on a line when enabled,
+/// matching the output of `makoctl mode`.
+async fn refresh_with_command(this: &WeakEntity<DoNotDisturb>, cx: &mut AsyncApp, command: &str) {
+    match run_command(cx, command).await {
+        Ok(output) => {
+            let enabled = output
+                .lines()
+                .any(|line| matches!(line.trim(), "true" | "do-not-disturb"));
+            set_enabled(this, cx, enabled);
+        }
+        Err(e) => {
+            tracing::error!(command, error = %e, "Failed to run status command");
+            let _ = this.update(cx, |this, cx| {
+                this.error_message = Some(format!("Failed to run `{command}`: {e}"));
+                cx.notify();
+            });
+        }
+    }
+}
+
+async fn run_command(cx: &AsyncApp, command: &str) -> io::Result<String> {
+    let command = command.to_owned();
+    let output = cx
+        .background_spawn(async move { Command::new("sh").arg("-c").arg(command).output() })
+        .await?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("exited with {}", output.status)));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Debug, PartialEq)]
+pub struct DoNotDisturbConfig {
+    #[serde(default)]
+    backend: DoNotDisturbBackend,
+    /// Used by the `command` backend
+    #[serde(default)]
+    toggle_command: Option<String>,
+    /// Used by the `command` backend, see [`refresh_with_command`] for the expected output
+    #[serde(default)]
+    status_command: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum DoNotDisturbBackend {
+    /// SwayNotificationCenter over the session bus
+    #[default]
+    Swaync,
+    /// mako through `makoctl`
+    Mako,
+    /// Custom `toggle_command` and `status_command`
+    Command,
+}
+
+// <https://github.com/ErikReider/SwayNotificationCenter/blob/main/src/controlCenter/controlCenter.vala>
+#[proxy(
+    interface = "org.erikreider.swaync.cc",
+    default_service = "org.erikreider.swaync.cc",
+    default_path = "/org/erikreider/swaync/cc"
+)]
+trait SwayncControlCenter {
+    fn get_dnd(&self) -> zbus::Result<bool>;
+    fn set_dnd(&self, state: bool) -> zbus::Result<()>;
+    fn toggle_dnd(&self) -> zbus::Result<bool>;
+
+    #[zbus(signal)]
+    fn subscribe_v2(
+        &self,
+        count: u32,
+        dnd: bool,
+        cc_open: bool,
+        inhibited: bool,
+    ) -> zbus::Result<()>;
+}
@@ -0,0 +1,48 @@
+use std::fmt;
+
+/// Why a widget can't show its state. It's shown in the widget (with
+/// [`super::error_element`]) unless [`WidgetError::hides_widget`].
+#[derive(Clone, Debug)]
+pub enum WidgetError {
+    /// Connecting to a service (e.g. `system bus` or `UPower`) failed, the widget keeps retrying
+    ConnectionFailed {
+        service: &'static str,
+        message: String,
+    },
+    /// The service isn't there, e.g. power-profiles-daemon isn't installed
+    ServiceMissing(String),
+    /// The configured device isn't there, shown with the ones that are since it's likely a typo
+    DeviceNotFound {
+        device: String,
+        available: Vec<String>,
+    },
+}
+
+impl WidgetError {
+    /// A missing service is expected on some systems, so the widget is hidden instead of showing
+    /// an error.
+    pub fn hides_widget(&self) -> bool {
+        matches!(self, Self::ServiceMissing(_))
+    }
+}
+
+impl fmt::Display for WidgetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ConnectionFailed { service, message } => {
+                write!(f, "Failed to connect to {service}: {message}")
+            }
+            Self::ServiceMissing(service) => write!(f, "{service} not found"),
+            Self::DeviceNotFound { device, available } if available.is_empty() => {
+                write!(f, "No device `{device}`")
+            }
+            Self::DeviceNotFound { device, available } => {
+                write!(
+                    f,
+                    "No device `{device}`, available: {}",
+                    available.join(", ")
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::{env, fs};
+
+use gpui::{
+    Context, InteractiveElement, IntoElement, MouseButton, MouseDownEvent, ParentElement, Render,
+    Window, prelude::FluentBuilder,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::widget::{Widget, copy_to_clipboard, widget_wrapper};
+
+/// The hostname and the user, to tell which machine a bar is on.
+pub struct Host {
+    text: String,
+    copy_on_click: bool,
+    copied: bool,
+}
+
+impl Widget for Host {
+    type Config = HostConfig;
+
+    fn new(_cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        let hostname = hostname().unwrap_or_else(|| "unknown".to_owned());
+        let user = env::var("USER").unwrap_or_else(|_| "unknown".to_owned());
+        Self {
+            text: config
+                .format
+                .replace("{hostname}", &hostname)
+                .replace("{user}", &user),
+            copy_on_click: config.copy_on_click,
+            copied: false,
+        }
+    }
+
+    fn activate(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.copy_on_click {
+            copy_to_clipboard(self.text.clone(), self, |this| &mut this.copied, cx);
+        }
+    }
+
+    fn activatable(&self) -> bool {
+        self.copy_on_click
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct HostConfig {
+    /// `{hostname}` and `{user}` are replaced, e.g. `"{user}@{hostname}"`
+    #[serde(default = "default_format")]
+    pub format: String,
+    /// Copy the text to the clipboard on click
+    #[serde(default)]
+    pub copy_on_click: bool,
+}
+
+impl Default for HostConfig {
+    fn default() -> Self {
+        Self {
+            format: default_format(),
+            copy_on_click: false,
+        }
+    }
+}
+
+fn default_format() -> String {
+    "{hostname}".to_owned()
+}
+
+fn hostname() -> Option<String> {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"]
+        .into_iter()
+        .find_map(|path| match fs::read_to_string(path) {
+            Ok(x) if !x.trim().is_empty() => Some(x.trim().to_owned()),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!(path, error = %e, "Failed to read hostname");
+                None
+            }
+        })
+}
+
+impl Render for Host {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if self.copied {
+            return widget_wrapper(cx).child("Copied");
+        }
+        widget_wrapper(cx)
+            .child(self.text.clone())
+            .when(self.copy_on_click, |this| {
+                this.on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|this, _: &MouseDownEvent, window, cx| this.activate(window, cx)),
+                )
+            })
+    }
+}
@@ -1 +1,101 @@
+use std::{env, fmt::Display, path::Path, rc::Rc};
+
+use futures::{AsyncReadExt, AsyncWriteExt, io::BufReader};
+use gpui::{App, PlatformDisplay, point, px};
+use gpui_net::async_net::UnixStream;
+use serde::Deserialize;
+
+pub mod submap;
+pub mod window_state;
 pub mod workspaces;
+
+/// Directory of the sockets of the running hyprland instance.
+pub fn instance_dir() -> Result<String, String> {
+    let hyprland_instance_signature = env::var("HYPRLAND_INSTANCE_SIGNATURE")
+        .map_err(|e| format!("error while getting HYPRLAND_INSTANCE_SIGNATURE: {e}"))?;
+    // TODO: use the fallback format!("/run/user/{uid}/hypr"):
+    // <https://github.com/hyprwm/Hyprland/blob/main/hyprctl/src/main.cpp>
+    let xdg_runtime_dir = env::var("XDG_RUNTIME_DIR")
+        .map_err(|e| format!("error while getting XDG_RUNTIME_DIR: {e}"))?;
+    Ok(format!(
+        "{xdg_runtime_dir}/hypr/{hyprland_instance_signature}"
+    ))
+}
+
+/// Path of the socket for requests, like `hyprctl`.
+pub fn command_socket_path() -> Result<String, String> {
+    Ok(format!("{}/.socket.sock", instance_dir()?))
+}
+
+/// Path of the socket for events, each line is an event like `workspacev2>>1,1`.
+pub fn event_socket_path() -> Result<String, String> {
+    Ok(format!("{}/.socket2.sock", instance_dir()?))
+}
+
+/// Connect to the event socket.
+pub async fn connect_events() -> Result<BufReader<UnixStream>, String> {
+    let event_socket_path = event_socket_path()?;
+    UnixStream::connect(&event_socket_path)
+        .await
+        .map(BufReader::new)
+        .map_err(|e| {
+            format!("error while connecting to hyprland socket ({event_socket_path}): {e}")
+        })
+}
+
+/// Send a request (e.g. `j/workspaces`) to the command socket and read the whole reply.
+pub async fn request<P>(command_socket_path: P, request: &str) -> Result<Vec<u8>, String>
+where
+    P: AsRef<Path> + Display,
+{
+    let mut stream = UnixStream::connect(&command_socket_path)
+        .await
+        .map_err(|e| {
+            format!("error while connecting to hyprland socket ({command_socket_path}): {e}")
+        })?;
+
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| format!("write_all error: {e}"))?;
+
+    let mut buffer = vec![];
+    stream
+        .read_to_end(&mut buffer)
+        .await
+        .map_err(|e| format!("read_to_end error: {e}"))?;
+
+    let _ = stream.close().await;
+
+    Ok(buffer)
+}
+
+#[derive(Deserialize)]
+pub struct MonitorInfo {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub focused: bool,
+}
+
+pub async fn monitors() -> Result<Vec<MonitorInfo>, String> {
+    let buffer = request(command_socket_path()?, "j/monitors").await?;
+    serde_json::from_slice(&buffer)
+        .map_err(|e| format!("parsing `{:?}`: {e}", String::from_utf8(buffer)))
+}
+
+pub async fn focused_monitor() -> Result<MonitorInfo, String> {
+    monitors()
+        .await?
+        .into_iter()
+        .find(|x| x.focused)
+        .ok_or_else(|| "there is no focused monitor".to_owned())
+}
+
+/// The gpui display at the same position as a hyprland monitor.
+pub fn monitor_display(monitor: &MonitorInfo, cx: &App) -> Option<Rc<dyn PlatformDisplay>> {
+    let origin = point(px(monitor.x as f32), px(monitor.y as f32));
+    cx.displays()
+        .into_iter()
+        .find(|display| display.bounds().origin == origin)
+}
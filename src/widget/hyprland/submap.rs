@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use futures::AsyncBufReadExt;
+use gpui::{
+    AsyncApp, Context, IntoElement, ParentElement, Render, Styled, WeakEntity, Window, div, rems,
+};
+
+use crate::widget::{
+    Widget, error_element, hyprland::connect_events, spawn_watched_task, widget_wrapper,
+};
+
+/// The active submap (keybind mode) of hyprland, hidden in the default submap.
+pub struct Submap {
+    error_message: Option<String>,
+    /// Empty in the default submap
+    submap: String,
+}
+
+impl Widget for Submap {
+    type Config = ();
+
+    fn new(cx: &mut Context<Self>, _config: &Self::Config) -> Self {
+        spawn_watched_task(cx, task).detach();
+
+        Self {
+            error_message: None,
+            submap: String::new(),
+        }
+    }
+
+    fn visible(&self) -> bool {
+        self.error_message.is_some() || !self.submap.is_empty()
+    }
+}
+
+impl Render for Submap {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if let Some(e) = &self.error_message {
+            return widget_wrapper(cx).child(error_element(cx, e));
+        }
+        widget_wrapper(cx)
+            .flex()
+            .items_center()
+            .gap(rems(0.25))
+            .child(div().font_family("Material Symbols Rounded").child(""))
+            .child(self.submap.clone())
+    }
+}
+
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+async fn task(this: WeakEntity<Submap>, cx: &mut AsyncApp) {
+    loop {
+        let result = listen(&this, cx).await;
+        // the submap is reset when hyprland restarts, and unknown until the next event anyway
+        let Ok(()) = this.update(cx, |this, cx| {
+            this.submap.clear();
+            this.error_message = result.err();
+            cx.notify();
+        }) else {
+            return;
+        };
+        cx.background_executor().timer(RECONNECT_INTERVAL).await;
+    }
+}
+
+/// Update the submap until the event socket is closed.
+async fn listen(this: &WeakEntity<Submap>, cx: &mut AsyncApp) -> Result<(), String> {
+    let mut event_stream = connect_events().await?;
+    let _ = this.update(cx, |this, cx| {
+        this.error_message = None;
+        cx.notify();
+    });
+    loop {
+        let mut line = String::new();
+        match event_stream.read_line(&mut line).await {
+            Ok(0) => {
+                tracing::warn!("The hyprland event socket is closed, reconnecting");
+                return Ok(());
+            }
+            Ok(_) => (),
+            Err(e) => return Err(format!("error while reading the socket: {e}")),
+        }
+        let line = line.strip_suffix('\n').unwrap_or(line.as_str());
+        if let Some(submap) = line.strip_prefix("submap>>") {
+            let submap = submap.to_owned();
+            this.update(cx, |this, cx| {
+                this.submap = submap;
+                cx.notify();
+            })
+            .map_err(|e| e.to_string())?;
+        }
+    }
+}
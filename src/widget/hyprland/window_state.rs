@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+use futures::AsyncBufReadExt;
+use gpui::{
+    AsyncApp, Context, IntoElement, ParentElement, Render, Styled, WeakEntity, Window, rems,
+};
+use serde::Deserialize;
+
+use crate::widget::{
+    Widget, error_element,
+    hyprland::{command_socket_path, connect_events, request},
+    spawn_watched_task, widget_wrapper,
+};
+
+/// Whether the active window is fullscreen or floating, hidden when it's neither.
+pub struct WindowState {
+    error_message: Option<String>,
+    /// Without the `0x` prefix, like in the events
+    address: Option<String>,
+    fullscreen: bool,
+    floating: bool,
+}
+
+impl Widget for WindowState {
+    type Config = ();
+
+    fn new(cx: &mut Context<Self>, _config: &Self::Config) -> Self {
+        spawn_watched_task(cx, task).detach();
+
+        Self {
+            error_message: None,
+            address: None,
+            fullscreen: false,
+            floating: false,
+        }
+    }
+
+    fn visible(&self) -> bool {
+        self.error_message.is_some() || self.fullscreen || self.floating
+    }
+}
+
+impl Render for WindowState {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if let Some(e) = &self.error_message {
+            return widget_wrapper(cx).child(error_element(cx, e));
+        }
+        let mut icons = String::new();
+        if self.fullscreen {
+            icons.push('');
+        }
+        if self.floating {
+            icons.push('');
+        }
+        widget_wrapper(cx)
+            .font_family("Material Symbols Rounded")
+            .flex()
+            .gap(rems(0.25))
+            .child(icons)
+    }
+}
+
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+async fn task(this: WeakEntity<WindowState>, cx: &mut AsyncApp) {
+    loop {
+        let result = listen(&this, cx).await;
+        let Ok(()) = this.update(cx, |this, cx| {
+            this.address = None;
+            this.fullscreen = false;
+            this.floating = false;
+            this.error_message = result.err();
+            cx.notify();
+        }) else {
+            return;
+        };
+        cx.background_executor().timer(RECONNECT_INTERVAL).await;
+    }
+}
+
+/// Update the state until the event socket is closed.
+async fn listen(this: &WeakEntity<WindowState>, cx: &mut AsyncApp) -> Result<(), String> {
+    let mut event_stream = connect_events().await?;
+    refresh(this, cx).await?;
+    loop {
+        let mut line = String::new();
+        match event_stream.read_line(&mut line).await {
+            Ok(0) => {
+                tracing::warn!("The hyprland event socket is closed, reconnecting");
+                return Ok(());
+            }
+            Ok(_) => (),
+            Err(e) => return Err(format!("error while reading the socket: {e}")),
+        }
+        let line = line.strip_suffix('\n').unwrap_or(line.as_str());
+
+        if line.starts_with("activewindowv2>>") {
+            refresh(this, cx).await?;
+        } else if let Some(fullscreen) = line.strip_prefix("fullscreen>>") {
+            let fullscreen = fullscreen != "0";
+            this.update(cx, |this, cx| {
+                this.fullscreen = fullscreen;
+                cx.notify();
+            })
+            .map_err(|e| e.to_string())?;
+        } else if let Some(line) = line.strip_prefix("changefloatingmode>>") {
+            let Some((address, floating)) = line.split_once(',') else {
+                tracing::error!(
+                    "Received a `changefloatingmode` update `{line}`, but it doesn't contain any `,`"
+                );
+                continue;
+            };
+            let floating = floating != "0";
+            this.update(cx, |this, cx| {
+                if this.address.as_deref() == Some(address) {
+                    this.floating = floating;
+                    cx.notify();
+                }
+            })
+            .map_err(|e| e.to_string())?;
+        }
+    }
+}
+
+async fn refresh(this: &WeakEntity<WindowState>, cx: &mut AsyncApp) -> Result<(), String> {
+    let active_window = active_window().await?;
+    this.update(cx, |this, cx| {
+        match active_window {
+            Some(window) => {
+                this.address = Some(
+                    window
+                        .address
+                        .strip_prefix("0x")
+                        .unwrap_or(&window.address)
+                        .to_owned(),
+                );
+                this.fullscreen = is_set(&window.fullscreen);
+                this.floating = window.floating;
+            }
+            None => {
+                this.address = None;
+                this.fullscreen = false;
+                this.floating = false;
+            }
+        }
+        this.error_message = None;
+        cx.notify();
+    })
+    .map_err(|e| e.to_string())
+}
+
+#[derive(Deserialize)]
+struct ActiveWindow {
+    address: String,
+    floating: bool,
+    /// A bool in older versions, the fullscreen mode (`0` for none) in newer ones
+    fullscreen: serde_json::Value,
+}
+
+fn is_set(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Bool(x) => *x,
+        serde_json::Value::Number(x) => x.as_i64() != Some(0),
+        _ => false,
+    }
+}
+
+/// `None` when no window is focused, hyprland replies with `{}` then.
+async fn active_window() -> Result<Option<ActiveWindow>, String> {
+    let buffer = request(command_socket_path()?, "j/activewindow").await?;
+    let value = serde_json::from_slice::<serde_json::Value>(&buffer)
+        .map_err(|e| format!("parsing `{:?}`: {e}", String::from_utf8_lossy(&buffer)))?;
+    if value.as_object().is_some_and(|x| x.is_empty()) {
+        return Ok(None);
+    }
+    serde_json::from_value(value)
+        .map(Some)
+        .map_err(|e| format!("parsing the active window: {e}"))
+}
@@ -1,38 +1,66 @@
 use std::{
     collections::{BTreeMap, btree_map},
-    env,
     fmt::Display,
     path::Path,
 };
 
-use futures::{
-    AsyncReadExt, AsyncWriteExt,
-    io::{AsyncBufReadExt, BufReader},
-};
+use futures::io::AsyncBufReadExt;
 use gpui::{
-    AsyncApp, Context, IntoElement, ParentElement, Render, Styled, WeakEntity, Window, black, div,
-    opaque_grey, rems,
+    AnyElement, AsyncApp, Context, InteractiveElement, IntoElement, ParentElement, Render,
+    ScrollWheelEvent, Styled, WeakEntity, Window, prelude::FluentBuilder, rems,
 };
-use gpui_net::async_net::UnixStream;
 use serde::Deserialize;
 
-use crate::widget::{Widget, widget_wrapper};
+use crate::{
+    config::ThemeConfig,
+    util::retry_with_backoff,
+    widget::{
+        ScrollAccumulator, Widget, error_element,
+        hyprland::{command_socket_path, connect_events, request},
+        is_preview, scroll_delta, spawn_watched_task, transition, widget_wrapper,
+        workspaces::{WorkspaceView, WorkspacesConfig},
+    },
+};
 
 pub struct HyprlandWorkspace {
+    config: WorkspacesConfig,
     error_message: Option<String>,
+    scrolled: ScrollAccumulator,
     workspaces: BTreeMap<i64, WorkspaceInfo>,
     active_workspace: Option<i64>,
     active_special_workspace: Option<i64>,
 }
 
 impl Widget for HyprlandWorkspace {
-    type Config = ();
+    type Config = WorkspacesConfig;
 
-    fn new(cx: &mut Context<Self>, _config: &Self::Config) -> Self {
-        cx.spawn(info).detach();
+    fn new(cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        if is_preview(cx) {
+            let workspace = |name: &str, windows| WorkspaceInfo {
+                name: name.to_owned(),
+                windows,
+                is_persistent: true,
+            };
+            return Self {
+                config: config.clone(),
+                error_message: None,
+                scrolled: ScrollAccumulator::default(),
+                workspaces: BTreeMap::from([
+                    (1, workspace("1", 2)),
+                    (2, workspace("2", 1)),
+                    (3, workspace("3", 0)),
+                    (4, workspace("4", 3)),
+                ]),
+                active_workspace: Some(2),
+                active_special_workspace: None,
+            };
+        }
+        spawn_watched_task(cx, info).detach();
 
         Self {
+            config: config.clone(),
             error_message: None,
+            scrolled: ScrollAccumulator::default(),
             workspaces: BTreeMap::new(),
             active_workspace: None,
             active_special_workspace: None,
@@ -41,202 +69,295 @@ impl Widget for HyprlandWorkspace {
 }
 
 impl Render for HyprlandWorkspace {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         if let Some(e) = &self.error_message {
-            return widget_wrapper().child(e.trim().to_owned());
+            return widget_wrapper(cx).child(error_element(cx, e));
         }
 
-        widget_wrapper()
+        widget_wrapper(cx)
             .flex()
             .gap(rems(0.5))
-            .children(self.workspaces.iter().map(|(&id, info)| {
-                if Some(id) == self.active_workspace || Some(id) == self.active_special_workspace {
-                    div()
-                        .text_color(black())
-                        .bg(opaque_grey(1.0, 0.75))
-                        .rounded(rems(0.5))
-                        .child(format!(" > {} < ", info.name))
-                } else {
-                    div().child(info.name.clone())
-                }
-            }))
+            .when(self.config.scroll, |this| {
+                this.on_scroll_wheel(cx.listener(|this, event: &ScrollWheelEvent, window, cx| {
+                    let delta = scroll_delta(event, window, cx);
+                    let steps = this.scrolled.add(delta, window.line_height());
+                    this.scroll(steps, cx);
+                }))
+            })
+            .children(self.render_workspaces(cx))
         // .child(format!("special: {:?}", self.active_special_workspace))
         // .child(format!("workspace: {:?}", self.active_workspace))
     }
 }
 
-async fn info(this: WeakEntity<HyprlandWorkspace>, cx: &mut AsyncApp) {
-    let hyprland_instance_signature = match env::var("HYPRLAND_INSTANCE_SIGNATURE") {
-        Ok(x) => x,
-        Err(e) => {
-            let _ = this.update(cx, |this, cx| {
-                this.error_message = Some(format!(
-                    "error while getting HYPRLAND_INSTANCE_SIGNATURE: {e}"
-                ));
-                cx.notify();
-            });
+impl HyprlandWorkspace {
+    fn render_workspaces(&self, cx: &mut Context<Self>) -> Vec<AnyElement> {
+        let theme = cx.global::<ThemeConfig>().clone();
+        let is_active = |id: i64| {
+            Some(id) == self.active_workspace || Some(id) == self.active_special_workspace
+        };
+        let elements = self
+            .workspaces
+            .iter()
+            .map(|(&id, info)| {
+                let active = is_active(id);
+                let element = self.config.render_workspace(
+                    WorkspaceView {
+                        name: &info.name,
+                        id: Some(&id.to_string()),
+                        active,
+                        urgent: false,
+                        // persistent workspaces stay around while empty
+                        dimmed: !active && info.is_persistent && info.windows == 0,
+                    },
+                    &theme,
+                );
+                transition(format!("workspace-highlight-{id}"), active, element, cx)
+            })
+            .collect();
+        let active = self.workspaces.keys().position(|&id| is_active(id));
+        self.config.limit_visible(elements, active)
+    }
+    fn scroll(&self, steps: i32, cx: &mut Context<Self>) {
+        // special workspaces are toggled, not switched to
+        let workspaces = self
+            .workspaces
+            .iter()
+            .filter(|(_, info)| !info.name.starts_with("special:"))
+            .collect::<Vec<_>>();
+        let Some(current) = workspaces
+            .iter()
+            .position(|(&id, _)| Some(id) == self.active_workspace)
+        else {
             return;
-        }
-    };
-    let runtime_dir = match env::var("XDG_RUNTIME_DIR") {
-        Ok(xdg_runtime_dir) => format!("{xdg_runtime_dir}/hypr"),
-        Err(e) => {
-            // TODO: use the fallback format!("/run/user/{uid}/hypr"):
-            // <https://github.com/hyprwm/Hyprland/blob/main/hyprctl/src/main.cpp>
-            let _ = this.update(cx, |this, cx| {
-                this.error_message = Some(format!("error while getting XDG_RUNTIME_DIR: {e}"));
-                cx.notify();
-            });
+        };
+        let Some((&id, info)) = self
+            .config
+            .scroll_target(current, workspaces.len(), steps)
+            .and_then(|index| workspaces.get(index))
+        else {
             return;
-        }
-    };
-
-    let event_socket_path = format!("{runtime_dir}/{hyprland_instance_signature}/.socket2.sock");
-    let command_socket_path = format!("{runtime_dir}/{hyprland_instance_signature}/.socket.sock");
+        };
+        // named workspaces don't have positive ids
+        let command = if id > 0 {
+            format!("dispatch workspace {id}")
+        } else {
+            format!("dispatch workspace name:{}", info.name)
+        };
+        cx.background_spawn(async move {
+            let result = match command_socket_path() {
+                Ok(path) => request(path, &command).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                tracing::error!(command, error = %e, "Failed to switch workspace");
+            }
+        })
+        .detach();
+    }
+}
 
-    let mut event_stream = match UnixStream::connect(&event_socket_path).await {
-        Ok(x) => BufReader::new(x),
+async fn info(this: WeakEntity<HyprlandWorkspace>, cx: &mut AsyncApp) {
+    let command_socket_path = match command_socket_path() {
+        Ok(x) => x,
         Err(e) => {
             let _ = this.update(cx, |this, cx| {
-                this.error_message = Some(format!(
-                    "error while connecting to hyprland socket ({event_socket_path}): {e}"
-                ));
+                this.error_message = Some(e);
                 cx.notify();
             });
             return;
         }
     };
 
-    try_update_with_get_workspace(&command_socket_path, &this, cx).await;
-
+    // reconnect when hyprland restarts or the socket fails, the workspaces are read again then
     loop {
-        let mut line = String::new();
-        match event_stream.read_line(&mut line).await {
-            Ok(_) => (),
-            Err(e) => {
-                let _ = this.update(cx, |this, cx| {
-                    this.error_message = Some(format!("error while reading the socket: {e}"));
+        let Some(mut event_stream) = retry_with_backoff(
+            cx,
+            async |_| connect_events().await,
+            |cx, e, retry_in| {
+                tracing::error!(error = %e, ?retry_in, "Failed to connect to the hyprland event socket");
+                this.update(cx, |this, cx| {
+                    this.error_message = Some(e);
                     cx.notify();
-                });
-                break;
-            }
+                })
+                .is_ok()
+            },
+        )
+        .await
+        else {
+            return;
+        };
+        let Ok(()) = this.update(cx, |this, cx| {
+            this.error_message = None;
+            cx.notify();
+        }) else {
+            return;
         };
-        let line = line.strip_suffix('\n').unwrap_or(line.as_str());
 
-        if let Some(line) = line.strip_prefix("createworkspacev2>>") {
-            if let Some((id, name)) = line.split_once(",") {
-                match id.parse() {
-                    Ok(id) => {
-                        let _ = this.update(cx, |this, cx| {
-                            let workspace = WorkspaceInfo { name: name.to_owned() };
-                            match this.workspaces.entry(id) {
-                                btree_map::Entry::Occupied(mut entry) => {
-                                    let old = entry.insert(workspace);
-                                    tracing::warn!("Received a `createworkspacev2` with id = {id} and name = {name}, but there is already an old workspace with name = {}", old.name);
-                                    // TODO: Maybe use try_update_with_get_workspace
-                                }
-                                btree_map::Entry::Vacant(entry) => {
-                                    entry.insert(workspace);
+        try_update_with_get_workspace(&command_socket_path, &this, cx).await;
+
+        loop {
+            let mut line = String::new();
+            match event_stream.read_line(&mut line).await {
+                Ok(0) => {
+                    tracing::warn!("The hyprland event socket is closed, reconnecting");
+                    break;
+                }
+                Ok(_) => (),
+                Err(e) => {
+                    let _ = this.update(cx, |this, cx| {
+                        this.error_message = Some(format!("error while reading the socket: {e}"));
+                        cx.notify();
+                    });
+                    break;
+                }
+            };
+            let line = line.strip_suffix('\n').unwrap_or(line.as_str());
+
+            if let Some(line) = line.strip_prefix("createworkspacev2>>") {
+                if let Some((id, name)) = line.split_once(",") {
+                    match id.parse() {
+                        Ok(id) => {
+                            let _ = this.update(cx, |this, cx| {
+                                let workspace = WorkspaceInfo {
+                                    name: name.to_owned(),
+                                    windows: 0,
+                                    is_persistent: false,
+                                };
+                                match this.workspaces.entry(id) {
+                                    btree_map::Entry::Occupied(mut entry) if entry.get().is_persistent => {
+                                        // a persistent placeholder is becoming a real workspace
+                                        entry.get_mut().name = name.to_owned();
+                                    }
+                                    btree_map::Entry::Occupied(mut entry) => {
+                                        let old = entry.insert(workspace);
+                                        tracing::warn!("Received a `createworkspacev2` with id = {id} and name = {name}, but there is already an old workspace with name = {}", old.name);
+                                        // TODO: Maybe use try_update_with_get_workspace
+                                    }
+                                    btree_map::Entry::Vacant(entry) => {
+                                        entry.insert(workspace);
+                                    }
                                 }
-                            }
-                            cx.notify();
-                        });
-                    }
-                    Err(e) => {
-                        tracing::error!(
-                            "Failed to parse the id ({id}) from `createworkspacev2`: {e}"
-                        );
-                        try_update_with_get_workspace(&command_socket_path, &this, cx).await;
+                                cx.notify();
+                            });
+                        }
+                        Err(e) => {
+                            tracing::error!(
+                                "Failed to parse the id ({id}) from `createworkspacev2`: {e}"
+                            );
+                            try_update_with_get_workspace(&command_socket_path, &this, cx).await;
+                        }
                     }
+                } else {
+                    tracing::error!(
+                        "Received a `createworkspacev2` update `{line}`, but it doesn't contain any `,`"
+                    );
+                    try_update_with_get_workspace(&command_socket_path, &this, cx).await;
                 }
-            } else {
-                tracing::error!(
-                    "Received a `createworkspacev2` update `{line}`, but it doesn't contain any `,`"
-                );
-                try_update_with_get_workspace(&command_socket_path, &this, cx).await;
-            }
-        } else if let Some(line) = line.strip_prefix("destroyworkspacev2>>") {
-            if let Some((id, name)) = line.split_once(",") {
-                match id.parse() {
-                    Ok(id) => {
-                        let _ = this.update(cx, |this, cx| {
-                            match this.workspaces.entry(id) {
-                                btree_map::Entry::Occupied(entry) => {
-                                    let old = entry.remove();
-                                    if old.name != name {
-                                        tracing::warn!("Received a `destroyworkspacev2` with id = {id} and name = {name}, but the old name is not the same: `{}`", old.name);
+            } else if let Some(line) = line.strip_prefix("destroyworkspacev2>>") {
+                if let Some((id, name)) = line.split_once(",") {
+                    match id.parse() {
+                        Ok(id) => {
+                            let _ = this.update(cx, |this, cx| {
+                                match this.workspaces.entry(id) {
+                                    btree_map::Entry::Occupied(mut entry) if entry.get().is_persistent => {
+                                        // keep persistent workspaces as empty placeholders
+                                        entry.get_mut().windows = 0;
+                                    }
+                                    btree_map::Entry::Occupied(entry) => {
+                                        let old = entry.remove();
+                                        if old.name != name {
+                                            tracing::warn!("Received a `destroyworkspacev2` with id = {id} and name = {name}, but the old name is not the same: `{}`", old.name);
+                                        }
+                                    }
+                                    btree_map::Entry::Vacant(_) => {
+                                        tracing::error!("Received a `destroyworkspacev2` with id = {id} and name = {name}, but there is no workspace with same id");
+                                        // TODO: Maybe use try_update_with_get_workspace
                                     }
                                 }
-                                btree_map::Entry::Vacant(_) => {
-                                    tracing::error!("Received a `destroyworkspacev2` with id = {id} and name = {name}, but there is no workspace with same id");
-                                    // TODO: Maybe use try_update_with_get_workspace
-                                }
-                            }
-                            cx.notify();
-                        });
-                    }
-                    Err(e) => {
-                        tracing::error!(
-                            "Failed to parse the id ({id}) from `destroyworkspacev2`: {e}"
-                        );
-                        try_update_with_get_workspace(&command_socket_path, &this, cx).await;
+                                cx.notify();
+                            });
+                        }
+                        Err(e) => {
+                            tracing::error!(
+                                "Failed to parse the id ({id}) from `destroyworkspacev2`: {e}"
+                            );
+                            try_update_with_get_workspace(&command_socket_path, &this, cx).await;
+                        }
                     }
+                } else {
+                    tracing::error!(
+                        "Received a `destroyworkspacev2` update `{line}`, but it doesn't contain any `,`"
+                    );
+                    try_update_with_get_workspace(&command_socket_path, &this, cx).await;
                 }
-            } else {
-                tracing::error!(
-                    "Received a `destroyworkspacev2` update `{line}`, but it doesn't contain any `,`"
-                );
+            } else if line.starts_with("openwindow>>")
+                || line.starts_with("closewindow>>")
+                || line.starts_with("movewindowv2>>")
+            {
+                // the window counts are only used to dim empty persistent workspaces, don't ask for
+                // all the workspaces on every window change when there are none
+                let has_persistent = this.read_with(cx, |this, _| {
+                    this.workspaces.values().any(|x| x.is_persistent)
+                });
+                if has_persistent.unwrap_or(false) {
+                    try_update_with_get_workspace(&command_socket_path, &this, cx).await;
+                }
+            } else if line.starts_with("configreloaded>>") {
+                // persistent workspaces come from the config
                 try_update_with_get_workspace(&command_socket_path, &this, cx).await;
-            }
-        } else if let Some(line) = line.strip_prefix("workspacev2>>") {
-            let Some((id, _)) = line.split_once(",") else {
-                tracing::error!(
-                    "Received a `workspacev2` update `{line}`, but it doesn't contain any `,`"
-                );
-                continue;
-            };
-            let id = if id.is_empty() {
-                None
-            } else {
-                match id.parse() {
-                    Ok(x) => Some(x),
-                    Err(e) => {
-                        tracing::error!("Failed to parse the id ({id}) from `workspacev2`: {e}");
-                        continue;
+            } else if let Some(line) = line.strip_prefix("workspacev2>>") {
+                let Some((id, _)) = line.split_once(",") else {
+                    tracing::error!(
+                        "Received a `workspacev2` update `{line}`, but it doesn't contain any `,`"
+                    );
+                    continue;
+                };
+                let id = if id.is_empty() {
+                    None
+                } else {
+                    match id.parse() {
+                        Ok(x) => Some(x),
+                        Err(e) => {
+                            tracing::error!(
+                                "Failed to parse the id ({id}) from `workspacev2`: {e}"
+                            );
+                            continue;
+                        }
                     }
-                }
-            };
+                };
 
-            let _ = this.update(cx, |this, cx| {
-                this.active_workspace = id;
-                cx.notify();
-            });
-        } else if let Some(line) = line.strip_prefix("activespecialv2>>") {
-            let Some((id, _)) = line.split_once(",") else {
-                tracing::error!(
-                    "Received a `activespecialv2` update `{line}`, but it doesn't contain any `,`"
-                );
-                continue;
-            };
-            let id = if id.is_empty() {
-                None
-            } else {
-                match id.parse() {
-                    Ok(x) => Some(x),
-                    Err(e) => {
-                        tracing::error!(
-                            "Failed to parse the id ({id}) from `activespecialv2`: {e}"
-                        );
-                        continue;
+                let _ = this.update(cx, |this, cx| {
+                    this.active_workspace = id;
+                    cx.notify();
+                });
+            } else if let Some(line) = line.strip_prefix("activespecialv2>>") {
+                let Some((id, _)) = line.split_once(",") else {
+                    tracing::error!(
+                        "Received a `activespecialv2` update `{line}`, but it doesn't contain any `,`"
+                    );
+                    continue;
+                };
+                let id = if id.is_empty() {
+                    None
+                } else {
+                    match id.parse() {
+                        Ok(x) => Some(x),
+                        Err(e) => {
+                            tracing::error!(
+                                "Failed to parse the id ({id}) from `activespecialv2`: {e}"
+                            );
+                            continue;
+                        }
                     }
-                }
-            };
+                };
 
-            let _ = this.update(cx, |this, cx| {
-                this.active_special_workspace = id;
-                cx.notify();
-            });
-        };
+                let _ = this.update(cx, |this, cx| {
+                    this.active_special_workspace = id;
+                    cx.notify();
+                });
+            };
+        }
     }
 }
 
@@ -270,35 +391,18 @@ struct WorkspaceInfo {
     name: String,
     // monitor: String,
     // monitor_id: i64,
-    // windows: i32,
+    windows: i32,
     // has_fullscreen: bool,
     // last_window: String, // TODO: should be i64, but use string for now
     // last_window_title: String,
-    // is_persistent: bool,
+    is_persistent: bool,
 }
 
 async fn get_workspaces<P>(command_socket_path: P) -> Result<BTreeMap<i64, WorkspaceInfo>, String>
 where
     P: AsRef<Path> + Display,
 {
-    let mut stream = UnixStream::connect(&command_socket_path)
-        .await
-        .map_err(|e| {
-            format!("error while connecting to hyprland socket ({command_socket_path}): {e}")
-        })?;
-
-    stream
-        .write_all(b"j/workspaces")
-        .await
-        .map_err(|e| format!("write_all error: {e}"))?;
-
-    let mut buffer = vec![];
-    stream
-        .read_to_end(&mut buffer)
-        .await
-        .map_err(|e| format!("read_to_end error: {e}"))?;
-
-    let _ = stream.close().await;
+    let buffer = request(command_socket_path, "j/workspaces").await?;
 
     let workspaces = serde_json::from_slice::<Vec<WorkspaceInfoRaw>>(&buffer)
         .map_err(|e| format!("parsing `{:?}`: {e}", String::from_utf8(buffer)))?;
@@ -316,15 +420,15 @@ struct WorkspaceInfoRaw {
     // monitor: String,
     // #[serde(rename = "monitorID")]
     // monitor_id: i64,
-    // windows: i32,
+    windows: i32,
     // #[serde(rename = "hasfullscreen")]
     // has_fullscreen: bool,
     // #[serde(rename = "lastwindow")]
     // last_window: String, // TODO: should be i64, but use string for now
     // #[serde(rename = "lastwindowtitle")]
     // last_window_title: String,
-    // #[serde(rename = "ispersistent")]
-    // is_persistent: bool,
+    #[serde(rename = "ispersistent", default)]
+    is_persistent: bool,
 }
 
 impl From<WorkspaceInfoRaw> for (i64, WorkspaceInfo) {
@@ -335,11 +439,11 @@ impl From<WorkspaceInfoRaw> for (i64, WorkspaceInfo) {
                 name: value.name,
                 // monitor: value.monitor,
                 // monitor_id: value.monitor_id,
-                // windows: value.windows,
+                windows: value.windows,
                 // has_fullscreen: value.has_fullscreen,
                 // last_window: value.last_window,
                 // last_window_title: value.last_window_title,
-                // is_persistent: value.is_persistent,
+                is_persistent: value.is_persistent,
             },
         )
     }
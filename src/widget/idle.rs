@@ -0,0 +1,134 @@
+use futures::StreamExt;
+use gpui::{AsyncApp, Context, IntoElement, ParentElement, Render, Styled, WeakEntity, Window};
+use zbus::{Connection, proxy};
+
+use crate::{
+    util::retry_with_backoff,
+    widget::{
+        TaskEnd, Widget, dependency::Dependency, error_element, spawn_watched_task, widget_wrapper,
+    },
+};
+
+/// Shows an away glyph while logind considers the session idle. This only observes the idle
+/// state, nothing is inhibited.
+pub struct Idle {
+    error_message: Option<String>,
+    idle: bool,
+    /// Hide the widget when the session has no idle hint, e.g. outside of a logind session
+    unavailable: bool,
+}
+
+impl Widget for Idle {
+    type Config = ();
+
+    fn dependencies() -> &'static [Dependency] {
+        &[Dependency::SystemDbus("org.freedesktop.login1")]
+    }
+
+    fn new(cx: &mut Context<Self>, _config: &Self::Config) -> Self {
+        spawn_watched_task(cx, async move |this, cx| {
+            task(this.clone(), cx).await;
+            // there is nothing to watch without a logind session
+            match this.read_with(cx, |this, _| this.unavailable) {
+                Ok(true) => TaskEnd::Done,
+                _ => TaskEnd::Unexpected,
+            }
+        })
+        .detach();
+
+        Self {
+            error_message: None,
+            idle: false,
+            unavailable: false,
+        }
+    }
+
+    fn visible(&self) -> bool {
+        !self.unavailable && (self.idle || self.error_message.is_some())
+    }
+}
+
+impl Render for Idle {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if let Some(e) = &self.error_message {
+            widget_wrapper(cx).child(error_element(cx, e))
+        } else {
+            widget_wrapper(cx)
+                .font_family("Material Symbols Rounded")
+                .child("")
+        }
+    }
+}
+
+async fn task(this: WeakEntity<Idle>, cx: &mut AsyncApp) {
+    let Some(connection) = retry_with_backoff(
+        cx,
+        async |_| Connection::system().await,
+        |cx, e, retry_in| {
+            tracing::error!(error = %e, ?retry_in, "Failed to connect to system bus");
+            this.update(cx, |this, cx| {
+                this.error_message = Some(format!("Failed to connect to system bus: {e}"));
+                cx.notify();
+            })
+            .is_ok()
+        },
+    )
+    .await
+    else {
+        return;
+    };
+    let _ = this.update(cx, |this, cx| {
+        this.error_message = None;
+        cx.notify();
+    });
+    let proxy = match session_proxy(&connection).await {
+        Ok(x) => x,
+        Err(e) => {
+            tracing::warn!(error = %e, "No logind session found, hiding the idle widget");
+            let _ = this.update(cx, |this, cx| {
+                this.unavailable = true;
+                cx.notify();
+            });
+            return;
+        }
+    };
+    let mut stream = proxy.receive_idle_hint_changed().await;
+    while let Some(idle_hint) = stream.next().await {
+        match idle_hint.get().await {
+            Ok(idle) => {
+                tracing::info!(idle, "Session idle hint changed");
+                let _ = this.update(cx, |this, cx| {
+                    this.idle = idle;
+                    cx.notify();
+                });
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to get new IdleHint");
+            }
+        }
+    }
+    tracing::warn!("Receive IdleHint stream ended");
+}
+
+/// The session of this process, checking that it has an idle hint at all.
+async fn session_proxy(connection: &Connection) -> zbus::Result<SessionProxy<'static>> {
+    let proxy = SessionProxy::new(connection).await?;
+    proxy.idle_hint().await?;
+    Ok(proxy)
+}
+
+// <https://www.freedesktop.org/software/systemd/man/latest/org.freedesktop.login1.html>
+#[proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+pub trait Session {
+    /// `subsystem` is `backlight` or `leds`, `name` is the device in `/sys/class/<subsystem>`
+    fn set_brightness(&self, subsystem: &str, name: &str, brightness: u32) -> zbus::Result<()>;
+    #[zbus(property)]
+    fn idle_hint(&self) -> zbus::Result<bool>;
+    /// Microseconds since the epoch (`CLOCK_REALTIME`)
+    #[zbus(property)]
+    fn idle_since_hint(&self) -> zbus::Result<u64>;
+}
@@ -0,0 +1,171 @@
+use gpui::{
+    AsyncApp, Context, InteractiveElement, IntoElement, MouseButton, MouseDownEvent, ParentElement,
+    Render, WeakEntity, Window, prelude::FluentBuilder,
+};
+use serde::{Deserialize, Serialize};
+use zbus::Connection;
+
+use crate::{
+    util::retry_with_backoff,
+    widget::{
+        Widget, copy_to_clipboard, dependency::Dependency, error_element,
+        network_manager::addresses, poll_interval, spawn_watched_task, widget_wrapper,
+    },
+};
+
+/// The addresses of the primary connection (or of an interface) from NetworkManager.
+pub struct IpAddress {
+    config: IpAddressConfig,
+    error_message: Option<String>,
+    ipv4: Option<String>,
+    ipv6: Option<String>,
+    copied: bool,
+}
+
+impl Widget for IpAddress {
+    type Config = IpAddressConfig;
+
+    fn dependencies() -> &'static [Dependency] {
+        &[Dependency::SystemDbus("org.freedesktop.NetworkManager")]
+    }
+
+    fn new(cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        spawn_watched_task(cx, task).detach();
+
+        Self {
+            config: config.clone(),
+            error_message: None,
+            ipv4: None,
+            ipv6: None,
+            copied: false,
+        }
+    }
+
+    fn activate(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.config.copy_on_click {
+            copy_to_clipboard(self.text(), self, |this| &mut this.copied, cx);
+        }
+    }
+
+    fn activatable(&self) -> bool {
+        self.config.copy_on_click
+    }
+}
+
+impl IpAddress {
+    /// `format` with the addresses, `no_address` if there are none
+    fn text(&self) -> String {
+        if self.ipv4.is_none() && self.ipv6.is_none() {
+            return self.config.no_address.clone();
+        }
+        self.config
+            .format
+            .replace("{ipv4}", self.ipv4.as_deref().unwrap_or("-"))
+            .replace("{ipv6}", self.ipv6.as_deref().unwrap_or("-"))
+    }
+}
+
+impl Render for IpAddress {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if let Some(e) = &self.error_message {
+            return widget_wrapper(cx).child(error_element(cx, e));
+        }
+        if self.copied {
+            return widget_wrapper(cx).child("Copied");
+        }
+        widget_wrapper(cx)
+            .child(self.text())
+            .when(self.config.copy_on_click, |this| {
+                this.on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|this, _: &MouseDownEvent, window, cx| this.activate(window, cx)),
+                )
+            })
+    }
+}
+
+async fn task(this: WeakEntity<IpAddress>, cx: &mut AsyncApp) {
+    let Ok((interval, interface)) = this.read_with(cx, |this, cx| {
+        (
+            poll_interval(cx, this.config.poll_interval),
+            this.config.interface.clone(),
+        )
+    }) else {
+        return;
+    };
+    let Some(connection) = retry_with_backoff(
+        cx,
+        async |_| Connection::system().await,
+        |cx, e, retry_in| {
+            tracing::error!(error = %e, ?retry_in, "Failed to connect to system bus");
+            this.update(cx, |this, cx| {
+                this.error_message = Some(format!("Failed to connect to system bus: {e}"));
+                cx.notify();
+            })
+            .is_ok()
+        },
+    )
+    .await
+    else {
+        return;
+    };
+    loop {
+        let result = addresses(&connection, interface.as_deref()).await;
+        let Ok(()) = this.update(cx, |this, cx| {
+            match result {
+                Ok((ipv4, ipv6)) => {
+                    this.ipv4 = ipv4;
+                    this.ipv6 = ipv6;
+                    this.error_message = None;
+                }
+                Err(e) => {
+                    tracing::error!(error = %e, ?interface, "Failed to get the ip addresses");
+                    this.error_message = Some(format!("Failed to get the ip addresses: {e}"));
+                }
+            }
+            cx.notify();
+        }) else {
+            return;
+        };
+        cx.background_executor().timer(interval).await;
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct IpAddressConfig {
+    /// e.g. `"wlan0"`, the primary connection if unset
+    #[serde(default)]
+    pub interface: Option<String>,
+    /// `{ipv4}` and `{ipv6}` are replaced, with `-` if there is no such address
+    #[serde(default = "default_format")]
+    pub format: String,
+    /// Shown when there is no address at all, e.g. while disconnected
+    #[serde(default = "default_no_address")]
+    pub no_address: String,
+    /// Copy the text to the clipboard on click
+    #[serde(default)]
+    pub copy_on_click: bool,
+    /// Seconds between updates, defaults to `bar.poll_interval`
+    #[serde(default)]
+    pub poll_interval: Option<f64>,
+}
+
+impl Default for IpAddressConfig {
+    fn default() -> Self {
+        Self {
+            interface: None,
+            format: default_format(),
+            no_address: default_no_address(),
+            copy_on_click: false,
+            poll_interval: None,
+        }
+    }
+}
+
+fn default_format() -> String {
+    "{ipv4}".to_owned()
+}
+
+fn default_no_address() -> String {
+    "No address".to_owned()
+}
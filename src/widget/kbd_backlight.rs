@@ -0,0 +1,221 @@
+use std::pin::pin;
+
+use futures::{
+    StreamExt,
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    stream,
+};
+use gpui::{
+    AsyncApp, Context, InteractiveElement, IntoElement, ParentElement, Render, ScrollWheelEvent,
+    Styled, WeakEntity, Window, div, px, rems,
+};
+use zbus::Connection;
+
+use crate::widget::{
+    Widget,
+    dependency::Dependency,
+    error_element,
+    power::{UpowerKbdBacklightProxy, UpowerProxy},
+    scroll_delta, spawn_watched_task, widget_wrapper,
+};
+
+/// Keyboard backlight level from UPower, scroll to adjust.
+pub struct KbdBacklight {
+    error_message: Option<String>,
+    brightness: Option<i32>,
+    max_brightness: Option<i32>,
+    /// Hide the widget when there is no keyboard backlight
+    unavailable: bool,
+    adjust_tx: UnboundedSender<i32>,
+}
+
+impl Widget for KbdBacklight {
+    type Config = ();
+
+    fn dependencies() -> &'static [Dependency] {
+        &[Dependency::SystemDbus("org.freedesktop.UPower")]
+    }
+
+    fn new(cx: &mut Context<Self>, _config: &Self::Config) -> Self {
+        let (adjust_tx, adjust_rx) = mpsc::unbounded();
+        spawn_watched_task(cx, async move |this, cx| task(this, cx, adjust_rx).await).detach();
+
+        Self {
+            error_message: None,
+            brightness: None,
+            max_brightness: None,
+            unavailable: false,
+            adjust_tx,
+        }
+    }
+
+    fn visible(&self) -> bool {
+        !self.unavailable
+    }
+}
+
+impl Render for KbdBacklight {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if let Some(e) = &self.error_message {
+            return widget_wrapper(cx)
+                .child(error_element(cx, e))
+                .into_any_element();
+        }
+        let percentage = match (self.brightness, self.max_brightness) {
+            (Some(brightness), Some(max)) if max > 0 => format!("{}%", brightness * 100 / max),
+            _ => "?".to_owned(),
+        };
+        widget_wrapper(cx)
+            .on_scroll_wheel({
+                let adjust_tx = self.adjust_tx.clone();
+                move |event: &ScrollWheelEvent, window, cx| {
+                    let delta = scroll_delta(event, window, cx);
+                    let step = if delta > px(0.0) {
+                        1
+                    } else if delta < px(0.0) {
+                        -1
+                    } else {
+                        return;
+                    };
+                    if let Err(e) = adjust_tx.unbounded_send(step) {
+                        tracing::error!(error = %e, "Failed to send brightness adjustment");
+                    }
+                }
+            })
+            .flex()
+            .gap(rems(0.25))
+            .child(div().font_family("Material Symbols Rounded").child(""))
+            .child(percentage)
+            .into_any_element()
+    }
+}
+
+enum Event {
+    Changed(zbus::Result<i32>),
+    Adjust(i32),
+}
+
+async fn task(
+    this: WeakEntity<KbdBacklight>,
+    cx: &mut AsyncApp,
+    adjust_rx: UnboundedReceiver<i32>,
+) {
+    let connection = match Connection::system().await {
+        Ok(x) => x,
+        Err(e) => {
+            let _ = this.update(cx, |this, cx| {
+                this.error_message = Some(format!("Failed to connect to system bus: {e}"));
+                cx.notify();
+            });
+            tracing::error!(error = %e, "Failed to connect to system bus");
+            return;
+        }
+    };
+    let upower_proxy = match UpowerProxy::new(&connection).await {
+        Ok(x) => x,
+        Err(e) => {
+            let _ = this.update(cx, |this, cx| {
+                this.error_message = Some(format!("Failed to create UPower proxy: {e}"));
+                cx.notify();
+            });
+            tracing::error!(error = %e, "Failed to create UPower proxy");
+            return;
+        }
+    };
+    let path = match upower_proxy.enumerate_kbd_backlights().await {
+        Ok(paths) => paths.into_iter().next(),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to enumerate keyboard backlights");
+            None
+        }
+    };
+    let Some(path) = path else {
+        tracing::info!("No keyboard backlight, hiding the widget");
+        let _ = this.update(cx, |this, cx| {
+            this.unavailable = true;
+            cx.notify();
+        });
+        return;
+    };
+    let proxy = match UpowerKbdBacklightProxy::new(&connection, path).await {
+        Ok(x) => x,
+        Err(e) => {
+            let _ = this.update(cx, |this, cx| {
+                this.error_message = Some(format!("Failed to create kbd backlight proxy: {e}"));
+                cx.notify();
+            });
+            tracing::error!(error = %e, "Failed to create kbd backlight proxy");
+            return;
+        }
+    };
+    let max_brightness = match proxy.get_max_brightness().await {
+        Ok(x) => x,
+        Err(e) => {
+            let _ = this.update(cx, |this, cx| {
+                this.error_message = Some(format!("Failed to get max brightness: {e}"));
+                cx.notify();
+            });
+            tracing::error!(error = %e, "Failed to get max brightness");
+            return;
+        }
+    };
+    let mut brightness = proxy.get_brightness().await.ok();
+    let _ = this.update(cx, |this, cx| {
+        this.brightness = brightness;
+        this.max_brightness = Some(max_brightness);
+        cx.notify();
+    });
+    let signals = match proxy.receive_brightness_changed().await {
+        Ok(x) => x,
+        Err(e) => {
+            let _ = this.update(cx, |this, cx| {
+                this.error_message = Some(format!("Failed to subscribe to kbd backlight: {e}"));
+                cx.notify();
+            });
+            tracing::error!(error = %e, "Failed to subscribe to kbd backlight");
+            return;
+        }
+    };
+    let mut events = pin!(stream::select(
+        signals.map(|signal| Event::Changed(signal.args().map(|args| args.value))),
+        adjust_rx.map(Event::Adjust),
+    ));
+    while let Some(event) = events.next().await {
+        match event {
+            Event::Changed(Ok(value)) => {
+                tracing::info!(value, "Keyboard backlight changed");
+                brightness = Some(value);
+                let _ = this.update(cx, |this, cx| {
+                    this.brightness = Some(value);
+                    cx.notify();
+                });
+            }
+            Event::Changed(Err(e)) => {
+                tracing::error!(error = %e, "Failed to parse brightness signal");
+            }
+            Event::Adjust(step) => {
+                let Some(current) = brightness else {
+                    continue;
+                };
+                let value = (current + step).clamp(0, max_brightness);
+                if value == current {
+                    continue;
+                }
+                // update right away instead of waiting for the signal
+                match proxy.set_brightness(value).await {
+                    Ok(()) => {
+                        brightness = Some(value);
+                        let _ = this.update(cx, |this, cx| {
+                            this.brightness = Some(value);
+                            cx.notify();
+                        });
+                    }
+                    Err(e) => {
+                        tracing::error!(value, error = %e, "Failed to set keyboard backlight");
+                    }
+                }
+            }
+        }
+    }
+    tracing::warn!("Keyboard backlight event stream ended");
+}
@@ -0,0 +1,46 @@
+use gpui::{Context, IntoElement, ParentElement, Render, Styled, Window, rems};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Color,
+    widget::{Widget, widget_wrapper},
+};
+
+/// Fixed text with an optional icon and color.
+pub struct Label {
+    config: LabelConfig,
+}
+
+impl Widget for Label {
+    type Config = LabelConfig;
+
+    fn new(_cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Debug, PartialEq)]
+pub struct LabelConfig {
+    #[serde(default)]
+    pub text: String,
+    /// Shown before the text
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Color of the text and the icon, the bar's text color if unset
+    #[serde(default)]
+    pub color: Option<Color>,
+}
+
+impl Render for Label {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let mut wrapper = widget_wrapper(cx).flex().items_center().gap(rems(0.25));
+        if let Some(Color(color)) = self.config.color {
+            wrapper = wrapper.text_color(color);
+        }
+        wrapper
+            .children(self.config.icon.clone())
+            .child(self.config.text.clone())
+    }
+}
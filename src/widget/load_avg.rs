@@ -0,0 +1,151 @@
+use std::{fs, thread};
+
+use gpui::{
+    AsyncApp, Context, IntoElement, ParentElement, Render, Styled, WeakEntity, Window, div,
+    prelude::FluentBuilder, rems,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::ThemeConfig,
+    widget::{Widget, error_element, poll_interval, spawn_watched_task, widget_wrapper},
+};
+
+/// Load averages from `/proc/loadavg`, in the urgent color when above the number of cpus.
+pub struct LoadAvg {
+    config: LoadAvgConfig,
+    error_message: Option<String>,
+    /// 1, 5 and 15 minutes
+    load: Option<[f64; 3]>,
+    cpus: Option<usize>,
+}
+
+impl Widget for LoadAvg {
+    type Config = LoadAvgConfig;
+
+    fn new(cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        spawn_watched_task(cx, task).detach();
+
+        let cpus = match thread::available_parallelism() {
+            Ok(x) => Some(x.get()),
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to get the number of cpus");
+                None
+            }
+        };
+
+        Self {
+            config: config.clone(),
+            error_message: None,
+            load: None,
+            cpus,
+        }
+    }
+}
+
+impl Render for LoadAvg {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if let Some(e) = &self.error_message {
+            return widget_wrapper(cx).child(error_element(cx, e));
+        }
+        let Some(load) = self.load else {
+            return widget_wrapper(cx).child("?");
+        };
+        let urgent = cx.global::<ThemeConfig>().urgent();
+        widget_wrapper(cx)
+            .flex()
+            .gap(rems(0.5))
+            .children(self.config.show.iter().map(|period| {
+                let load = load[*period as usize];
+                let overloaded = self
+                    .cpus
+                    .is_some_and(|cpus| self.config.warn && load > cpus as f64);
+                div()
+                    .when(overloaded, |this| this.text_color(urgent))
+                    .child(format!("{load:.2}"))
+            }))
+    }
+}
+
+async fn task(this: WeakEntity<LoadAvg>, cx: &mut AsyncApp) {
+    let Ok(interval) = this.read_with(cx, |this, cx| poll_interval(cx, this.config.poll_interval))
+    else {
+        return;
+    };
+    loop {
+        let result = read_load_avg();
+        let Ok(()) = this.update(cx, |this, cx| {
+            match result {
+                Ok(load) => {
+                    this.load = Some(load);
+                    this.error_message = None;
+                }
+                Err(e) => this.error_message = Some(e),
+            }
+            cx.notify();
+        }) else {
+            return;
+        };
+        cx.background_executor().timer(interval).await;
+    }
+}
+
+fn read_load_avg() -> Result<[f64; 3], String> {
+    let content = fs::read_to_string("/proc/loadavg")
+        .map_err(|e| format!("error while reading /proc/loadavg: {e}"))?;
+    let mut fields = content.split_whitespace().map(|x| {
+        x.parse::<f64>()
+            .map_err(|e| format!("error while parsing `{x}` from /proc/loadavg: {e}"))
+    });
+    let mut next = || {
+        fields
+            .next()
+            .unwrap_or_else(|| Err(format!("too few fields in /proc/loadavg: `{content}`")))
+    };
+    Ok([next()?, next()?, next()?])
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct LoadAvgConfig {
+    /// Which of the load averages to show, in order
+    #[serde(default = "default_show")]
+    pub show: Vec<LoadAvgPeriod>,
+    /// Show a load average in red when it's above the number of cpus
+    #[serde(default = "default_warn")]
+    pub warn: bool,
+    /// Seconds between updates, defaults to `bar.poll_interval`
+    #[serde(default)]
+    pub poll_interval: Option<f64>,
+}
+
+impl Default for LoadAvgConfig {
+    fn default() -> Self {
+        Self {
+            show: default_show(),
+            warn: default_warn(),
+            poll_interval: None,
+        }
+    }
+}
+
+fn default_show() -> Vec<LoadAvgPeriod> {
+    vec![
+        LoadAvgPeriod::One,
+        LoadAvgPeriod::Five,
+        LoadAvgPeriod::Fifteen,
+    ]
+}
+
+fn default_warn() -> bool {
+    true
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadAvgPeriod {
+    #[serde(rename = "1")]
+    One = 0,
+    #[serde(rename = "5")]
+    Five = 1,
+    #[serde(rename = "15")]
+    Fifteen = 2,
+}
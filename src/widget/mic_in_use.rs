@@ -0,0 +1,63 @@
+use gpui::{
+    AsyncApp, Context, IntoElement, ParentElement, Render, Styled, WeakEntity, Window, red,
+};
+
+use crate::widget::{
+    Widget,
+    capture::{CaptureFilter, capture_task},
+    error_element, spawn_watched_task, widget_wrapper,
+};
+
+/// Streams recording from a microphone (or any other audio source), not e.g. from the monitor of
+/// a speaker.
+const FILTER: CaptureFilter = CaptureFilter {
+    media_classes: &["Stream/Input/Audio"],
+    sources: Some(&["Audio/Source"]),
+};
+
+/// A mic glyph while something is listening to the microphone, hidden otherwise.
+pub struct MicInUse {
+    error_message: Option<String>,
+    active: bool,
+}
+
+impl Widget for MicInUse {
+    type Config = ();
+
+    fn new(cx: &mut Context<Self>, _config: &Self::Config) -> Self {
+        spawn_watched_task(cx, task).detach();
+
+        Self {
+            error_message: None,
+            active: false,
+        }
+    }
+
+    fn visible(&self) -> bool {
+        self.active || self.error_message.is_some()
+    }
+}
+
+impl Render for MicInUse {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if let Some(e) = &self.error_message {
+            widget_wrapper(cx).child(error_element(cx, e))
+        } else {
+            widget_wrapper(cx)
+                .font_family("Material Symbols Rounded")
+                .text_color(red())
+                .child("")
+        }
+    }
+}
+
+async fn task(this: WeakEntity<MicInUse>, cx: &mut AsyncApp) {
+    capture_task(this, cx, FILTER, |this, update| match update {
+        Ok(active) => {
+            tracing::info!(active, "Mic in use changed");
+            this.active = active;
+        }
+        Err(e) => this.error_message = Some(e),
+    })
+    .await;
+}
@@ -1,74 +1,1050 @@
-use gpui::{AnyView, AppContext, Context, Div, Render, Styled, black, div, white};
-use serde::{Deserialize, de::DeserializeOwned};
+use std::{
+    any,
+    borrow::Cow,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+    rc::Rc,
+    time::{Duration, TryFromFloatSecsError},
+};
 
+use gpui::{
+    Animation, AnimationExt, AnyElement, AnyView, App, AppContext, AsyncApp, ClipboardItem,
+    Context, Div, ElementId, Entity, EntityId, Global, InteractiveElement, IntoElement,
+    MouseButton, ParentElement, Pixels, Render, ScrollWheelEvent, SharedString, Stateful,
+    StatefulInteractiveElement, Styled, Task, WeakEntity, Window, black, div, ease_in_out,
+    prelude::FluentBuilder, px, rems, white,
+};
+use serde::{
+    Deserialize, Deserializer, Serialize,
+    de::{DeserializeOwned, Error as _},
+};
+use tracing::Instrument;
+
+pub use battery_percent::BatteryPercent;
 pub use bluetooth::Bluetooth;
+pub use brightness::Brightness;
 pub use clock::Clock;
 pub use display::Display;
+pub use do_not_disturb::DoNotDisturb;
+pub use host::Host;
+pub use hyprland::submap::Submap;
+pub use hyprland::window_state::WindowState;
 pub use hyprland::workspaces::HyprlandWorkspace;
+pub use idle::Idle;
+pub use ip_address::IpAddress;
+pub use kbd_backlight::KbdBacklight;
+pub use label::Label;
+pub use load_avg::LoadAvg;
+pub use mic_in_use::MicInUse;
 pub use power::Power;
 pub use power_menu::PowerMenu;
 pub use power_profile::PowerProfile;
 pub use quit::Quit;
+pub use recording::Recording;
+pub use sway::workspaces::SwayWorkspace;
+pub use task_list::TaskList;
+pub use timer::Timer;
 pub use volume::Volume;
+pub use vpn::Vpn;
 pub use workspaces::Workspaces;
 
-use crate::config::Config;
+use crate::{
+    config::{BarConfig, Config, ThemeConfig, WidgetConfig, WidgetStyle},
+    util::spawn_shell,
+    widget::{dependency::Dependency, label::LabelConfig, workspaces::WorkspaceBackend},
+};
 
+pub mod app_icon;
+pub mod battery_percent;
+pub mod battery_source;
 pub mod bluetooth;
+pub mod brightness;
+pub mod capture;
 pub mod clock;
+pub mod dependency;
 pub mod display;
+pub mod do_not_disturb;
+pub mod error;
+pub mod host;
 pub mod hyprland;
+pub mod idle;
+pub mod ip_address;
+pub mod kbd_backlight;
+pub mod label;
+pub mod load_avg;
+pub mod mic_in_use;
+pub mod network_manager;
 pub mod power;
 pub mod power_menu;
 pub mod power_profile;
 pub mod quit;
+pub mod recording;
+pub mod sway;
+pub mod task_list;
+pub mod timer;
 pub mod volume;
+pub mod vpn;
 pub mod workspaces;
 
 // TODO: unify widget naming, like Workspaces or Workspace
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 pub enum WidgetOption {
+    BatteryPercent,
     Bluetooth,
+    Brightness,
     Clock,
     Display,
+    DoNotDisturb,
+    Host,
     HyprlandWorkspace,
+    Idle,
+    IpAddress,
+    KbdBacklight,
+    /// Fixed text, e.g. `{ Label = { text = "work" } }`
+    Label(LabelConfig),
+    LoadAvg,
+    MicInUse,
     Power,
     PowerMenu,
     PowerProfile,
     Quit,
+    Recording,
+    Submap,
+    SwayWorkspace,
+    TaskList,
+    Timer,
     Volume,
+    Vpn,
+    WindowState,
     Workspaces,
 }
 
 impl WidgetOption {
-    pub fn build(&self, cx: &mut impl AppContext, config: &Config) -> AnyView {
+    pub fn id(&self) -> SharedString {
+        match self {
+            Self::BatteryPercent => BatteryPercent::id(),
+            Self::Bluetooth => Bluetooth::id(),
+            Self::Brightness => Brightness::id(),
+            Self::Clock => Clock::id(),
+            Self::Display => Display::id(),
+            Self::DoNotDisturb => DoNotDisturb::id(),
+            Self::Host => Host::id(),
+            Self::HyprlandWorkspace => HyprlandWorkspace::id(),
+            Self::Idle => Idle::id(),
+            Self::IpAddress => IpAddress::id(),
+            Self::KbdBacklight => KbdBacklight::id(),
+            Self::Label(_) => Label::id(),
+            Self::LoadAvg => LoadAvg::id(),
+            Self::MicInUse => MicInUse::id(),
+            Self::Power => Power::id(),
+            Self::PowerMenu => PowerMenu::id(),
+            Self::PowerProfile => PowerProfile::id(),
+            Self::Quit => Quit::id(),
+            Self::Recording => Recording::id(),
+            Self::Submap => Submap::id(),
+            Self::SwayWorkspace => SwayWorkspace::id(),
+            Self::TaskList => TaskList::id(),
+            Self::Timer => Timer::id(),
+            Self::Volume => Volume::id(),
+            Self::Vpn => Vpn::id(),
+            Self::WindowState => WindowState::id(),
+            Self::Workspaces => Workspaces::id(),
+        }
+    }
+    /// See [`Widget::dependencies`].
+    pub fn dependencies(&self, config: &Config) -> &'static [Dependency] {
+        match self {
+            Self::BatteryPercent => BatteryPercent::dependencies(),
+            Self::Bluetooth => Bluetooth::dependencies(),
+            Self::Brightness => Brightness::dependencies(),
+            Self::Clock => Clock::dependencies(),
+            Self::Display => Display::dependencies(),
+            Self::DoNotDisturb => DoNotDisturb::dependencies(),
+            Self::Host => Host::dependencies(),
+            Self::HyprlandWorkspace => HyprlandWorkspace::dependencies(),
+            Self::Idle => Idle::dependencies(),
+            Self::IpAddress => IpAddress::dependencies(),
+            Self::KbdBacklight => KbdBacklight::dependencies(),
+            Self::Label(_) => Label::dependencies(),
+            Self::LoadAvg => LoadAvg::dependencies(),
+            Self::MicInUse => MicInUse::dependencies(),
+            Self::Power => Power::dependencies(),
+            Self::PowerMenu => PowerMenu::dependencies(),
+            Self::PowerProfile => PowerProfile::dependencies(),
+            Self::Quit => Quit::dependencies(),
+            Self::Recording => Recording::dependencies(),
+            Self::Submap => Submap::dependencies(),
+            Self::SwayWorkspace => SwayWorkspace::dependencies(),
+            Self::TaskList => TaskList::dependencies(),
+            Self::Timer => Timer::dependencies(),
+            Self::Volume => Volume::dependencies(),
+            Self::Vpn => Vpn::dependencies(),
+            Self::WindowState => WindowState::dependencies(),
+            // `auto` falls back to the other backends
+            Self::Workspaces => match config.widget.workspaces.backend {
+                WorkspaceBackend::ExtWorkspace => Workspaces::dependencies(),
+                _ => &[],
+            },
+        }
+    }
+    /// Whether the part of the widget config this widget is built from differs, e.g. on a reload.
+    /// A `Label` carries its config in the placement instead.
+    pub fn config_changed(&self, old: &WidgetConfig, new: &WidgetConfig) -> bool {
+        match self {
+            Self::BatteryPercent => old.battery_percent != new.battery_percent,
+            Self::Bluetooth => old.bluetooth != new.bluetooth,
+            Self::Brightness => old.brightness != new.brightness,
+            Self::Clock => old.clock != new.clock,
+            Self::Display => old.display != new.display,
+            Self::DoNotDisturb => old.do_not_disturb != new.do_not_disturb,
+            Self::Host => old.host != new.host,
+            Self::IpAddress => old.ip_address != new.ip_address,
+            Self::HyprlandWorkspace | Self::SwayWorkspace | Self::Workspaces => {
+                old.workspaces != new.workspaces
+            }
+            Self::LoadAvg => old.load_avg != new.load_avg,
+            Self::Power => old.power != new.power,
+            Self::PowerMenu => old.power_menu != new.power_menu,
+            Self::PowerProfile => old.power_profile != new.power_profile,
+            Self::TaskList => old.task_list != new.task_list,
+            Self::Timer => old.timer != new.timer,
+            Self::Volume => old.volume != new.volume,
+            Self::Vpn => old.vpn != new.vpn,
+            Self::Idle
+            | Self::KbdBacklight
+            | Self::Label(_)
+            | Self::MicInUse
+            | Self::Quit
+            | Self::Recording
+            | Self::Submap
+            | Self::WindowState => false,
+        }
+    }
+    pub fn build(&self, cx: &mut App, config: &Config) -> BarWidget {
         match self {
-            Self::Bluetooth => cx.new(|cx| Bluetooth::new(cx, &())).into(),
+            Self::BatteryPercent => cx
+                .new(|cx| BatteryPercent::new(cx, &config.widget.battery_percent))
+                .into(),
+            Self::Bluetooth => cx
+                .new(|cx| Bluetooth::new(cx, &config.widget.bluetooth))
+                .into(),
+            Self::Brightness => cx
+                .new(|cx| Brightness::new(cx, &config.widget.brightness))
+                .into(),
             Self::Clock => cx.new(|cx| Clock::new(cx, &config.widget.clock)).into(),
-            Self::Display => cx.new(|cx| Display::new(cx, &())).into(),
-            Self::HyprlandWorkspace => cx.new(|cx| HyprlandWorkspace::new(cx, &())).into(),
-            Self::Power => cx.new(|cx| Power::new(cx, &())).into(),
-            Self::PowerMenu => cx.new(|cx| PowerMenu::new(cx, &())).into(),
-            Self::PowerProfile => cx.new(|cx| PowerProfile::new(cx, &())).into(),
+            Self::Display => cx.new(|cx| Display::new(cx, &config.widget.display)).into(),
+            Self::DoNotDisturb => cx
+                .new(|cx| DoNotDisturb::new(cx, &config.widget.do_not_disturb))
+                .into(),
+            Self::Host => cx.new(|cx| Host::new(cx, &config.widget.host)).into(),
+            Self::HyprlandWorkspace => cx
+                .new(|cx| HyprlandWorkspace::new(cx, &config.widget.workspaces))
+                .into(),
+            Self::Idle => cx.new(|cx| Idle::new(cx, &())).into(),
+            Self::IpAddress => cx
+                .new(|cx| IpAddress::new(cx, &config.widget.ip_address))
+                .into(),
+            Self::KbdBacklight => cx.new(|cx| KbdBacklight::new(cx, &())).into(),
+            Self::Label(config) => cx.new(|cx| Label::new(cx, config)).into(),
+            Self::LoadAvg => cx
+                .new(|cx| LoadAvg::new(cx, &config.widget.load_avg))
+                .into(),
+            Self::MicInUse => cx.new(|cx| MicInUse::new(cx, &())).into(),
+            Self::Power => cx.new(|cx| Power::new(cx, &config.widget.power)).into(),
+            Self::PowerMenu => cx
+                .new(|cx| PowerMenu::new(cx, &config.widget.power_menu))
+                .into(),
+            Self::PowerProfile => cx
+                .new(|cx| PowerProfile::new(cx, &config.widget.power_profile))
+                .into(),
             Self::Quit => cx.new(|cx| Quit::new(cx, &())).into(),
-            Self::Volume => cx.new(|cx| Volume::new(cx, &())).into(),
-            Self::Workspaces => cx.new(|cx| Workspaces::new(cx, &())).into(),
+            Self::Recording => cx.new(|cx| Recording::new(cx, &())).into(),
+            Self::Submap => cx.new(|cx| Submap::new(cx, &())).into(),
+            Self::SwayWorkspace => cx
+                .new(|cx| SwayWorkspace::new(cx, &config.widget.workspaces))
+                .into(),
+            Self::TaskList => cx
+                .new(|cx| TaskList::new(cx, &config.widget.task_list))
+                .into(),
+            Self::Timer => cx.new(|cx| Timer::new(cx, &config.widget.timer)).into(),
+            Self::Volume => cx.new(|cx| Volume::new(cx, &config.widget.volume)).into(),
+            Self::Vpn => cx.new(|cx| Vpn::new(cx, &config.widget.vpn)).into(),
+            Self::WindowState => cx.new(|cx| WindowState::new(cx, &())).into(),
+            Self::Workspaces => {
+                let config = &config.widget.workspaces;
+                // ext-workspace needs handles from the compositor, the sway one shows the same
+                let backend = if is_preview(cx) {
+                    match config.backend {
+                        WorkspaceBackend::Auto | WorkspaceBackend::ExtWorkspace => {
+                            Some(WorkspaceBackend::Sway)
+                        }
+                        backend => Some(backend),
+                    }
+                } else {
+                    config.backend.resolve()
+                };
+                let mut widget: BarWidget = match backend {
+                    Some(WorkspaceBackend::Auto | WorkspaceBackend::ExtWorkspace) => {
+                        cx.new(|cx| Workspaces::new(cx, config)).into()
+                    }
+                    Some(WorkspaceBackend::Hyprland) => {
+                        cx.new(|cx| HyprlandWorkspace::new(cx, config)).into()
+                    }
+                    Some(WorkspaceBackend::Sway) => {
+                        cx.new(|cx| SwayWorkspace::new(cx, config)).into()
+                    }
+                    None => cx
+                        .new(|_| {
+                            Workspaces::unsupported(
+                                config,
+                                "No supported workspace backend (ext-workspace, hyprland or sway)"
+                                    .to_owned(),
+                            )
+                        })
+                        .into(),
+                };
+                // whichever backend it is, it's still this widget
+                widget.kind = self.id();
+                widget.id = self.id();
+                widget
+            }
+        }
+    }
+}
+
+/// A widget at some position of the bar, either just the widget name:
+///
+/// ```toml
+/// left = ["Clock"]
+/// ```
+///
+/// or a table with the options of this placement:
+///
+/// ```toml
+/// left = [{ widget = "Clock", on_click = "gnome-calendar" }]
+/// ```
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+#[serde(from = "WidgetPlacementRaw", into = "WidgetPlacementRaw")]
+pub struct WidgetPlacement {
+    pub widget: WidgetOption,
+    pub actions: WidgetActions,
+    pub style: PlacementStyle,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(untagged)]
+enum WidgetPlacementRaw {
+    Bare(WidgetOption),
+    Full(FullPlacement),
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct FullPlacement {
+    widget: WidgetOption,
+    #[serde(flatten)]
+    actions: WidgetActions,
+    #[serde(flatten)]
+    style: PlacementStyle,
+}
+
+/// The keys of a [`FullPlacement`], serde can't deny unknown fields next to `flatten`.
+const PLACEMENT_FIELDS: &[&str] = &[
+    "widget",
+    "on_click",
+    "on_right_click",
+    "on_middle_click",
+    "on_scroll_up",
+    "on_scroll_down",
+    "margin",
+    "min_width",
+    "max_width",
+];
+
+// not untagged, which would only say that the placement matched neither form
+impl<'de> Deserialize<'de> for WidgetPlacementRaw {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = toml::Value::deserialize(deserializer)?;
+        match &value {
+            toml::Value::Table(table) if table.contains_key("widget") => {
+                if let Some(key) = table
+                    .keys()
+                    .find(|key| !PLACEMENT_FIELDS.contains(&key.as_str()))
+                {
+                    return Err(D::Error::unknown_field(key, PLACEMENT_FIELDS));
+                }
+                value.try_into().map(Self::Full)
+            }
+            _ => value.try_into().map(Self::Bare),
+        }
+        .map_err(D::Error::custom)
+    }
+}
+
+impl From<WidgetPlacementRaw> for WidgetPlacement {
+    fn from(value: WidgetPlacementRaw) -> Self {
+        match value {
+            WidgetPlacementRaw::Bare(widget) => widget.into(),
+            WidgetPlacementRaw::Full(FullPlacement {
+                widget,
+                actions,
+                style,
+            }) => Self {
+                widget,
+                actions,
+                style,
+            },
+        }
+    }
+}
+
+impl From<WidgetPlacement> for WidgetPlacementRaw {
+    fn from(value: WidgetPlacement) -> Self {
+        let WidgetPlacement {
+            widget,
+            actions,
+            style,
+        } = value;
+        if actions == WidgetActions::default() && style == PlacementStyle::default() {
+            Self::Bare(widget)
+        } else {
+            Self::Full(FullPlacement {
+                widget,
+                actions,
+                style,
+            })
+        }
+    }
+}
+
+impl From<WidgetOption> for WidgetPlacement {
+    fn from(widget: WidgetOption) -> Self {
+        Self {
+            widget,
+            actions: WidgetActions::default(),
+            style: PlacementStyle::default(),
+        }
+    }
+}
+
+impl WidgetPlacement {
+    pub fn build(&self, cx: &mut App, config: &Config) -> BarWidget {
+        let mut widget = self.widget.build(cx, config);
+        widget.actions = Rc::new(self.actions.clone());
+        widget.style = self.style.clone();
+        widget.placement = Some(self.clone());
+        widget
+    }
+}
+
+/// Shell commands to run on pointer events, overriding the built-in behavior of the widget.
+#[derive(Deserialize, Serialize, Default, Clone, Debug, PartialEq)]
+pub struct WidgetActions {
+    #[serde(default)]
+    pub on_click: Option<String>,
+    #[serde(default)]
+    pub on_right_click: Option<String>,
+    #[serde(default)]
+    pub on_middle_click: Option<String>,
+    #[serde(default)]
+    pub on_scroll_up: Option<String>,
+    #[serde(default)]
+    pub on_scroll_down: Option<String>,
+}
+
+impl WidgetActions {
+    fn click(&self, button: MouseButton) -> Option<&String> {
+        match button {
+            MouseButton::Left => self.on_click.as_ref(),
+            MouseButton::Right => self.on_right_click.as_ref(),
+            MouseButton::Middle => self.on_middle_click.as_ref(),
+            _ => None,
+        }
+    }
+    fn has_click(&self) -> bool {
+        self.on_click.is_some() || self.on_right_click.is_some() || self.on_middle_click.is_some()
+    }
+    fn has_scroll(&self) -> bool {
+        self.on_scroll_up.is_some() || self.on_scroll_down.is_some()
+    }
+}
+
+/// Spacing and size of a widget at some placement, in pixels.
+#[derive(Deserialize, Serialize, Default, Clone, Debug, PartialEq)]
+pub struct PlacementStyle {
+    #[serde(default)]
+    pub margin: Option<f32>,
+    #[serde(default)]
+    pub min_width: Option<f32>,
+    /// Overflowing text is truncated with an ellipsis
+    #[serde(default)]
+    pub max_width: Option<f32>,
+}
+
+/// A built widget as the bar holds it.
+pub struct BarWidget {
+    /// Unique in the bar, see [`number_ids`]
+    id: SharedString,
+    /// The [`Widget::id`]
+    kind: SharedString,
+    view: AnyView,
+    visible: Rc<dyn Fn(&App) -> bool>,
+    activate: Rc<dyn Fn(&mut Window, &mut App)>,
+    activatable: Rc<dyn Fn(&App) -> bool>,
+    actions: Rc<WidgetActions>,
+    scroll: Rc<RefCell<ScrollAccumulator>>,
+    style: PlacementStyle,
+    /// What the widget was built from, `None` if it's not from the config
+    placement: Option<WidgetPlacement>,
+}
+
+impl BarWidget {
+    pub fn id(&self) -> &SharedString {
+        &self.id
+    }
+    pub fn placement(&self) -> Option<&WidgetPlacement> {
+        self.placement.as_ref()
+    }
+    pub fn visible(&self, cx: &App) -> bool {
+        (self.visible)(cx)
+    }
+    /// Whether [`Self::activate`] does anything.
+    pub fn activatable(&self, cx: &App) -> bool {
+        self.actions.click(MouseButton::Left).is_some() || (self.activatable)(cx)
+    }
+    /// Run the `on_click` command of the placement if there is one, otherwise
+    /// [`Widget::activate`].
+    pub fn activate(&self, window: &mut Window, cx: &mut App) {
+        if let Some(command) = self.actions.click(MouseButton::Left) {
+            let _ = spawn_shell(command);
+        } else {
+            // deferred, the bar is being updated and e.g. a popup reads it to place itself
+            let activate = self.activate.clone();
+            window.defer(cx, move |window, cx| activate(window, cx));
+        }
+    }
+    /// The widget wrapped with the handlers and style of its placement, dimmed if it's stale.
+    pub fn render(&self, cx: &App) -> AnyElement {
+        div()
+            .when(is_stale(self.view.entity_id(), cx), |this| {
+                this.opacity(0.5)
+            })
+            // right clicking the empty part of the bar opens its menu, not on a widget
+            .on_mouse_down(MouseButton::Right, |_, _, cx| cx.stop_propagation())
+            .when_some(self.style.margin, |this, margin| this.m(px(margin)))
+            .when_some(self.style.min_width, |this, min_width| {
+                this.min_w(px(min_width))
+            })
+            .when_some(self.style.max_width, |this, max_width| {
+                this.max_w(px(max_width)).truncate()
+            })
+            .when(self.actions.has_click(), |this| {
+                let actions = self.actions.clone();
+                // capture phase, so a configured command wins over the widget's own click handler
+                this.capture_any_mouse_down(move |event, _window, cx| {
+                    if let Some(command) = actions.click(event.button) {
+                        let _ = spawn_shell(command);
+                        cx.stop_propagation();
+                    }
+                })
+            })
+            .when(self.actions.has_scroll(), |this| {
+                let actions = self.actions.clone();
+                let scroll = self.scroll.clone();
+                this.on_scroll_wheel(move |event, window, cx| {
+                    let delta = scroll_delta(event, window, cx);
+                    let steps = scroll.borrow_mut().add(delta, window.line_height());
+                    let command = if steps > 0 {
+                        actions.on_scroll_up.as_ref()
+                    } else {
+                        actions.on_scroll_down.as_ref()
+                    };
+                    if let Some(command) = command {
+                        for _ in 0..steps.unsigned_abs() {
+                            let _ = spawn_shell(command);
+                        }
+                    }
+                })
+            })
+            .child(self.view.clone())
+            .into_any_element()
+    }
+}
+
+/// Give each widget of the bar a unique id for `move`, the [`Widget::id`] numbered from the second
+/// widget of the same kind, e.g. `clock` and `clock_2`, in the order of the config.
+pub fn number_ids<'a>(widgets: impl IntoIterator<Item = &'a mut BarWidget>) {
+    let mut widgets = widgets.into_iter().collect::<Vec<_>>();
+    let ids = numbered_ids(widgets.iter().map(|x| x.kind.clone()));
+    for (widget, id) in widgets.iter_mut().zip(ids) {
+        widget.id = id;
+    }
+}
+
+fn numbered_ids(kinds: impl IntoIterator<Item = SharedString>) -> Vec<SharedString> {
+    let mut counts = HashMap::<SharedString, usize>::new();
+    kinds
+        .into_iter()
+        .map(|kind| {
+            let count = counts.entry(kind.clone()).or_default();
+            *count += 1;
+            if *count == 1 {
+                kind
+            } else {
+                format!("{kind}_{count}").into()
+            }
+        })
+        .collect()
+}
+
+impl<W: Widget> From<Entity<W>> for BarWidget {
+    fn from(entity: Entity<W>) -> Self {
+        Self {
+            id: W::id(),
+            kind: W::id(),
+            view: entity.clone().into(),
+            activate: Rc::new({
+                let entity = entity.clone();
+                move |window, cx| entity.update(cx, |this, cx| this.activate(window, cx))
+            }),
+            activatable: Rc::new({
+                let entity = entity.clone();
+                move |cx| entity.read(cx).activatable()
+            }),
+            visible: Rc::new(move |cx| entity.read(cx).visible()),
+            actions: Rc::default(),
+            scroll: Rc::default(),
+            style: PlacementStyle::default(),
+            placement: None,
+        }
+    }
+}
+
+/// Maximum number of characters of an error message shown in the bar, see
+/// `BarConfig::max_error_length`.
+pub fn max_error_length(cx: &App) -> usize {
+    cx.global::<BarConfig>().max_error_length
+}
+
+/// An error message shown in a widget, cut off at [`max_error_length`] with the full message in a
+/// tooltip.
+pub fn error_element(cx: &App, message: &str) -> Stateful<Div> {
+    let message = message.trim();
+    let shown = truncate(message, max_error_length(cx));
+    let full = matches!(shown, Cow::Owned(_)).then(|| SharedString::from(message.to_owned()));
+    div()
+        .id("error")
+        .child(shown.into_owned())
+        .when_some(full, |this, full| {
+            this.tooltip(move |_, cx| cx.new(|_| ErrorTooltip(full.clone())).into())
+        })
+}
+
+/// The full message of a cut off error.
+struct ErrorTooltip(SharedString);
+
+impl Render for ErrorTooltip {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .px(rems(0.5))
+            .rounded(rems(0.25))
+            .text_color(white())
+            .bg(black())
+            .child(self.0.clone())
+    }
+}
+
+/// Truncate `text` to at most `max_chars` characters (not bytes), ending with `…` if anything is cut.
+pub fn truncate(text: &str, max_chars: usize) -> Cow<'_, str> {
+    if max_chars == 0 {
+        return Cow::Borrowed("");
+    }
+    match text.char_indices().nth(max_chars - 1) {
+        Some((end, _)) if text[end..].chars().nth(1).is_some() => {
+            Cow::Owned(format!("{}…", &text[..end]))
         }
+        _ => Cow::Borrowed(text),
     }
 }
 
-pub fn widget_wrapper() -> Div {
+/// Fade `element` in every time `key` changes, if transitions are enabled in the bar config.
+pub fn transition(
+    id: impl Into<SharedString>,
+    key: impl Hash,
+    element: impl IntoElement,
+    cx: &App,
+) -> AnyElement {
+    if !cx.global::<BarConfig>().transitions {
+        return element.into_any_element();
+    }
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    // a new id restarts the animation
     div()
+        .child(element)
+        .with_animation(
+            ElementId::NamedInteger(id.into(), hasher.finish()),
+            Animation::new(Duration::from_millis(200)).with_easing(ease_in_out),
+            |element, delta| element.opacity(delta),
+        )
+        .into_any_element()
+}
+
+/// Spawn a task of a widget inside a `widget` span with the id of the widget, so its logs can be
+/// filtered.
+pub fn spawn_task<W, R>(
+    cx: &Context<W>,
+    task: impl AsyncFnOnce(WeakEntity<W>, &mut AsyncApp) -> R + 'static,
+) -> Task<R>
+where
+    W: Widget,
+    R: 'static,
+{
+    let span = tracing::info_span!("widget", name = %W::id());
+    cx.spawn(async move |this, cx| task(this, cx).instrument(span).await)
+}
+
+/// Widgets whose task ended while they were still in the bar, see [`spawn_watched_task`].
+#[derive(Default)]
+struct StaleWidgets(HashSet<EntityId>);
+
+impl Global for StaleWidgets {}
+
+/// How a task of [`spawn_watched_task`] ended, tasks that return nothing end unexpectedly.
+pub enum TaskEnd {
+    /// There is nothing to watch, e.g. the widget hid itself since the service isn't there
+    Done,
+    Unexpected,
+}
+
+impl From<()> for TaskEnd {
+    fn from((): ()) -> Self {
+        Self::Unexpected
+    }
+}
+
+/// [`spawn_task`] for the task that keeps a widget updated (e.g. listening to a service), which
+/// should run as long as the widget. If it ends anyway without [`TaskEnd::Done`] the widget won't
+/// update anymore, so that is logged and the widget is dimmed in the bar.
+pub fn spawn_watched_task<W, R>(
+    cx: &Context<W>,
+    task: impl AsyncFnOnce(WeakEntity<W>, &mut AsyncApp) -> R + 'static,
+) -> Task<()>
+where
+    W: Widget,
+    R: Into<TaskEnd> + 'static,
+{
+    let span = tracing::info_span!("widget", name = %W::id());
+    cx.spawn(async move |this, cx| {
+        let end = task(this.clone(), cx).instrument(span.clone()).await.into();
+        // ending after the widget is dropped is expected
+        let Some(entity) = this.upgrade() else {
+            return;
+        };
+        if let TaskEnd::Done = end {
+            span.in_scope(|| tracing::info!("The widget task is done"));
+            return;
+        }
+        span.in_scope(|| tracing::warn!("The widget task ended, the widget is stale now"));
+        cx.update(|cx| {
+            let id = entity.entity_id();
+            cx.default_global::<StaleWidgets>().0.insert(id);
+            // e.g. a reload dropped the widget
+            cx.observe_release(&entity, move |_, cx| {
+                cx.default_global::<StaleWidgets>().0.remove(&id);
+            })
+            .detach();
+            cx.refresh_windows();
+        });
+    })
+}
+
+fn is_stale(id: EntityId, cx: &App) -> bool {
+    cx.try_global::<StaleWidgets>()
+        .is_some_and(|stale| stale.0.contains(&id))
+}
+
+/// How long a widget shows `Copied` after copying its text, see [`copy_to_clipboard`].
+const COPIED_FEEDBACK: Duration = Duration::from_millis(1200);
+
+/// Copy `text` for the widgets with `copy_on_click`. `copied` is the flag the widget shows
+/// `Copied` for, it's cleared after [`COPIED_FEEDBACK`].
+pub fn copy_to_clipboard<W: Widget>(
+    text: String,
+    this: &mut W,
+    copied: fn(&mut W) -> &mut bool,
+    cx: &mut Context<W>,
+) {
+    cx.write_to_clipboard(ClipboardItem::new_string(text.clone()));
+    // writing doesn't fail, e.g. without a data device manager, so check that it took
+    if cx.read_from_clipboard().and_then(|x| x.text()).as_ref() != Some(&text) {
+        tracing::warn!(id = %W::id(), "Failed to copy to the clipboard");
+        return;
+    }
+    *copied(this) = true;
+    cx.notify();
+    cx.spawn(async move |this, cx| {
+        cx.background_executor().timer(COPIED_FEEDBACK).await;
+        let _ = this.update(cx, |this, cx| {
+            *copied(this) = false;
+            cx.notify();
+        });
+    })
+    .detach();
+}
+
+/// Set by `--preview`, the widgets that support it show fake data instead of connecting to their
+/// services.
+pub struct Preview;
+
+impl Global for Preview {}
+
+pub fn is_preview(cx: &App) -> bool {
+    cx.has_global::<Preview>()
+}
+
+/// The vertical scroll distance of `event` for the widgets adjusted by scrolling, flipped with
+/// `BarConfig::natural_scroll`.
+pub fn scroll_delta(event: &ScrollWheelEvent, window: &Window, cx: &App) -> Pixels {
+    let delta = event.delta.pixel_delta(window.line_height()).y;
+    if cx.global::<BarConfig>().natural_scroll {
+        -delta
+    } else {
+        delta
+    }
+}
+
+/// Adds up scroll distances into whole steps, a touchpad sends many small deltas for one swipe
+/// which would otherwise be one action each.
+#[derive(Default, Debug)]
+pub struct ScrollAccumulator {
+    pending: f32,
+}
+
+impl ScrollAccumulator {
+    /// Add `delta` and take the whole `step`s scrolled so far, positive for up. The rest is kept
+    /// for the next delta in the same direction.
+    pub fn add(&mut self, delta: Pixels, step: Pixels) -> i32 {
+        let delta = f32::from(delta);
+        let step = f32::from(step).max(1.0);
+        if self.pending * delta < 0.0 {
+            self.pending = 0.0;
+        }
+        self.pending += delta;
+        let steps = (self.pending / step).trunc();
+        self.pending -= steps * step;
+        steps as i32
+    }
+}
+
+/// The shortest interval of anything the config repeats on a timer, e.g. `timer(Duration::ZERO)`
+/// would keep the bar busy.
+pub const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// `seconds` of `option` from the config as a duration of at least [`MIN_INTERVAL`].
+pub fn interval_from_secs(option: &str, seconds: f64) -> Result<Duration, TryFromFloatSecsError> {
+    let interval = Duration::try_from_secs_f64(seconds)?;
+    if interval < MIN_INTERVAL {
+        tracing::warn!(
+            seconds,
+            "`{option}` is shorter than {MIN_INTERVAL:?}, using that instead"
+        );
+        return Ok(MIN_INTERVAL);
+    }
+    Ok(interval)
+}
+
+/// How often a polled widget updates, its own `poll_interval` (in seconds) wins over the one of
+/// the bar.
+pub fn poll_interval(cx: &App, own: Option<f64>) -> Duration {
+    let seconds = own.unwrap_or(cx.global::<BarConfig>().poll_interval);
+    interval_from_secs("poll_interval", seconds).unwrap_or_else(|e| {
+        tracing::warn!(seconds, error = %e, "Invalid poll_interval, using 5 seconds");
+        Duration::from_secs(5)
+    })
+}
+
+pub fn widget_wrapper(cx: &App) -> Div {
+    let config = cx.global::<BarConfig>();
+    let wrapper = div()
         .text_color(white())
-        .bg(black())
-        .rounded_lg()
-        .px_2()
-        .py_0p5()
+        .px(rems(config.widget_padding_x))
+        .py(rems(config.widget_padding_y));
+    match config.widget_style {
+        WidgetStyle::Pill => wrapper
+            .bg(cx.global::<ThemeConfig>().pill_background())
+            .rounded(rems(config.widget_rounding)),
+        // the bar draws the background instead
+        WidgetStyle::Flat => wrapper,
+    }
 }
 
 pub trait Widget: Render {
     type Config: Default + DeserializeOwned;
 
     fn new(cx: &mut Context<Self>, config: &Self::Config) -> Self;
+
+    /// Stable name used to target the widget, e.g. `"clock"` or `"power_profile"`.
+    fn id() -> SharedString {
+        let type_name = any::type_name::<Self>();
+        let name = type_name.rsplit("::").next().unwrap_or(type_name);
+        let mut id = String::with_capacity(name.len() + 4);
+        for (index, c) in name.char_indices() {
+            if c.is_uppercase() {
+                if index != 0 {
+                    id.push('_');
+                }
+                id.extend(c.to_lowercase());
+            } else {
+                id.push(c);
+            }
+        }
+        id.into()
+    }
+
+    /// Services the widget needs at runtime, checked on startup to warn about widgets that will
+    /// likely fail.
+    fn dependencies() -> &'static [Dependency] {
+        &[]
+    }
+
+    /// Whether the widget should take up space in the bar, e.g. a battery widget on a desktop or a
+    /// widget hidden by a flag in its config like `hide_when_off`.
+    fn visible(&self) -> bool {
+        true
+    }
+
+    /// What Enter does when the widget is selected with the keyboard, see
+    /// `BarConfig::keyboard_focus`. Usually the same as a left click.
+    fn activate(&mut self, _window: &mut Window, _cx: &mut Context<Self>) {}
+
+    /// Whether [`Self::activate`] does anything, the others are skipped when selecting widgets
+    /// with the keyboard.
+    fn activatable(&self) -> bool {
+        false
+    }
+}
+
+/// Build `W` in a test app as in `--preview`, so it shows fixed states instead of connecting to
+/// services, and draw it once.
+#[cfg(test)]
+pub fn preview_widget<W: Widget>(cx: &mut gpui::TestAppContext, config: &W::Config) -> Entity<W> {
+    cx.update(|cx| {
+        cx.set_global(BarConfig::default());
+        cx.set_global(ThemeConfig::default());
+        cx.set_global(Preview);
+    });
+    let widget = cx.new(|cx| W::new(cx, config));
+    // e.g. the fake battery source sends its updates
+    cx.run_until_parked();
+    draw_widget(cx, &widget);
+    widget
+}
+
+/// Draw `widget` in a window the size of a bar, to catch panics in `render` (e.g. a missing
+/// global) after its state changed.
+#[cfg(test)]
+pub fn draw_widget<W: Widget>(cx: &mut gpui::TestAppContext, widget: &Entity<W>) {
+    let widget = widget.clone();
+    cx.add_empty_window().draw(
+        gpui::point(px(0.0), px(0.0)),
+        gpui::size(px(800.0), px(40.0)),
+        |_, _| widget.into_any_element(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Placements {
+        left: Vec<WidgetPlacement>,
+    }
+
+    fn parse(toml: &str) -> Result<Vec<WidgetPlacement>, toml::de::Error> {
+        toml::from_str::<Placements>(toml).map(|x| x.left)
+    }
+
+    #[test]
+    fn placement_forms() {
+        let placements = parse(
+            r#"left = [
+                "Clock",
+                { Label = { text = "work" } },
+                { widget = "Clock", margin = 4.0 },
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(placements[0], WidgetOption::Clock.into());
+        assert!(matches!(placements[1].widget, WidgetOption::Label(_)));
+        assert_eq!(placements[2].widget, WidgetOption::Clock);
+        assert_eq!(placements[2].style.margin, Some(4.0));
+    }
+
+    #[test]
+    fn placement_errors() {
+        let e = parse(r#"left = [{ widget = "Clock", on_clik = "x" }]"#).unwrap_err();
+        assert!(e.to_string().contains("on_clik"), "{e}");
+        let e = parse(r#"left = [{ widget = "Clok" }]"#).unwrap_err();
+        assert!(e.to_string().contains("Clok"), "{e}");
+        let e = parse(r#"left = ["Clok"]"#).unwrap_err();
+        assert!(e.to_string().contains("Clok"), "{e}");
+    }
+
+    #[test]
+    fn placement_round_trip() {
+        let placements =
+            parse(r#"left = ["Clock", { widget = "Clock", on_click = "x", max_width = 100.0 }]"#)
+                .unwrap();
+        for placement in placements {
+            let value = toml::Value::try_from(&placement).unwrap();
+            assert_eq!(value.try_into::<WidgetPlacement>().unwrap(), placement);
+        }
+    }
+
+    #[test]
+    fn truncate_multibyte() {
+        assert_eq!(truncate("日本語のテキスト", 3), "日本…");
+        assert_eq!(truncate("🔋🔋🔋🔋", 2), "🔋…");
+        assert_eq!(truncate("日本語", 3), "日本語");
+    }
+
+    #[test]
+    fn truncate_at_max_chars() {
+        assert_eq!(truncate("abc", 3), "abc");
+        assert!(matches!(truncate("abc", 3), Cow::Borrowed(_)));
+        assert_eq!(truncate("abcd", 3), "ab…");
+        assert_eq!(truncate("ab", 3), "ab");
+        assert_eq!(truncate("", 3), "");
+    }
+
+    #[test]
+    fn truncate_tiny_max_chars() {
+        assert_eq!(truncate("abc", 0), "");
+        assert_eq!(truncate("", 0), "");
+        assert_eq!(truncate("abc", 1), "…");
+        assert_eq!(truncate("a", 1), "a");
+        assert_eq!(truncate("日", 1), "日");
+    }
+
+    #[test]
+    fn repeated_ids_are_numbered() {
+        let ids = numbered_ids(["clock", "volume", "clock", "clock"].map(SharedString::from));
+        let ids = ids.iter().map(|x| x.as_ref()).collect::<Vec<&str>>();
+        assert_eq!(ids, ["clock", "volume", "clock_2", "clock_3"]);
+    }
+
+    #[test]
+    fn scroll_accumulates_small_deltas() {
+        let mut scroll = ScrollAccumulator::default();
+        assert_eq!(scroll.add(px(8.0), px(20.0)), 0);
+        assert_eq!(scroll.add(px(8.0), px(20.0)), 0);
+        assert_eq!(scroll.add(px(8.0), px(20.0)), 1);
+        assert_eq!(scroll.add(px(-8.0), px(20.0)), 0);
+        assert_eq!(scroll.add(px(-15.0), px(20.0)), -1);
+        assert_eq!(scroll.add(px(65.0), px(20.0)), 3);
+        assert_eq!(scroll.add(px(0.0), px(20.0)), 0);
+    }
 }
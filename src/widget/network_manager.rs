@@ -0,0 +1,163 @@
+//! NetworkManager over the system bus, for the widgets about the network.
+
+use std::collections::HashMap;
+
+use zbus::{
+    Connection, proxy,
+    zvariant::{OwnedObjectPath, OwnedValue},
+};
+
+/// The first IPv4 and the first global IPv6 address of `interface`, or of the primary connection
+/// if `None`. Either is `None` when there is no such address, e.g. while disconnected.
+pub async fn addresses(
+    connection: &Connection,
+    interface: Option<&str>,
+) -> zbus::Result<(Option<String>, Option<String>)> {
+    let network_manager = NetworkManagerProxy::new(connection).await?;
+    let (ip4_config, ip6_config) = match interface {
+        Some(interface) => {
+            let path = network_manager.get_device_by_ip_iface(interface).await?;
+            let device = DeviceProxy::builder(connection).path(path)?.build().await?;
+            (device.ip4_config().await?, device.ip6_config().await?)
+        }
+        None => {
+            let path = network_manager.primary_connection().await?;
+            // no primary connection
+            if path.as_str() == "/" {
+                return Ok((None, None));
+            }
+            let active = ActiveConnectionProxy::builder(connection)
+                .path(path)?
+                .build()
+                .await?;
+            (active.ip4_config().await?, active.ip6_config().await?)
+        }
+    };
+
+    let ipv4 = if ip4_config.as_str() == "/" {
+        None
+    } else {
+        let config = Ip4ConfigProxy::builder(connection)
+            .path(ip4_config)?
+            .build()
+            .await?;
+        config.address_data().await?.iter().find_map(address)
+    };
+    let ipv6 = if ip6_config.as_str() == "/" {
+        None
+    } else {
+        let config = Ip6ConfigProxy::builder(connection)
+            .path(ip6_config)?
+            .build()
+            .await?;
+        config
+            .address_data()
+            .await?
+            .iter()
+            .filter_map(address)
+            .find(|x| !x.starts_with("fe80:"))
+    };
+    Ok((ipv4, ipv6))
+}
+
+/// The names of the VPNs among the `active_connections`, including WireGuard connections which
+/// NetworkManager doesn't count as VPNs. Connections that are gone by now are skipped.
+pub async fn active_vpns(
+    connection: &Connection,
+    active_connections: &[OwnedObjectPath],
+) -> Vec<String> {
+    let mut names = Vec::new();
+    for path in active_connections {
+        let result = async {
+            let active = ActiveConnectionProxy::builder(connection)
+                .path(path.clone())?
+                .build()
+                .await?;
+            if active.vpn().await? || active.type_().await? == "wireguard" {
+                active.id().await.map(Some)
+            } else {
+                Ok(None)
+            }
+        }
+        .await;
+        match result {
+            Ok(Some(name)) => names.push(name),
+            Ok(None) => (),
+            Err(e) => {
+                tracing::warn!(path = path.as_str(), error = %e, "Failed to read an active connection");
+            }
+        }
+    }
+    names
+}
+
+fn address(data: &HashMap<String, OwnedValue>) -> Option<String> {
+    data.get("address")?.try_clone().ok()?.try_into().ok()
+}
+
+// <https://networkmanager.dev/docs/api/latest/gdbus-org.freedesktop.NetworkManager.html>
+#[proxy(
+    interface = "org.freedesktop.NetworkManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager"
+)]
+pub trait NetworkManager {
+    fn get_device_by_ip_iface(&self, iface: &str) -> zbus::Result<OwnedObjectPath>;
+    /// `/` if there is none
+    #[zbus(property)]
+    fn primary_connection(&self) -> zbus::Result<OwnedObjectPath>;
+    #[zbus(property)]
+    fn active_connections(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+// <https://networkmanager.dev/docs/api/latest/gdbus-org.freedesktop.NetworkManager.Connection.Active.html>
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Connection.Active",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait ActiveConnection {
+    /// The name of the connection
+    #[zbus(property)]
+    fn id(&self) -> zbus::Result<String>;
+    /// e.g. `802-11-wireless`, `vpn` or `wireguard`
+    #[zbus(property, name = "Type")]
+    fn type_(&self) -> zbus::Result<String>;
+    #[zbus(property)]
+    fn vpn(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn ip4_config(&self) -> zbus::Result<OwnedObjectPath>;
+    #[zbus(property)]
+    fn ip6_config(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+// <https://networkmanager.dev/docs/api/latest/gdbus-org.freedesktop.NetworkManager.Device.html>
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Device",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait Device {
+    #[zbus(property)]
+    fn ip4_config(&self) -> zbus::Result<OwnedObjectPath>;
+    #[zbus(property)]
+    fn ip6_config(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+// <https://networkmanager.dev/docs/api/latest/gdbus-org.freedesktop.NetworkManager.IP4Config.html>
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.IP4Config",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait Ip4Config {
+    #[zbus(property)]
+    fn address_data(&self) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+}
+
+// <https://networkmanager.dev/docs/api/latest/gdbus-org.freedesktop.NetworkManager.IP6Config.html>
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.IP6Config",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait Ip6Config {
+    #[zbus(property)]
+    fn address_data(&self) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+}
@@ -1,214 +1,444 @@
 use std::time::Duration;
 
-use futures::{StreamExt, join};
+use futures::{StreamExt, channel::mpsc, join};
 use gpui::{
-    AsyncApp, Context, IntoElement, ParentElement, Render, Styled, WeakEntity, Window, div, rems,
+    Animation, AnimationExt, AnyElement, AsyncApp, Context, Div, InteractiveElement, IntoElement,
+    MouseButton, MouseDownEvent, ParentElement, Pixels, Render, Styled, WeakEntity, Window, div,
+    green, prelude::FluentBuilder, px, rems, size,
 };
+use serde::{Deserialize, Serialize};
 use zbus::{
-    Connection, proxy,
+    proxy,
     zvariant::{ObjectPath, OwnedObjectPath},
 };
 
-use crate::widget::{Widget, widget_wrapper};
+use crate::{
+    popup::{Popup, PopupAnchor},
+    widget::{
+        Widget,
+        battery_source::{
+            BatteryDevice, BatterySource, BatteryUpdate, FakeBatterySource, UpowerBatterySource,
+            is_battery,
+        },
+        dependency::Dependency,
+        error::WidgetError,
+        error_element, is_preview, spawn_task, spawn_watched_task, widget_wrapper,
+    },
+};
+
+/// How long each glyph of the charging animation is shown.
+const CHARGING_FRAME: Duration = Duration::from_millis(600);
 
 #[derive(Clone)]
 pub struct Power {
-    error_message: Option<String>,
+    no_battery: NoBattery,
+    show_wattage: bool,
+    hide_on_ac: bool,
+    hide_when_full: bool,
+    show_icon: bool,
+    /// Sorted by `min`, highest first
+    glyphs: Vec<BatteryGlyph>,
+    animate_charging: bool,
+    /// Whether `device` is set, which can be a peripheral instead of a battery
+    configured_device: bool,
+    error: Option<WidgetError>,
     type_: Option<u32>,
+    is_present: Option<bool>,
+    on_battery: Option<bool>,
     state: Option<u32>,
     percentage: Option<f64>,
     time_to_empty: Option<Duration>,
     time_to_full: Option<Duration>,
+    energy_rate: Option<f64>,
+    device: Option<BatteryDevice>,
 }
 
 impl Widget for Power {
-    type Config = ();
+    type Config = PowerConfig;
+
+    fn dependencies() -> &'static [Dependency] {
+        &[Dependency::SystemDbus("org.freedesktop.UPower")]
+    }
+
+    fn new(cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        if is_preview(cx) {
+            let source = FakeBatterySource::discharging(42.0);
+            spawn_task(cx, async move |this, cx| task(this, cx, source).await).detach();
+        } else {
+            let source = UpowerBatterySource {
+                device: config.device.clone(),
+            };
+            spawn_watched_task(cx, async move |this, cx| task(this, cx, source).await).detach();
+        }
+        Self::from_config(config)
+    }
 
-    fn new(cx: &mut Context<Self>, _config: &Self::Config) -> Self {
-        cx.spawn(task).detach();
+    fn visible(&self) -> bool {
+        if let Some(e) = &self.error {
+            return !e.hides_widget();
+        }
+        if self.hide_on_ac && self.on_battery == Some(false) {
+            return false;
+        }
+        // 4: fully charged
+        if self.hide_when_full && self.state == Some(4) {
+            return false;
+        }
+        self.no_battery == NoBattery::Show || self.has_battery() != Some(false)
+    }
+
+    fn activate(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        // there is no pointer, the popup opens at the start of the bar
+        self.open_health(px(0.0), window, cx);
+    }
 
+    fn activatable(&self) -> bool {
+        self.has_battery() == Some(true) && self.device.is_some()
+    }
+}
+
+impl Power {
+    /// Nothing known yet, until the updates of a [`BatterySource`] come in.
+    fn from_config(config: &PowerConfig) -> Self {
         Self {
-            error_message: None,
+            no_battery: config.no_battery,
+            show_wattage: config.show_wattage,
+            hide_on_ac: config.hide_on_ac,
+            hide_when_full: config.hide_when_full,
+            show_icon: config.show_icon,
+            glyphs: sorted_glyphs(&config.glyphs),
+            animate_charging: config.animate_charging,
+            configured_device: config.device.is_some(),
+            error: None,
             type_: None,
+            is_present: None,
+            on_battery: None,
             state: None,
             percentage: None,
             time_to_empty: None,
             time_to_full: None,
+            energy_rate: None,
+            device: None,
         }
     }
-}
-
-impl Render for Power {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
-        if let Some(e) = &self.error_message {
-            widget_wrapper().child(e.clone())
-        } else if self.type_ == Some(2)
+    fn apply(&mut self, update: BatteryUpdate) {
+        match update {
+            BatteryUpdate::Error(x) => self.error = x,
+            BatteryUpdate::Device(x) => self.device = Some(x),
+            BatteryUpdate::OnBattery(x) => self.on_battery = Some(x),
+            BatteryUpdate::Type(x) => self.type_ = Some(x),
+            BatteryUpdate::IsPresent(x) => self.is_present = Some(x),
+            BatteryUpdate::State(x) => self.state = Some(x),
+            BatteryUpdate::Percentage(x) => self.percentage = Some(x),
+            BatteryUpdate::TimeToEmpty(x) => self.time_to_empty = x,
+            BatteryUpdate::TimeToFull(x) => self.time_to_full = x,
+            BatteryUpdate::EnergyRate(x) => self.energy_rate = Some(x),
+        }
+    }
+    /// `None` while UPower hasn't told us the device type yet.
+    fn has_battery(&self) -> Option<bool> {
+        // desktops still have a DisplayDevice, but it is not a battery (or not present)
+        Some(is_battery(self.type_?, self.configured_device) && self.is_present != Some(false))
+    }
+    /// The power draw, e.g. `↓ 12.3 W`, if enabled and UPower knows it.
+    fn wattage(&self, state: u32) -> Option<String> {
+        let arrow = match state {
+            1 => "↑",
+            2 => "↓",
+            _ => return None,
+        };
+        let energy_rate = self.energy_rate.filter(|&x| self.show_wattage && x > 0.0)?;
+        Some(format!("{arrow} {energy_rate:.1} W"))
+    }
+    /// Show the battery health in a popup, these change slowly so they are only fetched here.
+    fn open_health(&self, x: Pixels, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(device) = self
+            .device
+            .clone()
+            .filter(|_| self.has_battery() == Some(true))
+        else {
+            return;
+        };
+        let anchor = PopupAnchor::new(window, cx);
+        spawn_task(cx, async move |_, cx| {
+            let lines = device.health().await;
+            cx.update(|cx| {
+                Popup::open(anchor, x, size(px(240.0), px(80.0)), cx, move |_, _| {
+                    div()
+                        .flex()
+                        .flex_col()
+                        .children(lines.clone())
+                        .into_any_element()
+                });
+            });
+        })
+        .detach();
+    }
+    /// Ask UPower to re-read the battery, the new values come through the property streams.
+    fn refresh(&self, cx: &mut Context<Self>) {
+        let Some(device) = self.device.clone() else {
+            tracing::info!("Not connected to UPower yet, nothing to refresh");
+            return;
+        };
+        spawn_task(cx, async move |_, _| {
+            tracing::info!("Refreshing the battery");
+            device.refresh().await;
+        })
+        .detach();
+    }
+    fn glyph(&self, percentage: f64, charging: bool) -> String {
+        battery_glyph(&self.glyphs, percentage, charging).to_owned()
+    }
+    /// The charging glyph, filling up from the current level to full with `animate_charging`.
+    fn charging_icon(&self, percentage: f64) -> AnyElement {
+        let icon = div().font_family("Material Symbols Rounded");
+        let Some(level) = self
+            .glyphs
+            .iter()
+            .position(|x| percentage >= x.min)
+            .or(self.glyphs.len().checked_sub(1))
+            .filter(|_| self.animate_charging)
+        else {
+            return icon.child(self.glyph(percentage, true)).into_any_element();
+        };
+        // lowest first
+        let frames = self.glyphs[..=level]
+            .iter()
+            .rev()
+            .map(|x| x.charging.clone())
+            .collect::<Vec<_>>();
+        icon.with_animation(
+            "power-charging",
+            Animation::new(CHARGING_FRAME * frames.len() as u32).repeat(),
+            move |this, delta| {
+                let frame = ((delta * frames.len() as f32) as usize).min(frames.len() - 1);
+                this.child(frames[frame].clone())
+            },
+        )
+        .into_any_element()
+    }
+    fn percentage_text(&self, percentage: f64) -> String {
+        if self.show_icon {
+            format!("{percentage:.0}")
+        } else {
+            format!("{percentage:.0}%")
+        }
+    }
+    fn content(&self, cx: &mut Context<Self>) -> Div {
+        if let Some(e) = &self.error {
+            widget_wrapper(cx).child(error_element(cx, &e.to_string()))
+        } else if self.has_battery() == Some(true)
             && let Some(state) = self.state
             && let Some(percentage) = self.percentage
         {
             match state {
+                // Discharging, Pending charge or Pending discharge while on AC,
+                // e.g. the charger is too weak or charge thresholds are in effect
+                2 | 5 | 6 if self.on_battery == Some(false) => widget_wrapper(cx)
+                    .flex()
+                    .gap(rems(0.25))
+                    .when(self.show_icon, |this| {
+                        this.child(div().font_family("Material Symbols Rounded").child(""))
+                    })
+                    .child(self.percentage_text(percentage)),
                 // Charging
-                1 => widget_wrapper()
+                1 => widget_wrapper(cx)
+                    // still tell it apart from discharging without the icon
+                    .when(!self.show_icon, |this| this.text_color(green()))
                     .flex()
                     .gap(rems(0.25))
-                    .child(div().font_family("Material Symbols Rounded").child(
-                        if percentage >= 100.0 {
-                            ""
-                        } else if percentage >= 80.0 {
-                            ""
-                        } else if percentage >= 70.0 {
-                            ""
-                        } else if percentage >= 50.0 {
-                            ""
-                        } else if percentage >= 40.0 {
-                            ""
-                        } else if percentage >= 20.0 {
-                            ""
-                        } else if percentage >= 10.0 {
-                            ""
-                        } else {
-                            ""
-                        },
-                    ))
-                    .child(format!("{:.0}", percentage)),
+                    .when(self.show_icon, |this| {
+                        this.child(self.charging_icon(percentage))
+                    })
+                    .child(self.percentage_text(percentage))
+                    .when_some(self.wattage(state), |this, wattage| this.child(wattage)),
                 // Discharging
-                2 => widget_wrapper()
+                2 => widget_wrapper(cx)
                     .flex()
                     .gap(rems(0.25))
-                    .child(div().font_family("Material Symbols Rounded").child(
-                        if percentage >= 100.0 {
-                            ""
-                        } else if percentage >= 80.0 {
-                            ""
-                        } else if percentage >= 70.0 {
-                            ""
-                        } else if percentage >= 50.0 {
-                            ""
-                        } else if percentage >= 40.0 {
-                            ""
-                        } else if percentage >= 20.0 {
-                            ""
-                        } else if percentage >= 10.0 {
-                            ""
-                        } else {
-                            ""
-                        },
-                    ))
-                    .child(format!("{:.0}", percentage)),
+                    .when(self.show_icon, |this| {
+                        this.child(
+                            div()
+                                .font_family("Material Symbols Rounded")
+                                .child(self.glyph(percentage, false)),
+                        )
+                    })
+                    .child(self.percentage_text(percentage))
+                    .when_some(self.wattage(state), |this, wattage| this.child(wattage)),
                 // Empty
-                3 => widget_wrapper()
+                3 => widget_wrapper(cx)
                     .flex()
                     .gap(rems(0.25))
-                    .child("")
-                    .child(format!("{:.0}", percentage)),
+                    .when(self.show_icon, |this| this.child(""))
+                    .child(self.percentage_text(percentage)),
                 // Fully charged
-                4 => widget_wrapper()
+                4 => widget_wrapper(cx)
                     .flex()
                     .gap(rems(0.25))
-                    .child("")
-                    .child(format!("{:.0}", percentage)),
-                _ => widget_wrapper().child(format!("Other state: {state}")),
+                    .when(self.show_icon, |this| this.child(""))
+                    .child(self.percentage_text(percentage)),
+                _ => widget_wrapper(cx).child(format!("Other state: {state}")),
             }
+        } else if self.has_battery() == Some(false) {
+            widget_wrapper(cx)
+                .font_family("Material Symbols Rounded")
+                .child("")
         } else {
-            widget_wrapper().child("?")
+            widget_wrapper(cx).child("?")
             // let Self {
-            //     error_message: _,
+            //     error: _,
             //     type_,
             //     state,
             //     percentage,
             //     time_to_empty,
             //     time_to_full,
             // } = self.clone();
-            // widget_wrapper().child(format!("type = {type_:?}, state = {state:?}, percentage = {percentage:?}, time_to_empty = {time_to_empty:?}, time_to_full = {time_to_full:?}"))
+            // widget_wrapper(cx).child(format!("type = {type_:?}, state = {state:?}, percentage = {percentage:?}, time_to_empty = {time_to_empty:?}, time_to_full = {time_to_full:?}"))
         }
     }
 }
 
-async fn task(this: WeakEntity<Power>, cx: &mut AsyncApp) {
-    let connection = match Connection::system().await {
-        Ok(x) => x,
-        Err(e) => {
-            let _ = this.update(cx, |this, cx| {
-                this.error_message = Some(format!("Failed to connect to system bus: {e}"));
+/// `glyphs` from the config in the order [`battery_glyph`] needs, highest `min` first.
+fn sorted_glyphs(glyphs: &[BatteryGlyph]) -> Vec<BatteryGlyph> {
+    let mut glyphs = glyphs.to_vec();
+    glyphs.sort_by(|a, b| b.min.total_cmp(&a.min));
+    glyphs
+}
+
+/// The glyph of the highest threshold `percentage` reaches, the lowest one below all of them.
+/// `glyphs` are sorted by `min`, highest first.
+fn battery_glyph(glyphs: &[BatteryGlyph], percentage: f64, charging: bool) -> &str {
+    let Some(glyph) = glyphs
+        .iter()
+        .find(|x| percentage >= x.min)
+        .or(glyphs.last())
+    else {
+        return "";
+    };
+    if charging {
+        &glyph.charging
+    } else {
+        &glyph.discharging
+    }
+}
+
+impl Render for Power {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        self.content(cx)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, event: &MouseDownEvent, window, cx| {
+                    this.open_health(event.position.x, window, cx);
+                }),
+            )
+            .on_mouse_down(
+                MouseButton::Middle,
+                cx.listener(|this, _, _, cx| this.refresh(cx)),
+            )
+    }
+}
+
+async fn task(this: WeakEntity<Power>, cx: &mut AsyncApp, source: impl BatterySource) {
+    let (tx, mut rx) = mpsc::unbounded();
+    let mut source_cx = cx.clone();
+    join!(source.run(tx, &mut source_cx), async move {
+        while let Some(update) = rx.next().await {
+            let result = this.update(cx, |this, cx| {
+                this.apply(update);
                 cx.notify();
             });
-            tracing::error!(error = %e, "Failed to connect to system bus");
-            return;
-        }
-    };
-    let display_device_proxy =
-        match UpowerDeviceProxy::new(&connection, "/org/freedesktop/UPower/devices/DisplayDevice")
-            .await
-        {
-            Ok(x) => x,
-            Err(e) => {
-                let _ = this.update(cx, |this, cx| {
-                    this.error_message = Some(format!("Failed to create properties proxy: {e}"));
-                    cx.notify();
-                });
-                tracing::error!(error = %e, "Failed to create properties proxy");
-                return;
-            }
-        };
-    let mut type_stream = display_device_proxy.receive_type__changed().await;
-    let mut state_stream = display_device_proxy.receive_state_changed().await;
-    let mut percentage_stream = display_device_proxy.receive_percentage_changed().await;
-    let mut time_to_empty_stream = display_device_proxy.receive_time_to_empty_changed().await;
-    let mut time_to_full_stream = display_device_proxy.receive_time_to_full_changed().await;
-    macro_rules! handle_stream {
-        ($stream:expr, $field:ident, $name:literal $(, $and_then:expr)?) => {
-            {
-                let mut cx = cx.clone();
-                let this = &this;
-                async move {
-                    while let Some($field) = $stream.next().await {
-                        match $field.get().await {
-                            Ok($field) => {
-                                tracing::info!($field, concat!($name, " changed"));
-                                let _ = this.update(&mut cx, |this, cx| {
-                                    this.$field = Some($field)$(.and_then($and_then))?;
-                                    cx.notify()
-                                });
-                            }
-                            Err(e) => {
-                                tracing::error!(error = %e, concat!("Failed to get new ", $name));
-                            }
-                        }
-                    }
-                    tracing::warn!(concat!("Receive ", $name ," stream ended"));
-                }
+            if result.is_err() {
+                // dropping `rx` stops the source
+                break;
             }
-        };
+        }
+    });
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct PowerConfig {
+    /// What to do when the display device is not a battery, e.g. on desktops.
+    #[serde(default)]
+    no_battery: NoBattery,
+    /// Native path of the UPower device to show (see `upower --dump`), the display device if unset
+    #[serde(default)]
+    device: Option<String>,
+    /// Show how fast the battery is charging or discharging, in watts
+    #[serde(default)]
+    show_wattage: bool,
+    /// Hide the widget while on AC power
+    #[serde(default)]
+    hide_on_ac: bool,
+    /// Hide the widget while the battery is fully charged
+    #[serde(default)]
+    hide_when_full: bool,
+    /// Show the battery glyph, otherwise only the percentage (in green while charging)
+    #[serde(default = "default_show_icon")]
+    show_icon: bool,
+    /// The battery glyphs by the percentage, for icon fonts with other battery levels. Each is
+    /// shown from its `min` up to the next one, the lowest one also below its `min`.
+    #[serde(default = "default_glyphs")]
+    glyphs: Vec<BatteryGlyph>,
+    /// Fill the battery glyph up to full repeatedly while charging, instead of a fixed glyph
+    #[serde(default)]
+    animate_charging: bool,
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        Self {
+            no_battery: NoBattery::default(),
+            device: None,
+            show_wattage: false,
+            hide_on_ac: false,
+            hide_when_full: false,
+            show_icon: default_show_icon(),
+            glyphs: default_glyphs(),
+            animate_charging: false,
+        }
     }
-    join!(
-        handle_stream!(type_stream, type_, "Type"),
-        handle_stream!(state_stream, state, "State"),
-        handle_stream!(percentage_stream, percentage, "Percentage"),
-        handle_stream!(
-            time_to_empty_stream,
-            time_to_empty,
-            "TimeToEmpty",
-            |x| if x != 0
-                && let Ok(x) = x.try_into()
-            {
-                Some(Duration::from_secs(x))
-            } else {
-                None
-            }
-        ),
-        handle_stream!(
-            time_to_full_stream,
-            time_to_full,
-            "TimeToFull",
-            |x| if x != 0
-                && let Ok(x) = x.try_into()
-            {
-                Some(Duration::from_secs(x))
-            } else {
-                None
-            }
-        ),
-    );
+}
+
+fn default_show_icon() -> bool {
+    true
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct BatteryGlyph {
+    /// The lowest percentage showing this glyph
+    min: f64,
+    charging: String,
+    discharging: String,
+}
+
+fn default_glyphs() -> Vec<BatteryGlyph> {
+    [
+        (100.0, "", ""),
+        (80.0, "", ""),
+        (70.0, "", ""),
+        (50.0, "", ""),
+        (40.0, "", ""),
+        (20.0, "", ""),
+        (10.0, "", ""),
+        (0.0, "", ""),
+    ]
+    .into_iter()
+    .map(|(min, charging, discharging)| BatteryGlyph {
+        min,
+        charging: charging.to_owned(),
+        discharging: discharging.to_owned(),
+    })
+    .collect()
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum NoBattery {
+    /// Hide the widget
+    #[default]
+    Hide,
+    /// Show an AC power glyph
+    Show,
 }
 
 // <https://upower.freedesktop.org/docs/UPower.html>
@@ -217,7 +447,7 @@ async fn task(this: WeakEntity<Power>, cx: &mut AsyncApp) {
     default_service = "org.freedesktop.UPower",
     default_path = "/org/freedesktop/UPower"
 )]
-trait Upower {
+pub trait Upower {
     fn enumerate_devices(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
     fn enumerate_kbd_backlights(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
     fn get_display_device(&self) -> zbus::Result<OwnedObjectPath>;
@@ -243,7 +473,7 @@ trait Upower {
     interface = "org.freedesktop.UPower.Device",
     default_service = "org.freedesktop.UPower"
 )]
-trait UpowerDevice {
+pub trait UpowerDevice {
     fn refresh(&self) -> zbus::Result<()>;
     fn get_history(
         &self,
@@ -337,7 +567,7 @@ trait UpowerDevice {
     interface = "org.freedesktop.UPower.KbdBacklight",
     default_service = "org.freedesktop.UPower"
 )]
-trait UpowerKbdBacklight {
+pub trait UpowerKbdBacklight {
     fn get_max_brightness(&self) -> zbus::Result<i32>;
     fn get_brightness(&self) -> zbus::Result<i32>;
     fn set_brightness(&self, value: i32) -> zbus::Result<()>;
@@ -350,3 +580,150 @@ trait UpowerKbdBacklight {
     #[zbus(property)]
     fn native_path(&self) -> zbus::Result<String>;
 }
+
+#[cfg(test)]
+mod tests {
+    use gpui::TestAppContext;
+
+    use super::*;
+    use crate::widget::{draw_widget, preview_widget};
+
+    #[test]
+    fn default_glyph_tiers() {
+        let glyphs = default_glyphs();
+        for (index, glyph) in glyphs.iter().enumerate() {
+            assert_eq!(battery_glyph(&glyphs, glyph.min, false), glyph.discharging);
+            assert_eq!(battery_glyph(&glyphs, glyph.min, true), glyph.charging);
+            // just below a threshold is the next lower tier
+            if let Some(lower) = glyphs.get(index + 1) {
+                assert_eq!(
+                    battery_glyph(&glyphs, glyph.min - 0.1, false),
+                    lower.discharging
+                );
+                assert_eq!(
+                    battery_glyph(&glyphs, glyph.min - 0.1, true),
+                    lower.charging
+                );
+            }
+        }
+    }
+
+    fn glyph(min: f64, name: &str) -> BatteryGlyph {
+        BatteryGlyph {
+            min,
+            charging: format!("{name}+"),
+            discharging: name.to_owned(),
+        }
+    }
+
+    #[test]
+    fn edge_percentages() {
+        let glyphs = sorted_glyphs(&[
+            glyph(100.0, "full"),
+            glyph(50.0, "half"),
+            glyph(10.0, "low"),
+        ]);
+        assert_eq!(battery_glyph(&glyphs, 100.0, false), "full");
+        assert_eq!(battery_glyph(&glyphs, 99.9, false), "half");
+        assert_eq!(battery_glyph(&glyphs, 50.0, false), "half");
+        assert_eq!(battery_glyph(&glyphs, 49.9, false), "low");
+        assert_eq!(battery_glyph(&glyphs, 10.0, false), "low");
+        assert_eq!(battery_glyph(&glyphs, 10.0, true), "low+");
+    }
+
+    #[test]
+    fn below_the_lowest_min() {
+        let glyphs = sorted_glyphs(&[glyph(50.0, "half"), glyph(10.0, "low")]);
+        assert_eq!(battery_glyph(&glyphs, 9.9, false), "low");
+        assert_eq!(battery_glyph(&glyphs, 0.0, false), "low");
+        assert_eq!(battery_glyph(&glyphs, 0.0, true), "low+");
+    }
+
+    #[test]
+    fn empty_table() {
+        assert_eq!(battery_glyph(&[], 50.0, false), "");
+        assert_eq!(battery_glyph(&[], 50.0, true), "");
+    }
+
+    #[test]
+    fn unsorted_config() {
+        let glyphs = sorted_glyphs(&[
+            glyph(10.0, "low"),
+            glyph(100.0, "full"),
+            glyph(50.0, "half"),
+        ]);
+        let mins = glyphs.iter().map(|x| x.min).collect::<Vec<_>>();
+        assert_eq!(mins, [100.0, 50.0, 10.0]);
+        assert_eq!(battery_glyph(&glyphs, 100.0, false), "full");
+        assert_eq!(battery_glyph(&glyphs, 75.0, false), "half");
+        assert_eq!(battery_glyph(&glyphs, 20.0, false), "low");
+    }
+
+    fn power_after(updates: Vec<BatteryUpdate>) -> Power {
+        let mut power = Power::from_config(&PowerConfig::default());
+        for update in updates {
+            power.apply(update);
+        }
+        power
+    }
+
+    #[test]
+    fn fake_battery() {
+        let power = power_after(FakeBatterySource::discharging(42.0).updates);
+        assert_eq!(power.has_battery(), Some(true));
+        assert_eq!(power.percentage, Some(42.0));
+        assert_eq!(power.wattage(2), None);
+        assert!(power.visible());
+        assert!(power.activatable());
+    }
+
+    #[test]
+    fn no_battery_hides() {
+        let power = power_after(vec![]);
+        assert_eq!(power.has_battery(), None);
+        assert!(power.visible());
+        assert!(!power.activatable());
+
+        // 1: line power
+        let power = power_after(vec![BatteryUpdate::Type(1)]);
+        assert_eq!(power.has_battery(), Some(false));
+        assert!(!power.visible());
+    }
+
+    #[test]
+    fn missing_upower_hides() {
+        let mut updates = FakeBatterySource::discharging(42.0).updates;
+        updates.push(BatteryUpdate::Error(Some(WidgetError::ServiceMissing(
+            "UPower".to_owned(),
+        ))));
+        assert!(!power_after(updates).visible());
+    }
+
+    #[gpui::test]
+    fn render_states(cx: &mut TestAppContext) {
+        // the fake battery of `--preview`
+        let power = preview_widget::<Power>(cx, &PowerConfig::default());
+        power.read_with(cx, |power, _| {
+            assert!(power.visible());
+            assert_eq!(power.state, Some(2));
+            assert_eq!(power.percentage, Some(42.0));
+        });
+
+        power.update(cx, |power, _| {
+            power.apply(BatteryUpdate::Error(Some(WidgetError::ConnectionFailed {
+                service: "UPower",
+                message: "no system bus".to_owned(),
+            })));
+        });
+        draw_widget(cx, &power);
+        assert!(power.read_with(cx, |power, _| power.visible()));
+
+        // 1: line power
+        power.update(cx, |power, _| {
+            power.apply(BatteryUpdate::Error(None));
+            power.apply(BatteryUpdate::Type(1));
+        });
+        draw_widget(cx, &power);
+        assert!(!power.read_with(cx, |power, _| power.visible()));
+    }
+}
@@ -1,33 +1,57 @@
 use gpui::{
-    Context, InteractiveElement, IntoElement, ParentElement, Render, StatefulInteractiveElement,
-    Styled, Window, rgb,
+    App, Context, InteractiveElement, IntoElement, ParentElement, Render,
+    StatefulInteractiveElement, Styled, Window, rgb,
 };
 
-use crate::widget::{Widget, widget_wrapper};
+use crate::{
+    power_menu::{self, PowerMenuConfig},
+    widget::{Widget, widget_wrapper},
+};
 
-pub struct PowerMenu;
+pub struct PowerMenu {
+    focused_monitor: bool,
+}
 
 impl Widget for PowerMenu {
-    type Config = ();
+    type Config = PowerMenuConfig;
 
-    fn new(_cx: &mut Context<Self>, _config: &Self::Config) -> Self {
-        Self
+    fn new(_cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        Self {
+            focused_monitor: config.focused_monitor,
+        }
+    }
+
+    fn activate(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        open(self.focused_monitor, window, cx);
+    }
+
+    fn activatable(&self) -> bool {
+        true
     }
 }
 
 impl Render for PowerMenu {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
-        widget_wrapper()
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let focused_monitor = self.focused_monitor;
+        widget_wrapper(cx)
             .id("button_left")
-            .on_click(|_click_event, window, cx| {
-                cx.open_window(
-                    crate::power_menu::PowerMenu::window_options(window.display(cx)),
-                    crate::power_menu::PowerMenu::build_root_view,
-                )
-                .unwrap();
-            })
+            .on_click(move |_click_event, window, cx| open(focused_monitor, window, cx))
             .text_color(rgb(0x7ebae4))
             .font_family("NotoSans Nerd Font Propo")
             .child("")
     }
 }
+
+/// Open the power menu on the display of `window`, or the focused one with `focused_monitor`.
+fn open(focused_monitor: bool, window: &Window, cx: &mut App) {
+    let display = window.display(cx);
+    if focused_monitor {
+        cx.spawn(async move |cx| {
+            let display = power_menu::focused_display(cx).await.or(display);
+            cx.update(|cx| power_menu::PowerMenu::open(display, cx));
+        })
+        .detach();
+    } else {
+        power_menu::PowerMenu::open(display, cx);
+    }
+}
@@ -1,71 +1,205 @@
-use futures::StreamExt;
-use gpui::{AsyncApp, Context, IntoElement, ParentElement, Render, Styled, WeakEntity, Window};
-use zbus::{Connection, proxy};
+use std::{collections::HashMap, pin::pin};
 
-use crate::widget::{Widget, widget_wrapper};
+use futures::{
+    StreamExt,
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    future::{self, Either},
+};
+use gpui::{
+    AsyncApp, Context, Div, InteractiveElement, IntoElement, MouseButton, ParentElement, Render,
+    Styled, WeakEntity, Window, div, prelude::FluentBuilder, rems,
+};
+use serde::{Deserialize, Serialize};
+use zbus::{Connection, proxy, proxy::CacheProperties};
+
+use crate::{
+    util::retry_with_backoff,
+    widget::{
+        TaskEnd, Widget, error::WidgetError, error_element, is_preview, spawn_watched_task,
+        widget_wrapper,
+    },
+};
+
+/// Bus name (also the interface name) and object path of power-profiles-daemon, tried in order.
+/// It used to be `net.hadess.PowerProfiles` before moving under UPower.
+const SERVICES: &[(&str, &str)] = &[
+    (
+        "org.freedesktop.UPower.PowerProfiles",
+        "/org/freedesktop/UPower/PowerProfiles",
+    ),
+    ("net.hadess.PowerProfiles", "/net/hadess/PowerProfiles"),
+];
 
 pub struct PowerProfile {
-    error_message: Option<String>,
+    config: PowerProfileConfig,
+    /// A missing power-profiles-daemon hides the widget
+    error: Option<WidgetError>,
     active_profile: Option<String>,
+    /// Re-read the active profile, e.g. when it looks stale
+    refresh_tx: UnboundedSender<()>,
 }
 
 impl Widget for PowerProfile {
-    type Config = ();
+    type Config = PowerProfileConfig;
 
-    fn new(cx: &mut Context<Self>, _config: &Self::Config) -> Self {
-        cx.spawn(task).detach();
+    fn new(cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        let (refresh_tx, refresh_rx) = mpsc::unbounded();
+        if is_preview(cx) {
+            return Self {
+                config: config.clone(),
+                error: None,
+                active_profile: Some("balanced".to_owned()),
+                refresh_tx,
+            };
+        }
+        spawn_watched_task(cx, async move |this, cx| {
+            task(this.clone(), cx, refresh_rx).await;
+            // there is nothing to watch without power-profiles-daemon
+            match this.read_with(cx, |this, _| this.visible()) {
+                Ok(false) => TaskEnd::Done,
+                _ => TaskEnd::Unexpected,
+            }
+        })
+        .detach();
 
         Self {
-            error_message: None,
+            config: config.clone(),
+            error: None,
             active_profile: None,
+            refresh_tx,
         }
     }
+
+    fn visible(&self) -> bool {
+        !self.error.as_ref().is_some_and(WidgetError::hides_widget)
+    }
 }
 
-impl Render for PowerProfile {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
-        if let Some(e) = &self.error_message {
-            widget_wrapper().child(e.clone())
+#[derive(Deserialize, Serialize, Default, Clone, Debug, PartialEq)]
+pub struct PowerProfileConfig {
+    /// Glyphs by profile name, over the built-in ones for `power-saver`, `balanced` and
+    /// `performance`
+    #[serde(default)]
+    pub icons: HashMap<String, String>,
+    /// Labels by profile name, the profile name itself is used otherwise
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    /// Show the label next to the icon, profiles without an icon always show their label
+    #[serde(default)]
+    pub show_label: bool,
+}
+
+impl PowerProfileConfig {
+    fn icon(&self, profile: &str) -> Option<String> {
+        if let Some(icon) = self.icons.get(profile) {
+            return Some(icon.clone());
+        }
+        let icon = match profile {
+            "power-saver" => "",
+            "balanced" => "",
+            "performance" => "",
+            _ => return None,
+        };
+        Some(icon.to_owned())
+    }
+    fn label(&self, profile: &str) -> String {
+        self.labels
+            .get(profile)
+            .cloned()
+            .unwrap_or_else(|| profile.to_owned())
+    }
+}
+
+impl PowerProfile {
+    fn refresh(&self) {
+        if self.active_profile.is_none() {
+            tracing::info!("Not connected to power-profiles-daemon yet, nothing to refresh");
+            return;
+        }
+        if let Err(e) = self.refresh_tx.unbounded_send(()) {
+            tracing::error!(error = %e, "Failed to send refresh to the task");
+        }
+    }
+    fn content(&self, cx: &mut Context<Self>) -> Div {
+        if let Some(e) = &self.error {
+            widget_wrapper(cx).child(error_element(cx, &e.to_string()))
         } else if let Some(profile) = &self.active_profile {
-            let icon_wrapper = || widget_wrapper().font_family("Material Symbols Rounded");
-            match profile.as_str() {
-                "power-saver" => icon_wrapper().child(""),
-                "balanced" => icon_wrapper().child(""),
-                "performance" => icon_wrapper().child(""),
-                _ => widget_wrapper().child(profile.clone()),
+            match self.config.icon(profile) {
+                Some(icon) => widget_wrapper(cx)
+                    .flex()
+                    .gap(rems(0.25))
+                    .child(div().font_family("Material Symbols Rounded").child(icon))
+                    .when(self.config.show_label, |this| {
+                        this.child(self.config.label(profile))
+                    }),
+                None => widget_wrapper(cx).child(self.config.label(profile)),
             }
         } else {
-            widget_wrapper().child("?")
+            widget_wrapper(cx).child("?")
         }
     }
 }
 
-async fn task(this: WeakEntity<PowerProfile>, cx: &mut AsyncApp) {
-    let connection = match Connection::system().await {
-        Ok(x) => x,
-        Err(e) => {
-            let _ = this.update(cx, |this, cx| {
-                this.error_message = Some(format!("Failed to connect to system bus: {e}"));
+impl Render for PowerProfile {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        self.content(cx).on_mouse_down(
+            MouseButton::Middle,
+            cx.listener(|this, _, _, _| this.refresh()),
+        )
+    }
+}
+
+async fn task(
+    this: WeakEntity<PowerProfile>,
+    cx: &mut AsyncApp,
+    mut refresh_rx: UnboundedReceiver<()>,
+) {
+    let Some(connection) = retry_with_backoff(
+        cx,
+        async |_| Connection::system().await,
+        |cx, e, retry_in| {
+            tracing::error!(error = %e, ?retry_in, "Failed to connect to system bus");
+            this.update(cx, |this, cx| {
+                this.error = Some(WidgetError::ConnectionFailed {
+                    service: "system bus",
+                    message: e.to_string(),
+                });
                 cx.notify();
-            });
-            tracing::error!(error = %e, "Failed to connect to system bus");
-            return;
-        }
+            })
+            .is_ok()
+        },
+    )
+    .await
+    else {
+        return;
     };
-    let proxy = match PowerProfilesProxy::new(&connection).await {
-        Ok(x) => x,
-        Err(e) => {
-            let _ = this.update(cx, |this, cx| {
-                this.error_message = Some(format!("Failed to create properties proxy: {e}"));
-                cx.notify();
-            });
-            tracing::error!(error = %e, "Failed to create properties proxy");
-            return;
-        }
+    let _ = this.update(cx, |this, cx| {
+        this.error = None;
+        cx.notify();
+    });
+    let Some(proxy) = find_service(&connection).await else {
+        tracing::warn!("No power-profiles-daemon found, hiding the power profile widget");
+        let _ = this.update(cx, |this, cx| {
+            this.error = Some(WidgetError::ServiceMissing(
+                "power-profiles-daemon".to_owned(),
+            ));
+            cx.notify();
+        });
+        return;
     };
     let mut stream = proxy.receive_active_profile_changed().await;
-    while let Some(active_profile) = stream.next().await {
-        match active_profile.get().await {
+    loop {
+        let active_profile = match future::select(stream.next(), pin!(refresh_rx.next())).await {
+            Either::Left((Some(active_profile), _)) => active_profile.get().await,
+            Either::Left((None, _)) => break,
+            Either::Right((Some(()), _)) => {
+                tracing::info!("Refreshing the active profile");
+                read_active_profile(&proxy).await
+            }
+            // the widget is dropped
+            Either::Right((None, _)) => return,
+        };
+        match active_profile {
             Ok(active_profile) => {
                 tracing::info!(active_profile, "Power profile changed");
                 let _ = this.update(cx, |this, cx| {
@@ -81,6 +215,57 @@ async fn task(this: WeakEntity<PowerProfile>, cx: &mut AsyncApp) {
     tracing::warn!("Receive ActiveProfile stream ended");
 }
 
+/// The first service in [`SERVICES`] that answers.
+async fn find_service(connection: &Connection) -> Option<PowerProfilesProxy<'static>> {
+    for &(service, path) in SERVICES {
+        let proxy = match power_profiles_proxy(connection, service, path).await {
+            Ok(x) => x,
+            Err(e) => {
+                tracing::error!(service, error = %e, "Failed to create power profiles proxy");
+                continue;
+            }
+        };
+        // this also starts the daemon if it's dbus activated
+        match proxy.active_profile().await {
+            Ok(_) => {
+                tracing::info!(service, "Found power-profiles-daemon");
+                return Some(proxy);
+            }
+            Err(e) => {
+                tracing::info!(service, error = %e, "power-profiles-daemon is not at this name");
+            }
+        }
+    }
+    None
+}
+
+/// The active profile from the daemon itself instead of the property cache.
+async fn read_active_profile(proxy: &PowerProfilesProxy<'_>) -> zbus::Result<String> {
+    let inner = proxy.inner();
+    PowerProfilesProxy::builder(inner.connection())
+        .destination(inner.destination().to_owned())?
+        .path(inner.path().to_owned())?
+        .interface(inner.interface().to_owned())?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?
+        .active_profile()
+        .await
+}
+
+async fn power_profiles_proxy(
+    connection: &Connection,
+    service: &'static str,
+    path: &'static str,
+) -> zbus::Result<PowerProfilesProxy<'static>> {
+    PowerProfilesProxy::builder(connection)
+        .destination(service)?
+        .path(path)?
+        .interface(service)?
+        .build()
+        .await
+}
+
 // <https://upower.pages.freedesktop.org/power-profiles-daemon/gdbus-org.freedesktop.UPower.PowerProfiles.html>
 #[proxy(
     interface = "org.freedesktop.UPower.PowerProfiles",
@@ -13,11 +13,19 @@ impl Widget for Quit {
     fn new(_cx: &mut Context<Self>, _config: &Self::Config) -> Self {
         Self
     }
+
+    fn activate(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        cx.quit();
+    }
+
+    fn activatable(&self) -> bool {
+        true
+    }
 }
 
 impl Render for Quit {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
-        widget_wrapper()
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        widget_wrapper(cx)
             .id("quit-button")
             .on_click(|_click_event, _window, cx| {
                 cx.quit();
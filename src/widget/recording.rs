@@ -0,0 +1,62 @@
+use gpui::{
+    AsyncApp, Context, IntoElement, ParentElement, Render, Styled, WeakEntity, Window, red,
+};
+
+use crate::widget::{
+    Widget,
+    capture::{CaptureFilter, capture_task},
+    error_element, spawn_watched_task, widget_wrapper,
+};
+
+/// Cameras and screencasts, and the streams reading from them.
+const FILTER: CaptureFilter = CaptureFilter {
+    media_classes: &["Video/Source", "Stream/Input/Video"],
+    sources: None,
+};
+
+/// A red dot while something captures the screen or a camera, hidden otherwise.
+pub struct Recording {
+    error_message: Option<String>,
+    active: bool,
+}
+
+impl Widget for Recording {
+    type Config = ();
+
+    fn new(cx: &mut Context<Self>, _config: &Self::Config) -> Self {
+        spawn_watched_task(cx, task).detach();
+
+        Self {
+            error_message: None,
+            active: false,
+        }
+    }
+
+    fn visible(&self) -> bool {
+        self.active || self.error_message.is_some()
+    }
+}
+
+impl Render for Recording {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if let Some(e) = &self.error_message {
+            widget_wrapper(cx).child(error_element(cx, e))
+        } else {
+            widget_wrapper(cx)
+                .font_family("Material Symbols Rounded")
+                .text_color(red())
+                .child("")
+        }
+    }
+}
+
+async fn task(this: WeakEntity<Recording>, cx: &mut AsyncApp) {
+    capture_task(this, cx, FILTER, |this, update| match update {
+        Ok(active) => {
+            tracing::info!(active, "Recording changed");
+            this.active = active;
+        }
+        Err(e) => this.error_message = Some(e),
+    })
+    .await;
+}
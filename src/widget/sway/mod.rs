@@ -0,0 +1,101 @@
+use std::{env, fmt::Display, path::Path};
+
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use gpui_net::async_net::UnixStream;
+use serde::Deserialize;
+
+pub mod workspaces;
+
+// <https://i3wm.org/docs/ipc.html>
+const MAGIC: &[u8] = b"i3-ipc";
+
+pub const RUN_COMMAND: u32 = 0;
+pub const GET_WORKSPACES: u32 = 1;
+pub const SUBSCRIBE: u32 = 2;
+/// Events have the highest bit of the type set
+pub const EVENT_WORKSPACE: u32 = 0x8000_0000;
+
+/// Path of the ipc socket of the running sway (or i3) instance.
+pub fn socket_path() -> Result<String, String> {
+    env::var("SWAYSOCK")
+        .or_else(|_| env::var("I3SOCK"))
+        .map_err(|e| format!("error while getting SWAYSOCK or I3SOCK: {e}"))
+}
+
+/// Write a message with the `i3-ipc` header.
+pub async fn write_message<S>(stream: &mut S, type_: u32, payload: &[u8]) -> Result<(), String>
+where
+    S: AsyncWrite + Unpin,
+{
+    let length = u32::try_from(payload.len()).map_err(|e| format!("payload too long: {e}"))?;
+    let mut message = Vec::with_capacity(MAGIC.len() + 8 + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&length.to_ne_bytes());
+    message.extend_from_slice(&type_.to_ne_bytes());
+    message.extend_from_slice(payload);
+    stream
+        .write_all(&message)
+        .await
+        .map_err(|e| format!("write_all error: {e}"))
+}
+
+/// Read one message (a reply or an event), returning its type and payload.
+pub async fn read_message<S>(stream: &mut S) -> Result<(u32, Vec<u8>), String>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut header = [0; MAGIC.len() + 8];
+    stream
+        .read_exact(&mut header)
+        .await
+        .map_err(|e| format!("read_exact error: {e}"))?;
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(format!("invalid magic string in header: {header:?}"));
+    }
+    let [l0, l1, l2, l3, t0, t1, t2, t3] = header[MAGIC.len()..] else {
+        unreachable!("the header has 8 bytes after the magic string");
+    };
+    let length = u32::from_ne_bytes([l0, l1, l2, l3]);
+    let type_ = u32::from_ne_bytes([t0, t1, t2, t3]);
+
+    let mut payload = vec![0; length as usize];
+    stream
+        .read_exact(&mut payload)
+        .await
+        .map_err(|e| format!("read_exact error: {e}"))?;
+    Ok((type_, payload))
+}
+
+/// Send one message on a new connection and read the reply.
+pub async fn request<P>(socket_path: P, type_: u32, payload: &[u8]) -> Result<Vec<u8>, String>
+where
+    P: AsRef<Path> + Display,
+{
+    let mut stream = UnixStream::connect(&socket_path)
+        .await
+        .map_err(|e| format!("error while connecting to sway socket ({socket_path}): {e}"))?;
+    write_message(&mut stream, type_, payload).await?;
+    let (_, reply) = read_message(&mut stream).await?;
+    let _ = stream.close().await;
+    Ok(reply)
+}
+
+/// Run a sway command, like `swaymsg`.
+pub async fn run_command(command: &str) -> Result<(), String> {
+    let reply = request(socket_path()?, RUN_COMMAND, command.as_bytes()).await?;
+    let results = serde_json::from_slice::<Vec<CommandResult>>(&reply)
+        .map_err(|e| format!("parsing `{:?}`: {e}", String::from_utf8(reply)))?;
+    match results.into_iter().find(|x| !x.success) {
+        Some(CommandResult { error, .. }) => {
+            Err(error.unwrap_or_else(|| "unknown error".to_owned()))
+        }
+        None => Ok(()),
+    }
+}
+
+#[derive(Deserialize)]
+struct CommandResult {
+    success: bool,
+    #[serde(default)]
+    error: Option<String>,
+}
@@ -0,0 +1,254 @@
+use std::{fmt::Display, path::Path};
+
+use gpui::{
+    AnyElement, AsyncApp, Context, InteractiveElement, IntoElement, ParentElement, Render,
+    ScrollWheelEvent, StatefulInteractiveElement, Styled, WeakEntity, Window,
+    prelude::FluentBuilder, rems,
+};
+use gpui_net::async_net::UnixStream;
+use serde::Deserialize;
+
+use crate::{
+    config::ThemeConfig,
+    util::retry_with_backoff,
+    widget::{
+        ScrollAccumulator, Widget, error_element, is_preview, scroll_delta, spawn_watched_task,
+        sway::{
+            EVENT_WORKSPACE, GET_WORKSPACES, SUBSCRIBE, read_message, request, run_command,
+            socket_path, write_message,
+        },
+        transition, widget_wrapper,
+        workspaces::{WorkspaceView, WorkspacesConfig},
+    },
+};
+
+pub struct SwayWorkspace {
+    config: WorkspacesConfig,
+    error_message: Option<String>,
+    scrolled: ScrollAccumulator,
+    workspaces: Vec<WorkspaceInfo>,
+}
+
+impl Widget for SwayWorkspace {
+    type Config = WorkspacesConfig;
+
+    fn new(cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        if is_preview(cx) {
+            return Self {
+                config: config.clone(),
+                error_message: None,
+                scrolled: ScrollAccumulator::default(),
+                workspaces: (1..=4)
+                    .map(|num| WorkspaceInfo {
+                        num,
+                        name: num.to_string(),
+                        focused: num == 2,
+                        urgent: num == 4,
+                    })
+                    .collect(),
+            };
+        }
+        spawn_watched_task(cx, task).detach();
+
+        Self {
+            config: config.clone(),
+            error_message: None,
+            scrolled: ScrollAccumulator::default(),
+            workspaces: Vec::new(),
+        }
+    }
+}
+
+impl Render for SwayWorkspace {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if let Some(e) = &self.error_message {
+            return widget_wrapper(cx).child(error_element(cx, e));
+        }
+
+        widget_wrapper(cx)
+            .flex()
+            .gap(rems(0.5))
+            .when(self.config.scroll, |this| {
+                this.on_scroll_wheel(cx.listener(|this, event: &ScrollWheelEvent, window, cx| {
+                    let delta = scroll_delta(event, window, cx);
+                    let steps = this.scrolled.add(delta, window.line_height());
+                    this.scroll(steps, cx);
+                }))
+            })
+            .children(self.render_workspaces(cx))
+    }
+}
+
+impl SwayWorkspace {
+    fn render_workspaces(&self, cx: &mut Context<Self>) -> Vec<AnyElement> {
+        let theme = cx.global::<ThemeConfig>().clone();
+        let elements = self
+            .workspaces
+            .iter()
+            .enumerate()
+            .map(|(index, info)| {
+                let element = self
+                    .config
+                    .render_workspace(
+                        WorkspaceView {
+                            name: &info.name,
+                            id: Some(&info.num.to_string()),
+                            active: info.focused,
+                            urgent: info.urgent,
+                            dimmed: false,
+                        },
+                        &theme,
+                    )
+                    .id(format!("workspace-{index}"))
+                    .on_click({
+                        let name = info.name.clone();
+                        cx.listener(move |_, _, _, cx| switch_to(&name, cx))
+                    });
+                transition(
+                    format!("workspace-highlight-{index}"),
+                    (info.focused, info.urgent),
+                    element,
+                    cx,
+                )
+            })
+            .collect();
+        let active = self.workspaces.iter().position(|info| info.focused);
+        self.config.limit_visible(elements, active)
+    }
+    fn scroll(&self, steps: i32, cx: &mut Context<Self>) {
+        let Some(current) = self.workspaces.iter().position(|x| x.focused) else {
+            return;
+        };
+        if let Some(info) = self
+            .config
+            .scroll_target(current, self.workspaces.len(), steps)
+            .and_then(|index| self.workspaces.get(index))
+        {
+            switch_to(&info.name, cx);
+        }
+    }
+}
+
+fn switch_to(name: &str, cx: &mut Context<SwayWorkspace>) {
+    let command = format!(
+        "workspace \"{}\"",
+        name.replace('\\', "\\\\").replace('"', "\\\"")
+    );
+    cx.background_spawn(async move {
+        if let Err(e) = run_command(&command).await {
+            tracing::error!(command, error = %e, "Failed to switch workspace");
+        }
+    })
+    .detach();
+}
+
+async fn task(this: WeakEntity<SwayWorkspace>, cx: &mut AsyncApp) {
+    let socket_path = match socket_path() {
+        Ok(x) => x,
+        Err(e) => {
+            let _ = this.update(cx, |this, cx| {
+                this.error_message = Some(e);
+                cx.notify();
+            });
+            return;
+        }
+    };
+
+    // reconnect when sway restarts or the socket fails, the workspaces are read again then
+    loop {
+        let Some(mut event_stream) = retry_with_backoff(
+            cx,
+            async |_| subscribe(&socket_path).await,
+            |cx, e, retry_in| {
+                tracing::error!(error = %e, ?retry_in, "Failed to subscribe to sway events");
+                this.update(cx, |this, cx| {
+                    this.error_message = Some(e);
+                    cx.notify();
+                })
+                .is_ok()
+            },
+        )
+        .await
+        else {
+            return;
+        };
+
+        try_update_with_get_workspaces(&socket_path, &this, cx).await;
+
+        loop {
+            match read_message(&mut event_stream).await {
+                // the event only has the changed workspaces, get all of them to keep the order
+                Ok((EVENT_WORKSPACE, _)) => {
+                    try_update_with_get_workspaces(&socket_path, &this, cx).await;
+                }
+                Ok((type_, _)) => {
+                    tracing::warn!(type_, "Received an unexpected message from sway");
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to read the sway socket, reconnecting");
+                    let Ok(()) = this.update(cx, |this, cx| {
+                        this.error_message = Some(format!("error while reading the socket: {e}"));
+                        cx.notify();
+                    }) else {
+                        return;
+                    };
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// A connection that gets the `workspace` events.
+async fn subscribe(socket_path: &str) -> Result<UnixStream, String> {
+    let mut event_stream = UnixStream::connect(socket_path)
+        .await
+        .map_err(|e| format!("error while connecting to sway socket ({socket_path}): {e}"))?;
+    write_message(&mut event_stream, SUBSCRIBE, br#"["workspace"]"#)
+        .await
+        .map_err(|e| format!("error while subscribing to sway events: {e}"))?;
+    read_message(&mut event_stream)
+        .await
+        .map_err(|e| format!("error while subscribing to sway events: {e}"))?;
+    Ok(event_stream)
+}
+
+async fn try_update_with_get_workspaces<P>(
+    socket_path: P,
+    entity: &WeakEntity<SwayWorkspace>,
+    cx: &mut AsyncApp,
+) where
+    P: AsRef<Path> + Display + Copy,
+{
+    let workspaces = request(socket_path, GET_WORKSPACES, &[])
+        .await
+        .and_then(|buffer| {
+            serde_json::from_slice::<Vec<WorkspaceInfo>>(&buffer)
+                .map_err(|e| format!("parsing `{:?}`: {e}", String::from_utf8(buffer)))
+        });
+    match workspaces {
+        Ok(workspaces) => {
+            let _ = entity.update(cx, |this, cx| {
+                this.workspaces = workspaces;
+                // e.g. after sway restarted and the socket is back
+                this.error_message = None;
+                cx.notify();
+            });
+        }
+        Err(e) => {
+            tracing::error!("Failed to get workspaces from sway socket at `{socket_path}`: {e}");
+            let _ = entity.update(cx, |this, cx| {
+                this.error_message = Some(e);
+                cx.notify();
+            });
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct WorkspaceInfo {
+    num: i32,
+    name: String,
+    focused: bool,
+    urgent: bool,
+}
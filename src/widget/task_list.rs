@@ -0,0 +1,391 @@
+use std::{collections::HashMap, thread};
+
+use futures::{
+    StreamExt,
+    channel::mpsc::{self, UnboundedSender},
+};
+use gpui::{
+    AsyncApp, Context, InteractiveElement, IntoElement, ParentElement, Render,
+    StatefulInteractiveElement, Styled, WeakEntity, Window, div, prelude::FluentBuilder, rems,
+};
+use serde::{Deserialize, Serialize};
+use wayland_client::{
+    Connection, Dispatch, QueueHandle,
+    protocol::{
+        wl_registry::{self, WlRegistry},
+        wl_seat::{self, WlSeat},
+    },
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+    zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+};
+
+use crate::{
+    config::ThemeConfig,
+    widget::{
+        Widget, app_icon::app_icon, dependency::Dependency, error_element, spawn_watched_task,
+        truncate, widget_wrapper,
+    },
+};
+
+/// The open windows from `zwlr_foreign_toplevel_manager_v1` with their app icons and titles,
+/// click one to focus it.
+pub struct TaskList {
+    config: TaskListConfig,
+    error_message: Option<String>,
+    toplevels: HashMap<ZwlrForeignToplevelHandleV1, Toplevel>,
+    /// Needed to activate a toplevel
+    seat: Option<(Connection, WlSeat)>,
+}
+
+impl Widget for TaskList {
+    type Config = TaskListConfig;
+
+    fn dependencies() -> &'static [Dependency] {
+        &[Dependency::Wayland("zwlr_foreign_toplevel_manager_v1")]
+    }
+
+    fn new(cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        spawn_watched_task(cx, task).detach();
+
+        Self {
+            config: config.clone(),
+            error_message: None,
+            toplevels: HashMap::new(),
+            seat: None,
+        }
+    }
+}
+
+impl Render for TaskList {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if let Some(e) = &self.error_message {
+            return widget_wrapper(cx).child(error_element(cx, e));
+        }
+        let accent = cx.global::<ThemeConfig>().accent();
+        let mut toplevels = self.toplevels.iter().collect::<Vec<_>>();
+        toplevels.sort_by_key(|(_, toplevel)| toplevel.order);
+        let mut tasks = Vec::with_capacity(toplevels.len());
+        for (handle, toplevel) in toplevels {
+            let title = if toplevel.title.is_empty() {
+                &toplevel.app_id
+            } else {
+                &toplevel.title
+            };
+            tasks.push(
+                div()
+                    .id(format!("task-{}", toplevel.order))
+                    .flex()
+                    .items_center()
+                    .gap(rems(0.25))
+                    .px(rems(0.25))
+                    .rounded(rems(0.25))
+                    .when(toplevel.activated, |this| this.bg(accent.opacity(0.3)))
+                    .when(self.config.show_icons, |this| {
+                        this.child(app_icon(&toplevel.app_id, cx))
+                    })
+                    // without titles and icons there would be nothing left to click
+                    .when(self.config.show_titles || !self.config.show_icons, |this| {
+                        this.child(truncate(title, self.config.max_title_length).into_owned())
+                    })
+                    .on_click({
+                        let handle = handle.clone();
+                        cx.listener(move |this, _, _, _| this.activate(&handle))
+                    }),
+            );
+        }
+        widget_wrapper(cx)
+            .flex()
+            .gap(rems(0.5))
+            .when(tasks.is_empty(), |this| this.child("No windows"))
+            .children(tasks)
+    }
+}
+
+impl TaskList {
+    fn activate(&self, handle: &ZwlrForeignToplevelHandleV1) {
+        let Some((connection, seat)) = &self.seat else {
+            tracing::error!("Activating a window before binding a seat");
+            return;
+        };
+        handle.activate(seat);
+        // the wayland thread may be blocked on reading, flush the requests from here
+        if let Err(e) = connection.flush() {
+            tracing::error!(error = %e, "Failed to flush wayland requests");
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct TaskListConfig {
+    /// Longer titles are cut off with an ellipsis
+    #[serde(default = "default_max_title_length")]
+    pub max_title_length: usize,
+    /// The icon of the app, from its desktop entry and the icon theme
+    #[serde(default = "default_show_icons")]
+    pub show_icons: bool,
+    #[serde(default = "default_show_titles")]
+    pub show_titles: bool,
+}
+
+impl Default for TaskListConfig {
+    fn default() -> Self {
+        Self {
+            max_title_length: default_max_title_length(),
+            show_icons: default_show_icons(),
+            show_titles: default_show_titles(),
+        }
+    }
+}
+
+fn default_max_title_length() -> usize {
+    24
+}
+
+fn default_show_icons() -> bool {
+    true
+}
+
+fn default_show_titles() -> bool {
+    true
+}
+
+async fn task(this: WeakEntity<TaskList>, cx: &mut AsyncApp) {
+    let (tx, mut rx) = mpsc::unbounded();
+    thread::spawn(move || wayland_thread(tx));
+    while let Some(update) = rx.next().await {
+        let result = this.update(cx, |this, cx| {
+            match update {
+                Update::Snapshot(toplevels) => {
+                    this.toplevels = toplevels;
+                }
+                Update::Seat { connection, seat } => {
+                    this.seat = Some((connection, seat));
+                }
+                Update::Error(e) => {
+                    this.error_message = Some(e);
+                }
+            }
+            cx.notify();
+        });
+        if result.is_err() {
+            // dropping `rx` stops the wayland thread
+            break;
+        }
+    }
+}
+
+fn wayland_thread(tx: UnboundedSender<Update>) {
+    let connection = match Connection::connect_to_env() {
+        Ok(x) => x,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to connect to wayland server");
+            if let Err(e) = tx.unbounded_send(Update::Error(format!(
+                "Failed to connect to wayland server: {e}"
+            ))) {
+                tracing::error!(error = %e, "Failed to send update to ui thread");
+            }
+            return;
+        }
+    };
+    let display = connection.display();
+    let mut event_queue = connection.new_event_queue();
+    let queue_handle = event_queue.handle();
+    let _registry = display.get_registry(&queue_handle, ());
+    let mut state = State::new(tx);
+    loop {
+        // the events come in until the compositor is gone, stop once no one listens
+        if state.tx.is_closed() {
+            tracing::info!("The task list is dropped, stopping the wayland thread");
+            break;
+        }
+        if let Err(e) = event_queue.blocking_dispatch(&mut state) {
+            tracing::error!(error = %e, "Wayland dispatch error");
+            if let Err(e) = state
+                .tx
+                .unbounded_send(Update::Error(format!("Wayland dispatch error: {e}")))
+            {
+                tracing::error!(error = %e, "Failed to send update to ui thread");
+            }
+            break;
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Toplevel {
+    title: String,
+    app_id: String,
+    activated: bool,
+    /// When it was opened relative to the others, to keep the list in a stable order
+    order: u64,
+}
+
+enum Update {
+    /// All toplevels as of the last `done` of any of them
+    Snapshot(HashMap<ZwlrForeignToplevelHandleV1, Toplevel>),
+    Seat {
+        connection: Connection,
+        seat: WlSeat,
+    },
+    Error(String),
+}
+
+struct State {
+    tx: UnboundedSender<Update>,
+    manager: Option<ZwlrForeignToplevelManagerV1>,
+    seat: Option<WlSeat>,
+    /// Changes are applied here and only sent to the ui thread on `done` of the toplevel
+    toplevels: HashMap<ZwlrForeignToplevelHandleV1, Toplevel>,
+    next_order: u64,
+}
+
+impl State {
+    fn new(tx: UnboundedSender<Update>) -> Self {
+        Self {
+            tx,
+            manager: None,
+            seat: None,
+            toplevels: HashMap::new(),
+            next_order: 0,
+        }
+    }
+    /// Does nothing once the widget is dropped, the thread stops after the current dispatch.
+    fn send_snapshot(&self) {
+        let _ = self
+            .tx
+            .unbounded_send(Update::Snapshot(self.toplevels.clone()));
+    }
+}
+
+impl Dispatch<WlRegistry, ()> for State {
+    fn event(
+        state: &mut Self,
+        proxy: &WlRegistry,
+        event: wl_registry::Event,
+        _data: &(),
+        conn: &Connection,
+        qhandle: &QueueHandle<Self>,
+    ) {
+        use wl_registry::Event;
+
+        if let Event::Global {
+            name,
+            interface,
+            version,
+        } = event
+        {
+            match interface.as_str() {
+                "zwlr_foreign_toplevel_manager_v1" => {
+                    tracing::info!(name, interface, version);
+                    state.manager = Some(proxy.bind::<ZwlrForeignToplevelManagerV1, _, _>(
+                        name,
+                        version.min(3),
+                        qhandle,
+                        (),
+                    ));
+                }
+                // the first seat, there is usually only one
+                "wl_seat" if state.seat.is_none() => {
+                    tracing::info!(name, interface, version);
+                    let seat = proxy.bind::<WlSeat, _, _>(name, version.min(7), qhandle, ());
+                    if let Err(e) = state.tx.unbounded_send(Update::Seat {
+                        connection: conn.clone(),
+                        seat: seat.clone(),
+                    }) {
+                        tracing::error!(error = %e, "Failed to send update to ui thread");
+                    }
+                    state.seat = Some(seat);
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+impl Dispatch<WlSeat, ()> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlSeat,
+        _event: wl_seat::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        use zwlr_foreign_toplevel_manager_v1::Event;
+
+        match event {
+            Event::Toplevel { toplevel } => {
+                tracing::info!(?toplevel, "New toplevel");
+                state.toplevels.insert(
+                    toplevel,
+                    Toplevel {
+                        order: state.next_order,
+                        ..Toplevel::default()
+                    },
+                );
+                state.next_order += 1;
+            }
+            Event::Finished => {
+                tracing::warn!("The foreign toplevel manager finished");
+                state.manager = None;
+            }
+            _ => (),
+        }
+    }
+
+    wayland_client::event_created_child!(State, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        proxy: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        use zwlr_foreign_toplevel_handle_v1::{Event, State as ToplevelState};
+
+        if let Event::Closed = event {
+            tracing::info!(?proxy, "Toplevel closed");
+            state.toplevels.remove(proxy);
+            proxy.destroy();
+            state.send_snapshot();
+            return;
+        }
+        let Some(toplevel) = state.toplevels.get_mut(proxy) else {
+            tracing::error!(?proxy, ?event, "A new event for non-existing toplevel");
+            return;
+        };
+        match event {
+            Event::Title { title } => toplevel.title = title,
+            Event::AppId { app_id } => toplevel.app_id = app_id,
+            Event::State { state } => {
+                // an array of native endian u32 values of the `state` enum
+                toplevel.activated = state
+                    .chunks_exact(4)
+                    .filter_map(|x| x.try_into().ok().map(u32::from_ne_bytes))
+                    .any(|x| x == ToplevelState::Activated as u32);
+            }
+            Event::Done => state.send_snapshot(),
+            _ => (),
+        }
+    }
+}
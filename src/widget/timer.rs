@@ -0,0 +1,221 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use gpui::{
+    Animation, AnimationExt, AsyncApp, Context, InteractiveElement, IntoElement, MouseButton,
+    ParentElement, Render, StatefulInteractiveElement, Styled, Task, WeakEntity, Window, div, red,
+    rems,
+};
+use serde::{Deserialize, Serialize};
+use zbus::{Connection, proxy, zvariant::Value};
+
+use crate::widget::{Widget, spawn_task, widget_wrapper};
+
+/// A session countdown timer, left click to start/pause, right click to reset.
+pub struct Timer {
+    duration: Duration,
+    notify: bool,
+    state: TimerState,
+    ticker: Option<Task<()>>,
+}
+
+#[derive(Clone, Copy)]
+enum TimerState {
+    Stopped,
+    Running { end: Instant },
+    Paused { remaining: Duration },
+    Finished,
+}
+
+impl Widget for Timer {
+    type Config = TimerConfig;
+
+    fn new(_cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        Self {
+            duration: Duration::from_secs(config.duration),
+            notify: config.notify,
+            state: TimerState::Stopped,
+            ticker: None,
+        }
+    }
+
+    fn activate(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.toggle(cx);
+    }
+
+    fn activatable(&self) -> bool {
+        true
+    }
+}
+
+impl Timer {
+    fn remaining(&self) -> Duration {
+        match self.state {
+            TimerState::Stopped => self.duration,
+            TimerState::Running { end } => end.saturating_duration_since(Instant::now()),
+            TimerState::Paused { remaining } => remaining,
+            TimerState::Finished => Duration::ZERO,
+        }
+    }
+    fn toggle(&mut self, cx: &mut Context<Self>) {
+        match self.state {
+            TimerState::Stopped | TimerState::Finished => self.start(self.duration, cx),
+            TimerState::Paused { remaining } => self.start(remaining, cx),
+            TimerState::Running { .. } => {
+                self.state = TimerState::Paused {
+                    remaining: self.remaining(),
+                };
+                // dropping the task cancels it
+                self.ticker = None;
+            }
+        }
+        cx.notify();
+    }
+    fn start(&mut self, remaining: Duration, cx: &mut Context<Self>) {
+        self.state = TimerState::Running {
+            end: Instant::now() + remaining,
+        };
+        self.ticker = Some(spawn_task(cx, tick));
+    }
+    fn reset(&mut self, cx: &mut Context<Self>) {
+        self.state = TimerState::Stopped;
+        self.ticker = None;
+        cx.notify();
+    }
+}
+
+impl Render for Timer {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let remaining = self.remaining().as_millis().div_ceil(1000);
+        let wrapper = widget_wrapper(cx)
+            .id("timer")
+            .on_click(cx.listener(|this, _, _, cx| this.toggle(cx)))
+            .on_mouse_down(
+                MouseButton::Right,
+                cx.listener(|this, _, _, cx| this.reset(cx)),
+            )
+            .flex()
+            .gap(rems(0.25))
+            .child(div().font_family("Material Symbols Rounded").child(
+                if matches!(self.state, TimerState::Running { .. }) {
+                    ""
+                } else {
+                    ""
+                },
+            ))
+            .child(format!("{:02}:{:02}", remaining / 60, remaining % 60));
+
+        if let TimerState::Finished = self.state {
+            wrapper
+                .text_color(red())
+                .with_animation(
+                    "timer-finished",
+                    Animation::new(Duration::from_secs(1)).repeat(),
+                    |element, delta| element.opacity(if delta < 0.5 { 1.0 } else { 0.4 }),
+                )
+                .into_any_element()
+        } else {
+            wrapper.into_any_element()
+        }
+    }
+}
+
+async fn tick(this: WeakEntity<Timer>, cx: &mut AsyncApp) {
+    loop {
+        let Ok(remaining) = this.read_with(cx, |this, _| this.remaining()) else {
+            return;
+        };
+        if remaining.is_zero() {
+            break;
+        }
+        // wake up on the next whole second of the countdown
+        let until_next_second = Duration::from_nanos(remaining.subsec_nanos().into());
+        cx.background_executor()
+            .timer(if until_next_second.is_zero() {
+                Duration::from_secs(1)
+            } else {
+                until_next_second
+            })
+            .await;
+        let _ = this.update(cx, |_, cx| cx.notify());
+    }
+
+    let Ok(notify) = this.update(cx, |this, cx| {
+        this.state = TimerState::Finished;
+        cx.notify();
+        this.notify
+    }) else {
+        return;
+    };
+    tracing::info!("Timer finished");
+    if notify && let Err(e) = send_notification().await {
+        tracing::error!(error = %e, "Failed to send timer notification");
+    }
+}
+
+async fn send_notification() -> zbus::Result<()> {
+    let connection = Connection::session().await?;
+    let proxy = NotificationsProxy::new(&connection).await?;
+    proxy
+        .notify(
+            "eucalyptus-twig",
+            0,
+            "alarm",
+            "Timer",
+            "Time is up",
+            &[],
+            HashMap::new(),
+            -1,
+        )
+        .await?;
+    Ok(())
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct TimerConfig {
+    /// Countdown length in seconds
+    #[serde(default = "default_duration")]
+    duration: u64,
+    /// Send a desktop notification when the countdown hits zero
+    #[serde(default = "default_notify")]
+    notify: bool,
+}
+
+impl Default for TimerConfig {
+    fn default() -> Self {
+        Self {
+            duration: default_duration(),
+            notify: default_notify(),
+        }
+    }
+}
+
+fn default_duration() -> u64 {
+    25 * 60
+}
+
+fn default_notify() -> bool {
+    true
+}
+
+// <https://specifications.freedesktop.org/notification/latest/protocol.html>
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
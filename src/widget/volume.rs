@@ -17,65 +17,158 @@ use pipewire::{
         param::ParamType,
         pod::{Pod, deserialize::PodDeserializer},
         sys::{SPA_PROP_channelVolumes, SPA_PROP_mute},
-        utils::Id,
+        utils::{Id, dict::DictRef},
     },
     types::ObjectType,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::widget::{Widget, widget_wrapper};
+use crate::widget::{
+    Widget, error_element, is_preview, spawn_watched_task, transition, widget_wrapper,
+};
 
 pub struct Volume {
+    config: VolumeConfig,
     error_message: Option<String>,
     mute: Option<bool>,
     volume: Option<f32>,
+    /// What the default sink is, `None` if unknown
+    output: Option<OutputKind>,
 }
 
 impl Widget for Volume {
-    type Config = ();
+    type Config = VolumeConfig;
 
-    fn new(cx: &mut Context<Self>, _config: &Self::Config) -> Self {
-        cx.spawn(task).detach();
+    fn new(cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        if is_preview(cx) {
+            return Self {
+                config: config.clone(),
+                error_message: None,
+                mute: Some(false),
+                // shown as 60%, the volume is cubic
+                volume: Some(0.6_f32.powi(3)),
+                output: None,
+            };
+        }
+        spawn_watched_task(cx, task).detach();
 
         Self {
+            config: config.clone(),
             error_message: None,
             mute: None,
             volume: None,
+            output: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Debug, PartialEq)]
+pub struct VolumeConfig {
+    /// Glyphs by the kind of the default sink (`speaker`, `headphones`, `hdmi` or `bluetooth`),
+    /// over the built-in ones
+    #[serde(default)]
+    pub output_icons: HashMap<String, String>,
+}
+
+impl VolumeConfig {
+    /// The glyph of `output` from `output_icons`, otherwise the built-in one. Speakers and unknown
+    /// sinks show the volume.
+    fn icon(&self, output: Option<OutputKind>, volume: f32) -> String {
+        if let Some(icon) = output.and_then(|x| self.output_icons.get(x.name())) {
+            return icon.clone();
+        }
+        output
+            .and_then(|x| x.default_icon())
+            .unwrap_or_else(|| volume_glyph(volume))
+            .to_owned()
+    }
+}
+
+/// The kind of an audio sink, from the properties of its node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputKind {
+    Speaker,
+    Headphones,
+    Hdmi,
+    Bluetooth,
+}
+
+impl OutputKind {
+    fn from_props(props: &DictRef) -> Option<Self> {
+        if props.get("device.api") == Some("bluez5") {
+            return Some(Self::Bluetooth);
+        }
+        let form_factor = props.get("device.form-factor");
+        if matches!(form_factor, Some("headphone" | "headset")) {
+            return Some(Self::Headphones);
+        }
+        // e.g. `alsa_output.pci-0000_00_1f.3.hdmi-stereo`
+        if form_factor == Some("tv") || props.get("node.name").is_some_and(|x| x.contains("hdmi")) {
+            return Some(Self::Hdmi);
+        }
+        match form_factor {
+            Some("speaker" | "internal" | "computer" | "hifi") => Some(Self::Speaker),
+            _ => None,
+        }
+    }
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::Speaker => "speaker",
+            Self::Headphones => "headphones",
+            Self::Hdmi => "hdmi",
+            Self::Bluetooth => "bluetooth",
+        }
+    }
+    /// `None` for the speaker, which shows the volume instead
+    const fn default_icon(&self) -> Option<&'static str> {
+        match self {
+            Self::Speaker => None,
+            Self::Headphones => Some(""),
+            Self::Hdmi => Some(""),
+            Self::Bluetooth => Some(""),
         }
     }
 }
 
 impl Render for Volume {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         if let Some(e) = &self.error_message {
-            widget_wrapper().child(e.clone())
+            widget_wrapper(cx).child(error_element(cx, e))
         } else if self.mute == Some(true) {
-            widget_wrapper()
+            widget_wrapper(cx)
                 .font_family("Material Symbols Rounded")
                 .child("󰖁")
         } else if let Some(volume) = self.volume {
             let volume = volume.cbrt() * 100.0;
-            widget_wrapper()
+            widget_wrapper(cx)
                 .flex()
                 .gap(rems(0.25))
                 .child(
                     div()
                         .font_family("Material Symbols Rounded")
-                        .child(if volume <= 0.0 {
-                            "󰕿"
-                        } else if volume < 50.0 {
-                            "󰖀"
-                        } else {
-                            "󰕾"
-                        }),
+                        .child(self.config.icon(self.output, volume)),
                 )
-                .child(format!("{:.1}", volume))
+                .child({
+                    let text = format!("{:.1}", volume);
+                    transition("volume", &text, text.clone(), cx)
+                })
         } else {
-            widget_wrapper().child("?")
+            widget_wrapper(cx).child("?")
         }
     }
 }
 
+/// The speaker glyph by the volume in percent.
+fn volume_glyph(volume: f32) -> &'static str {
+    if volume <= 0.0 {
+        "󰕿"
+    } else if volume < 50.0 {
+        "󰖀"
+    } else {
+        "󰕾"
+    }
+}
+
 async fn task(this: WeakEntity<Volume>, cx: &mut AsyncApp) {
     let (tx, mut rx) = mpsc::unbounded();
     thread::spawn(move || pipewire_thread(tx));
@@ -93,6 +186,12 @@ async fn task(this: WeakEntity<Volume>, cx: &mut AsyncApp) {
                     cx.notify();
                 });
             }
+            Update::Output(output) => {
+                let _ = this.update(cx, |this, cx| {
+                    this.output = output;
+                    cx.notify();
+                });
+            }
             Update::ErrorMessage(e) => {
                 let _ = this.update(cx, |this, cx| {
                     this.error_message = Some(e);
@@ -107,6 +206,7 @@ async fn task(this: WeakEntity<Volume>, cx: &mut AsyncApp) {
 enum Update {
     Volume(Option<f32>),
     Mute(Option<bool>),
+    Output(Option<OutputKind>),
     ErrorMessage(String),
 }
 
@@ -180,6 +280,7 @@ fn pipewire_thread(tx: UnboundedSender<Update>) {
     let volumes = Rc::new(RefCell::new(
         HashMap::<String, (Option<bool>, Option<f32>)>::new(),
     ));
+    let outputs = Rc::new(RefCell::new(HashMap::<String, OutputKind>::new()));
     let default_sink_name = Rc::new(RefCell::new(None::<String>));
 
     let _registry_listener = registry
@@ -208,6 +309,26 @@ fn pipewire_thread(tx: UnboundedSender<Update>) {
                     tracing::info!(node_name, "Got a node");
                     let listener = node
                         .add_listener_local()
+                        .info({
+                            let node_name = node_name.clone();
+                            let outputs = outputs.clone();
+                            let default_sink_name = default_sink_name.clone();
+                            let tx = tx.clone();
+                            let main_loop = main_loop.clone();
+                            move |info| {
+                                let Some(output) = info.props().and_then(OutputKind::from_props) else {
+                                    return;
+                                };
+                                tracing::info!(node_name, ?output, "Got the kind of a node");
+                                outputs.borrow_mut().insert(node_name.clone(), output);
+                                if Some(&node_name) == default_sink_name.borrow().as_ref()
+                                    && let Err(e) = tx.unbounded_send(Update::Output(Some(output)))
+                                {
+                                    tracing::warn!(error = %e, "Failed to send update to ui thread");
+                                    main_loop.quit();
+                                }
+                            }
+                        })
                         .param({
                             let volumes = volumes.clone();
                             let default_sink_name = default_sink_name.clone();
@@ -239,10 +360,11 @@ fn pipewire_thread(tx: UnboundedSender<Update>) {
                             let default_sink_name = default_sink_name.clone();
                             let tx = tx.clone();
                             let volumes = volumes.clone();
+                            let outputs = outputs.clone();
                             let main_loop = main_loop.clone();
                             move |subject, key, type_, value| {
                                 // TODO: what is this subject parameter
-                                metadata_listener(subject, key, type_, value, &tx, &volumes, &default_sink_name, &main_loop)
+                                metadata_listener(subject, key, type_, value, &tx, &volumes, &outputs, &default_sink_name, &main_loop)
                             }
                         })
                         .register();
@@ -359,6 +481,7 @@ fn metadata_listener(
     value: Option<&str>,
     tx: &UnboundedSender<Update>,
     volumes: &Rc<RefCell<HashMap<String, (Option<bool>, Option<f32>)>>>,
+    outputs: &Rc<RefCell<HashMap<String, OutputKind>>>,
     default_sink_name: &Rc<RefCell<Option<String>>>,
     main_loop: &MainLoopRc,
 ) -> i32 {
@@ -381,6 +504,11 @@ fn metadata_listener(
                         tracing::warn!(error = %e, "Failed to send update to ui thread");
                         main_loop.quit();
                     }
+                    let output = outputs.borrow().get(&value.name).copied();
+                    if let Err(e) = tx.unbounded_send(Update::Output(output)) {
+                        tracing::warn!(error = %e, "Failed to send update to ui thread");
+                        main_loop.quit();
+                    }
                     *default_sink_name.borrow_mut() = Some(value.name);
                 }
                 Err(e) => {
@@ -408,3 +536,34 @@ fn metadata_listener(
 struct DefaultAudioSink {
     name: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_icons() {
+        let mut config = VolumeConfig::default();
+        assert_eq!(
+            config.icon(Some(OutputKind::Speaker), 30.0),
+            volume_glyph(30.0)
+        );
+        assert_eq!(config.icon(None, 80.0), volume_glyph(80.0));
+        assert_eq!(config.icon(Some(OutputKind::Headphones), 30.0), "");
+        config
+            .output_icons
+            .insert("speaker".to_owned(), "speaker".to_owned());
+        assert_eq!(config.icon(Some(OutputKind::Speaker), 30.0), "speaker");
+    }
+
+    #[test]
+    fn volume_glyph_tiers() {
+        assert_eq!(volume_glyph(-1.0), "󰕿");
+        assert_eq!(volume_glyph(0.0), "󰕿");
+        assert_eq!(volume_glyph(0.1), "󰖀");
+        assert_eq!(volume_glyph(49.9), "󰖀");
+        assert_eq!(volume_glyph(50.0), "󰕾");
+        assert_eq!(volume_glyph(100.0), "󰕾");
+        assert_eq!(volume_glyph(150.0), "󰕾");
+    }
+}
@@ -0,0 +1,139 @@
+use futures::StreamExt;
+use gpui::{
+    AsyncApp, Context, IntoElement, ParentElement, Render, Styled, WeakEntity, Window, div,
+    prelude::FluentBuilder, rems,
+};
+use serde::{Deserialize, Serialize};
+use zbus::Connection;
+
+use crate::{
+    util::retry_with_backoff,
+    widget::{
+        Widget,
+        dependency::Dependency,
+        error_element,
+        network_manager::{NetworkManagerProxy, active_vpns},
+        spawn_watched_task, widget_wrapper,
+    },
+};
+
+/// The active VPN connections of NetworkManager, WireGuard included.
+pub struct Vpn {
+    hide_when_inactive: bool,
+    error_message: Option<String>,
+    /// Names of the active VPNs
+    active: Vec<String>,
+}
+
+impl Widget for Vpn {
+    type Config = VpnConfig;
+
+    fn dependencies() -> &'static [Dependency] {
+        &[Dependency::SystemDbus("org.freedesktop.NetworkManager")]
+    }
+
+    fn new(cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        spawn_watched_task(cx, task).detach();
+
+        Self {
+            hide_when_inactive: config.hide_when_inactive,
+            error_message: None,
+            active: Vec::new(),
+        }
+    }
+
+    fn visible(&self) -> bool {
+        !self.hide_when_inactive || !self.active.is_empty() || self.error_message.is_some()
+    }
+}
+
+impl Render for Vpn {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if let Some(e) = &self.error_message {
+            return widget_wrapper(cx).child(error_element(cx, e));
+        }
+        widget_wrapper(cx)
+            .flex()
+            .items_center()
+            .gap(rems(0.25))
+            .child(
+                div()
+                    .font_family("Material Symbols Rounded")
+                    .when(self.active.is_empty(), |this| this.opacity(0.5))
+                    .child(""),
+            )
+            .when(!self.active.is_empty(), |this| {
+                this.child(self.active.join(", "))
+            })
+    }
+}
+
+async fn task(this: WeakEntity<Vpn>, cx: &mut AsyncApp) {
+    let Some(connection) = retry_with_backoff(
+        cx,
+        async |_| Connection::system().await,
+        |cx, e, retry_in| {
+            tracing::error!(error = %e, ?retry_in, "Failed to connect to system bus");
+            this.update(cx, |this, cx| {
+                this.error_message = Some(format!("Failed to connect to system bus: {e}"));
+                cx.notify();
+            })
+            .is_ok()
+        },
+    )
+    .await
+    else {
+        return;
+    };
+    let proxy = match NetworkManagerProxy::new(&connection).await {
+        Ok(x) => x,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to connect to NetworkManager");
+            let _ = this.update(cx, |this, cx| {
+                this.error_message = Some(format!("Failed to connect to NetworkManager: {e}"));
+                cx.notify();
+            });
+            return;
+        }
+    };
+    let _ = this.update(cx, |this, cx| {
+        this.error_message = None;
+        cx.notify();
+    });
+    let mut stream = proxy.receive_active_connections_changed().await;
+    while let Some(active_connections) = stream.next().await {
+        match active_connections.get().await {
+            Ok(active_connections) => {
+                let active = active_vpns(&connection, &active_connections).await;
+                tracing::info!(?active, "Active VPNs changed");
+                let _ = this.update(cx, |this, cx| {
+                    this.active = active;
+                    cx.notify();
+                });
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to get new ActiveConnections");
+            }
+        }
+    }
+    tracing::warn!("Receive ActiveConnections stream ended");
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct VpnConfig {
+    /// Hide the widget while no VPN is active, otherwise a dimmed shield is shown
+    #[serde(default = "default_hide_when_inactive")]
+    pub hide_when_inactive: bool,
+}
+
+impl Default for VpnConfig {
+    fn default() -> Self {
+        Self {
+            hide_when_inactive: default_hide_when_inactive(),
+        }
+    }
+}
+
+fn default_hide_when_inactive() -> bool {
+    true
+}
@@ -1,16 +1,23 @@
-use std::{collections::HashMap, thread};
+use std::{
+    collections::HashMap, env, hash::Hash, ops::Range, sync::OnceLock, thread, time::Duration,
+};
 
 use futures::{
     StreamExt,
     channel::mpsc::{self, UnboundedSender},
 };
 use gpui::{
-    AsyncApp, Context, InteractiveElement, IntoElement, ParentElement, Render,
-    StatefulInteractiveElement, Styled, WeakEntity, Window, black, div, opaque_grey, red, rems,
+    AnyElement, AsyncApp, Context, Div, InteractiveElement, IntoElement, ParentElement, Render,
+    ScrollWheelEvent, StatefulInteractiveElement, Styled, WeakEntity, Window, black, div,
+    opaque_grey, prelude::FluentBuilder, rems,
 };
+use serde::{Deserialize, Serialize};
 use wayland_client::{
     Connection, Dispatch, QueueHandle,
-    protocol::wl_registry::{self, WlRegistry},
+    protocol::{
+        wl_callback::{self, WlCallback},
+        wl_registry::{self, WlRegistry},
+    },
 };
 use wayland_protocols::ext::workspace::v1::client::{
     ext_workspace_group_handle_v1::{self, ExtWorkspaceGroupHandleV1},
@@ -18,137 +25,397 @@ use wayland_protocols::ext::workspace::v1::client::{
     ext_workspace_manager_v1::{self, ExtWorkspaceManagerV1},
 };
 
-use crate::widget::{Widget, widget_wrapper};
-
-const IGNORE_HIDDEN: bool = true;
+use crate::{
+    config::ThemeConfig,
+    widget::{
+        ScrollAccumulator, Widget,
+        dependency::{Dependency, wayland_global_supported},
+        error_element, interval_from_secs, scroll_delta, spawn_watched_task, transition,
+        widget_wrapper,
+    },
+};
 
 pub struct Workspaces {
+    config: WorkspacesConfig,
     error_message: Option<String>,
+    scrolled: ScrollAccumulator,
     workspaces: HashMap<ExtWorkspaceHandleV1, Workspace>,
+    manager: Option<(Connection, ExtWorkspaceManagerV1)>,
 }
 
 impl Widget for Workspaces {
-    type Config = ();
+    type Config = WorkspacesConfig;
 
-    fn new(cx: &mut Context<Self>, _config: &Self::Config) -> Self {
-        cx.spawn(task).detach();
+    fn dependencies() -> &'static [Dependency] {
+        &[Dependency::Wayland("ext_workspace_manager_v1")]
+    }
+
+    fn new(cx: &mut Context<Self>, config: &Self::Config) -> Self {
+        let resync_interval = config.resync_interval.and_then(|seconds| {
+            interval_from_secs("resync_interval", seconds)
+                .inspect_err(|e| {
+                    tracing::warn!(seconds, error = %e, "Invalid resync_interval, not resyncing");
+                })
+                .ok()
+        });
+        spawn_watched_task(cx, async move |this, cx| {
+            task(this, cx, resync_interval).await
+        })
+        .detach();
 
         Self {
+            config: config.clone(),
             error_message: None,
+            scrolled: ScrollAccumulator::default(),
+            workspaces: HashMap::new(),
+            manager: None,
+        }
+    }
+}
+
+impl Workspaces {
+    /// A widget that only shows `message`, for when there is no backend to use.
+    pub fn unsupported(config: &WorkspacesConfig, message: String) -> Self {
+        Self {
+            config: config.clone(),
+            scrolled: ScrollAccumulator::default(),
+            error_message: Some(message),
             workspaces: HashMap::new(),
+            manager: None,
         }
     }
 }
 
 impl Render for Workspaces {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         if let Some(e) = &self.error_message {
-            return widget_wrapper().child(e.trim().to_owned());
-        }
-
-        widget_wrapper().flex().gap(rems(0.5)).children(
-            self.workspaces
-                .iter()
-                .enumerate()
-                .filter_map(|(index, (handle, workspace))| {
-                    if !IGNORE_HIDDEN && workspace.state.hidden {
-                        None
-                    } else {
-                        let name = if workspace.state.active {
-                            format!(" > {} < ", workspace.name)
-                        } else {
-                            workspace.name.clone()
-                        };
-
-                        let div = if workspace.state.urgent {
-                            div().text_color(black()).bg(red()).rounded(rems(0.5))
-                        } else if workspace.state.active {
-                            div()
-                                .text_color(black())
-                                .bg(opaque_grey(1.0, 0.75))
-                                .rounded(rems(0.5))
-                        } else {
-                            div()
-                        };
-                        Some(if workspace.capabilities.activate {
-                            div.id(format!("workspace-{index}"))
-                                .on_click({
-                                    let handle = handle.clone();
-                                    move |_, _, _| {
-                                        handle.activate();
-                                    }
-                                })
-                                .child(name)
-                                .into_any_element()
-                        } else {
-                            div.child(name).into_any_element()
+            return widget_wrapper(cx).child(error_element(cx, e));
+        }
+
+        widget_wrapper(cx)
+            .flex()
+            .gap(rems(0.5))
+            .when(self.config.scroll, |this| {
+                this.on_scroll_wheel(cx.listener(|this, event: &ScrollWheelEvent, window, cx| {
+                    let delta = scroll_delta(event, window, cx);
+                    let steps = this.scrolled.add(delta, window.line_height());
+                    this.scroll(steps);
+                }))
+            })
+            .children(self.render_workspaces(cx))
+    }
+}
+
+impl Workspaces {
+    fn render_workspaces(&self, cx: &mut Context<Self>) -> Vec<AnyElement> {
+        let theme = cx.global::<ThemeConfig>().clone();
+        let workspaces = self.sorted_workspaces();
+        let active = workspaces
+            .iter()
+            .position(|(_, workspace)| workspace.state.active);
+        let elements = workspaces
+            .into_iter()
+            .enumerate()
+            .map(|(index, (handle, workspace))| {
+                let div = self.config.render_workspace(
+                    WorkspaceView {
+                        name: &workspace.name,
+                        id: workspace.id.as_deref(),
+                        active: workspace.state.active,
+                        urgent: workspace.state.urgent,
+                        // only there with `show_hidden`
+                        dimmed: workspace.state.hidden,
+                    },
+                    &theme,
+                );
+                let element = if workspace.capabilities.activate {
+                    div.id(format!("workspace-{index}"))
+                        .on_click({
+                            let handle = handle.clone();
+                            cx.listener(move |this, _, _, _| this.activate(&handle))
                         })
-                    }
-                }),
-        )
+                        .into_any_element()
+                } else {
+                    div.into_any_element()
+                };
+                transition(
+                    format!("workspace-highlight-{index}"),
+                    (workspace.state.active, workspace.state.urgent),
+                    element,
+                    cx,
+                )
+            })
+            .collect();
+        self.config.limit_visible(elements, active)
+    }
+    /// Workspaces to show in a stable order, by coordinates and then name.
+    fn sorted_workspaces(&self) -> Vec<(&ExtWorkspaceHandleV1, &Workspace)> {
+        let mut workspaces = self
+            .workspaces
+            .iter()
+            .filter(|(_, workspace)| self.config.show_hidden || !workspace.state.hidden)
+            .collect::<Vec<_>>();
+        workspaces
+            .sort_by(|(_, a), (_, b)| (&a.coordinates, &a.name).cmp(&(&b.coordinates, &b.name)));
+        workspaces
+    }
+    fn activate(&self, handle: &ExtWorkspaceHandleV1) {
+        let Some((connection, manager)) = &self.manager else {
+            tracing::error!("Activating a workspace before binding the workspace manager");
+            return;
+        };
+        handle.activate();
+        manager.commit();
+        // the wayland thread may be blocked on reading, flush the requests from here
+        if let Err(e) = connection.flush() {
+            tracing::error!(error = %e, "Failed to flush wayland requests");
+        }
+    }
+    fn scroll(&self, steps: i32) {
+        let workspaces = self.sorted_workspaces();
+        let Some(current) = workspaces
+            .iter()
+            .position(|(_, workspace)| workspace.state.active)
+        else {
+            return;
+        };
+        if let Some((handle, _)) = self
+            .config
+            .scroll_target(current, workspaces.len(), steps)
+            .and_then(|index| workspaces.get(index))
+        {
+            self.activate(handle);
+        }
     }
 }
 
-async fn task(this: WeakEntity<Workspaces>, cx: &mut AsyncApp) {
+/// Shared by the ext-workspace and hyprland workspace widgets.
+#[derive(Deserialize, Serialize, Default, Clone, Debug, PartialEq)]
+pub struct WorkspacesConfig {
+    /// Material Symbols icons (glyphs or ligature names) by workspace name or id,
+    /// e.g. `"1" = "terminal"`
+    #[serde(default)]
+    workspace_icons: HashMap<String, String>,
+    /// Icon for workspaces without an entry in `workspace_icons`, their name is shown if unset
+    #[serde(default)]
+    default_icon: Option<String>,
+    /// Used by the `Workspaces` widget
+    #[serde(default)]
+    pub backend: WorkspaceBackend,
+    /// Also show the workspaces the compositor marks as hidden, dimmed (ext-workspace only)
+    #[serde(default)]
+    show_hidden: bool,
+    /// Switch to the previous/next workspace by scrolling over the widget
+    #[serde(default)]
+    pub scroll: bool,
+    /// Scrolling past the last workspace goes to the first one, and the other way around
+    #[serde(default)]
+    scroll_wrap: bool,
+    #[serde(default)]
+    style: WorkspaceStyle,
+    /// Only show this many workspaces around the active one, with `…` for the others
+    #[serde(default)]
+    max_visible: Option<usize>,
+    /// Re-read all workspaces every this many seconds, in case the compositor misses sending some
+    /// changes (ext-workspace only, never if unset), at least 0.1
+    #[serde(default)]
+    resync_interval: Option<f64>,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkspaceStyle {
+    /// The icon or the name of each workspace
+    #[default]
+    Named,
+    /// A dot for each workspace, larger and in the accent color when active
+    Dots,
+}
+
+/// One workspace as the workspace widgets show it, whatever the backend.
+pub struct WorkspaceView<'a> {
+    pub name: &'a str,
+    pub id: Option<&'a str>,
+    pub active: bool,
+    pub urgent: bool,
+    /// e.g. hidden or empty workspaces
+    pub dimmed: bool,
+}
+
+impl WorkspacesConfig {
+    /// A workspace highlighted by its state, the caller adds the handlers.
+    pub fn render_workspace(&self, workspace: WorkspaceView, theme: &ThemeConfig) -> Div {
+        if self.style == WorkspaceStyle::Dots {
+            return Self::render_dot(&workspace, theme);
+        }
+        let div = if workspace.urgent {
+            div()
+                .text_color(black())
+                .bg(theme.urgent())
+                .rounded(rems(0.5))
+        } else if workspace.active {
+            div()
+                .text_color(black())
+                .bg(theme.accent())
+                .rounded(rems(0.5))
+        } else {
+            div()
+        };
+        div.when(workspace.dimmed, |this| this.opacity(0.5).italic())
+            .child(self.label(workspace.name, workspace.id, workspace.active))
+    }
+    fn render_dot(workspace: &WorkspaceView, theme: &ThemeConfig) -> Div {
+        let dot = if workspace.urgent {
+            div().size(rems(0.625)).bg(theme.urgent())
+        } else if workspace.active {
+            div().size(rems(0.625)).bg(theme.accent())
+        } else {
+            div().size(rems(0.375)).bg(opaque_grey(1.0, 0.6))
+        };
+        // same size for every dot, so it's easy to click
+        div()
+            .size(rems(0.75))
+            .flex()
+            .items_center()
+            .justify_center()
+            .when(workspace.dimmed, |this| this.opacity(0.5))
+            .child(dot.rounded_full())
+    }
+    /// At most `max_visible` of the rendered workspaces, keeping `active` in the window, with a `…`
+    /// on each side that has more workspaces.
+    pub fn limit_visible(
+        &self,
+        mut workspaces: Vec<AnyElement>,
+        active: Option<usize>,
+    ) -> Vec<AnyElement> {
+        let len = workspaces.len();
+        let Some(range) = self.visible_range(len, active) else {
+            return workspaces;
+        };
+        let overflow = || div().child("…").into_any_element();
+        let mut visible = Vec::with_capacity(range.len() + 2);
+        if range.start > 0 {
+            visible.push(overflow());
+        }
+        let end = range.end;
+        visible.extend(workspaces.drain(range));
+        if end < len {
+            visible.push(overflow());
+        }
+        visible
+    }
+    /// The workspaces shown by [`Self::limit_visible`] out of `len`, `None` if they all fit.
+    pub fn visible_range(&self, len: usize, active: Option<usize>) -> Option<Range<usize>> {
+        let max_visible = self.max_visible.map(|x| x.max(1)).filter(|&x| x < len)?;
+        let start = active
+            .unwrap_or(0)
+            .saturating_sub(max_visible / 2)
+            .min(len - max_visible);
+        Some(start..start + max_visible)
+    }
+    /// The content of a workspace button, its icon if there is one, its name otherwise.
+    fn label(&self, name: &str, id: Option<&str>, active: bool) -> AnyElement {
+        let icon = self
+            .workspace_icons
+            .get(name)
+            .or_else(|| id.and_then(|id| self.workspace_icons.get(id)))
+            .or(self.default_icon.as_ref());
+        let text = icon.map(String::as_str).unwrap_or(name);
+        let text = if active {
+            format!(" > {text} < ")
+        } else {
+            text.to_owned()
+        };
+        div()
+            .when(icon.is_some(), |this| {
+                this.font_family("Material Symbols Rounded")
+            })
+            .child(text)
+            .into_any_element()
+    }
+    /// The index to switch to after scrolling `steps` of a [`ScrollAccumulator`] from `current`,
+    /// `None` to stay. Only one workspace at a time, `current` is stale until the compositor
+    /// reports the switch.
+    pub fn scroll_target(&self, current: usize, len: usize, steps: i32) -> Option<usize> {
+        if steps < 0 {
+            if current + 1 < len {
+                Some(current + 1)
+            } else {
+                self.scroll_wrap.then_some(0)
+            }
+        } else if steps > 0 {
+            if current > 0 {
+                Some(current - 1)
+            } else {
+                self.scroll_wrap.then(|| len.checked_sub(1)).flatten()
+            }
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkspaceBackend {
+    /// The first supported one of the backends below
+    #[default]
+    Auto,
+    ExtWorkspace,
+    Hyprland,
+    /// Also works with i3
+    Sway,
+}
+
+impl WorkspaceBackend {
+    /// The backend to use, `None` if `Auto` finds nothing supported.
+    pub fn resolve(self) -> Option<Self> {
+        match self {
+            Self::Auto => {
+                let backend = if env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+                    Some(Self::Hyprland)
+                } else if ext_workspace_supported() {
+                    Some(Self::ExtWorkspace)
+                } else if env::var_os("SWAYSOCK").is_some() || env::var_os("I3SOCK").is_some() {
+                    Some(Self::Sway)
+                } else {
+                    None
+                };
+                tracing::info!(?backend, "Detected workspace backend");
+                backend
+            }
+            backend => Some(backend),
+        }
+    }
+}
+
+/// Whether the compositor advertises `ext_workspace_manager_v1`. Listing the globals blocks on a
+/// roundtrip, so it's only done for the first bar and reused by the others and on reload.
+fn ext_workspace_supported() -> bool {
+    static SUPPORTED: OnceLock<bool> = OnceLock::new();
+    *SUPPORTED.get_or_init(|| {
+        wayland_global_supported("ext_workspace_manager_v1").unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Failed to list wayland globals");
+            false
+        })
+    })
+}
+
+async fn task(this: WeakEntity<Workspaces>, cx: &mut AsyncApp, resync_interval: Option<Duration>) {
     let (tx, mut rx) = mpsc::unbounded();
     // TODO: see if thread is avoidable using `event_queue.poll_dispatch_pending`
-    thread::spawn(move || wayland_thread(tx));
+    thread::spawn(move || wayland_thread(tx, resync_interval));
     while let Some(update) = rx.next().await {
-        let _ = this.update(cx, |this, cx| {
+        let result = this.update(cx, |this, cx| {
             match update {
-                Update::NewWorkspace { handle, workspace } => {
-                    this.workspaces.insert(handle, workspace);
+                Update::Snapshot(workspaces) => {
+                    this.workspaces = workspaces;
                 }
-                Update::WorkspaceEvent { handle, event } => {
-                    use ext_workspace_handle_v1::Event;
-
-                    let Some(workspace) = this.workspaces.get_mut(&handle) else {
-                        tracing::error!(?handle, ?event, "A new event for non-existing workspace");
-                        return;
-                    };
-                    match event {
-                        Event::Id { id } => {
-                            tracing::info!(id);
-                            workspace.id = Some(id);
-                        }
-                        Event::Name { name } => {
-                            tracing::info!(name);
-                            workspace.name = name;
-                        }
-                        Event::Coordinates { coordinates } => {
-                            tracing::info!(?coordinates);
-                            workspace.coordinates = Some(coordinates);
-                        }
-                        Event::State { state } => {
-                            let state = match state.into_result() {
-                                Ok(x) => x,
-                                Err(e) => {
-                                    tracing::error!(error = %e, "Failed to extract state");
-                                    return;
-                                }
-                            };
-                            tracing::info!(?state);
-                            workspace.state = state.into();
-                        }
-                        Event::Capabilities { capabilities } => {
-                            let capabilities = match capabilities.into_result() {
-                                Ok(x) => x,
-                                Err(e) => {
-                                    tracing::error!(error = %e, "Failed to extract state");
-                                    return;
-                                }
-                            };
-                            tracing::info!(?capabilities);
-                            workspace.capabilities = capabilities.into();
-                        }
-                        Event::Removed => {
-                            if this.workspaces.remove(&handle).is_none() {
-                                tracing::error!("Remove event for a non-existing workspace");
-                            }
-                            tracing::info!(?handle, "remove workspace");
-                        }
-                        _ => (),
-                    }
+                Update::Manager {
+                    connection,
+                    manager,
+                } => {
+                    this.manager = Some((connection, manager));
                 }
                 Update::Error(e) => {
                     this.error_message = Some(e);
@@ -156,10 +423,14 @@ async fn task(this: WeakEntity<Workspaces>, cx: &mut AsyncApp) {
             }
             cx.notify();
         });
+        if result.is_err() {
+            // dropping `rx` stops the wayland threads
+            break;
+        }
     }
 }
 
-fn wayland_thread(tx: UnboundedSender<Update>) {
+fn wayland_thread(tx: UnboundedSender<Update>, resync_interval: Option<Duration>) {
     let connection = match Connection::connect_to_env() {
         Ok(x) => x,
         Err(e) => {
@@ -177,7 +448,31 @@ fn wayland_thread(tx: UnboundedSender<Update>) {
     let queue_handle = event_queue.handle();
     let _registry = display.get_registry(&queue_handle, ());
     let mut state = State::new(tx);
+    if let Some(interval) = resync_interval {
+        let connection = connection.clone();
+        let queue_handle = queue_handle.clone();
+        let tx = state.tx.clone();
+        thread::spawn(move || {
+            loop {
+                thread::sleep(interval);
+                if tx.is_closed() {
+                    tracing::info!("The workspaces are dropped, stop resyncing");
+                    return;
+                }
+                // wakes up the dispatch loop, which starts the resync on the callback
+                let _callback = display.sync(&queue_handle, ());
+                if let Err(e) = connection.flush() {
+                    tracing::error!(error = %e, "Failed to flush wayland requests, stop resyncing");
+                    return;
+                }
+            }
+        });
+    }
     loop {
+        if state.tx.is_closed() {
+            tracing::info!("The workspaces are dropped, stopping the wayland thread");
+            break;
+        }
         if let Err(e) = event_queue.blocking_dispatch(&mut state) {
             tracing::error!(error = %e, "Wayland dispatch error");
             if let Err(e) = state
@@ -190,8 +485,11 @@ fn wayland_thread(tx: UnboundedSender<Update>) {
         }
         tracing::info!("wayland dispatch");
     }
+    // also stops the resync thread, the error sent above is still received
+    state.tx.close_channel();
 }
 
+#[derive(Clone)]
 struct Workspace {
     id: Option<String>,
     name: String,
@@ -200,6 +498,7 @@ struct Workspace {
     capabilities: WorkspaceCapabilities,
 }
 
+#[derive(Clone)]
 struct WorkspaceState {
     active: bool,
     urgent: bool,
@@ -223,6 +522,7 @@ impl From<ext_workspace_handle_v1::State> for WorkspaceState {
 
 // TODO: use other workspace capabilities
 #[allow(dead_code)]
+#[derive(Clone)]
 struct WorkspaceCapabilities {
     activate: bool,
     deactivate: bool,
@@ -256,30 +556,181 @@ struct PendingWorkspace {
     capabilities: Option<ext_workspace_handle_v1::WorkspaceCapabilities>,
 }
 
+impl PendingWorkspace {
+    /// Returns `false` if the workspace was removed. An event that fails to parse is ignored, so
+    /// the pending workspace is never lost.
+    fn apply(&mut self, event: ext_workspace_handle_v1::Event) -> bool {
+        use ext_workspace_handle_v1::Event;
+
+        match event {
+            Event::Id { id } => {
+                tracing::info!(id);
+                self.id = Some(id);
+            }
+            Event::Name { name } => {
+                tracing::info!(name);
+                self.name = Some(name);
+            }
+            Event::Coordinates { coordinates } => {
+                tracing::info!(?coordinates);
+                self.coordinates = Some(coordinates);
+            }
+            Event::State { state } => match state.into_result() {
+                Ok(state) => {
+                    tracing::info!(?state);
+                    self.state = Some(state);
+                }
+                Err(e) => {
+                    tracing::error!(error = %e, "Failed to extract state");
+                }
+            },
+            Event::Capabilities { capabilities } => match capabilities.into_result() {
+                Ok(capabilities) => {
+                    tracing::info!(?capabilities);
+                    self.capabilities = Some(capabilities);
+                }
+                Err(e) => {
+                    tracing::error!(error = %e, "Failed to extract capabilities");
+                }
+            },
+            Event::Removed => return false,
+            _ => (),
+        }
+        true
+    }
+}
+
+impl TryFrom<PendingWorkspace> for Workspace {
+    type Error = PendingWorkspace;
+
+    fn try_from(value: PendingWorkspace) -> Result<Self, Self::Error> {
+        match value {
+            PendingWorkspace {
+                id,
+                name: Some(name),
+                coordinates,
+                state: Some(state),
+                capabilities: Some(capabilities),
+            } => Ok(Self {
+                id,
+                name,
+                coordinates,
+                state: state.into(),
+                capabilities: capabilities.into(),
+            }),
+            value => Err(value),
+        }
+    }
+}
+
+/// The complete workspaces of a resync, which replace all the old ones, and the handles of the
+/// incomplete ones to destroy.
+fn complete_workspaces<H: Eq + Hash>(
+    pending: HashMap<H, PendingWorkspace>,
+) -> (HashMap<H, Workspace>, Vec<H>) {
+    let mut workspaces = HashMap::new();
+    let mut incomplete = Vec::new();
+    for (handle, pending_workspace) in pending {
+        match Workspace::try_from(pending_workspace) {
+            Ok(workspace) => {
+                workspaces.insert(handle, workspace);
+            }
+            Err(pending_workspace) => {
+                tracing::warn!(?pending_workspace, "Incomplete workspace after resync");
+                incomplete.push(handle);
+            }
+        }
+    }
+    (workspaces, incomplete)
+}
+
 enum Update {
-    NewWorkspace {
-        handle: ExtWorkspaceHandleV1,
-        workspace: Workspace,
-    },
-    WorkspaceEvent {
-        handle: ExtWorkspaceHandleV1,
-        event: ext_workspace_handle_v1::Event,
+    /// All workspaces as of the last `done` of the workspace manager
+    Snapshot(HashMap<ExtWorkspaceHandleV1, Workspace>),
+    Manager {
+        connection: Connection,
+        manager: ExtWorkspaceManagerV1,
     },
     Error(String),
 }
 
+/// A second workspace manager bound to re-read all workspaces, it replaces the current one on its
+/// first `done`.
+struct Resync {
+    manager: ExtWorkspaceManagerV1,
+    workspaces: HashMap<ExtWorkspaceHandleV1, PendingWorkspace>,
+}
+
 struct State {
     tx: UnboundedSender<Update>,
+    /// The registry, name and version of the workspace manager global, to bind it again on resync
+    manager_global: Option<(WlRegistry, u32, u32)>,
     workspace_manager: Option<ExtWorkspaceManagerV1>,
+    resync: Option<Resync>,
     pending_workspaces: HashMap<ExtWorkspaceHandleV1, PendingWorkspace>,
+    /// Changes are applied here and only sent to the ui thread on `done`, so it never sees a
+    /// half-applied state like two active workspaces
+    workspaces: HashMap<ExtWorkspaceHandleV1, Workspace>,
+    changed: bool,
 }
 
 impl State {
     fn new(tx: UnboundedSender<Update>) -> Self {
         Self {
             tx,
+            manager_global: None,
             workspace_manager: None,
+            resync: None,
             pending_workspaces: HashMap::new(),
+            workspaces: HashMap::new(),
+            changed: false,
+        }
+    }
+    fn start_resync(&mut self, qhandle: &QueueHandle<Self>) {
+        if self.resync.is_some() {
+            tracing::warn!("The last resync isn't done yet");
+            return;
+        }
+        let Some((registry, name, version)) = &self.manager_global else {
+            return;
+        };
+        tracing::info!("Resyncing workspaces");
+        let manager = registry.bind::<ExtWorkspaceManagerV1, _, _>(*name, *version, qhandle, ());
+        self.resync = Some(Resync {
+            manager,
+            workspaces: HashMap::new(),
+        });
+    }
+    /// Replace the workspaces and the manager with the ones of the resync.
+    fn finish_resync(&mut self, conn: &Connection) {
+        let Some(resync) = self.resync.take() else {
+            return;
+        };
+        let (workspaces, incomplete) = complete_workspaces(resync.workspaces);
+        for handle in incomplete {
+            handle.destroy();
+        }
+        tracing::info!(workspaces = workspaces.len(), "Resynced workspaces");
+        if let Some(old) = self.workspace_manager.replace(resync.manager.clone()) {
+            old.stop();
+        }
+        for handle in self.workspaces.keys().chain(self.pending_workspaces.keys()) {
+            handle.destroy();
+        }
+        self.pending_workspaces.clear();
+        self.workspaces = workspaces;
+        self.changed = false;
+        if let Err(e) = self.tx.unbounded_send(Update::Manager {
+            connection: conn.clone(),
+            manager: resync.manager,
+        }) {
+            tracing::error!(error = %e, "Failed to send update to ui thread");
+        }
+        if let Err(e) = self
+            .tx
+            .unbounded_send(Update::Snapshot(self.workspaces.clone()))
+        {
+            tracing::error!(error = %e, "Failed to send update to ui thread");
         }
     }
 }
@@ -290,7 +741,7 @@ impl Dispatch<WlRegistry, ()> for State {
         proxy: &WlRegistry,
         event: wl_registry::Event,
         _data: &(),
-        _conn: &Connection,
+        conn: &Connection,
         qhandle: &QueueHandle<Self>,
     ) {
         use wl_registry::Event;
@@ -305,7 +756,14 @@ impl Dispatch<WlRegistry, ()> for State {
                     tracing::info!(name, interface, version);
                     let workspace_manager =
                         proxy.bind::<ExtWorkspaceManagerV1, _, _>(name, version, qhandle, ());
+                    if let Err(e) = state.tx.unbounded_send(Update::Manager {
+                        connection: conn.clone(),
+                        manager: workspace_manager.clone(),
+                    }) {
+                        tracing::error!(error = %e, "Failed to send update to ui thread");
+                    }
                     state.workspace_manager = Some(workspace_manager);
+                    state.manager_global = Some((proxy.clone(), name, version));
                 }
                 _ => (),
             },
@@ -314,18 +772,54 @@ impl Dispatch<WlRegistry, ()> for State {
     }
 }
 
+impl Dispatch<WlCallback, ()> for State {
+    fn event(
+        state: &mut Self,
+        _proxy: &WlCallback,
+        event: wl_callback::Event,
+        _data: &(),
+        _conn: &Connection,
+        qhandle: &QueueHandle<Self>,
+    ) {
+        if let wl_callback::Event::Done { .. } = event {
+            state.start_resync(qhandle);
+        }
+    }
+}
+
 impl Dispatch<ExtWorkspaceManagerV1, ()> for State {
     fn event(
         state: &mut Self,
-        _proxy: &ExtWorkspaceManagerV1,
+        proxy: &ExtWorkspaceManagerV1,
         event: ext_workspace_manager_v1::Event,
         _data: &(),
-        _conn: &Connection,
+        conn: &Connection,
         _qhandle: &QueueHandle<Self>,
     ) {
         use ext_workspace_manager_v1::Event;
 
         tracing::info!(?event, "ext_workspace_manager_v1");
+        if let Some(resync) = &mut state.resync
+            && &resync.manager == proxy
+        {
+            match event {
+                Event::Workspace { workspace } => {
+                    resync
+                        .workspaces
+                        .insert(workspace, PendingWorkspace::default());
+                }
+                Event::Done => state.finish_resync(conn),
+                _ => (),
+            }
+            return;
+        }
+        if state.workspace_manager.as_ref() != Some(proxy) {
+            // the manager replaced by a resync, until the compositor finishes it
+            if let Event::Workspace { workspace } = event {
+                workspace.destroy();
+            }
+            return;
+        }
         match event {
             Event::WorkspaceGroup { workspace_group } => {
                 tracing::info!(?workspace_group);
@@ -336,7 +830,17 @@ impl Dispatch<ExtWorkspaceManagerV1, ()> for State {
                     .pending_workspaces
                     .insert(workspace, PendingWorkspace::default());
             }
-            Event::Done => {}
+            Event::Done => {
+                if state.changed {
+                    state.changed = false;
+                    if let Err(e) = state
+                        .tx
+                        .unbounded_send(Update::Snapshot(state.workspaces.clone()))
+                    {
+                        tracing::error!(error = %e, "Failed to send update to ui thread");
+                    }
+                }
+            }
             Event::Finished => {}
             _ => (),
         }
@@ -386,20 +890,49 @@ impl Dispatch<ExtWorkspaceHandleV1, ()> for State {
         use ext_workspace_handle_v1::Event;
 
         tracing::info!(?event, "ext_workspace_handle_v1");
+        if let Some(resync) = &mut state.resync
+            && let Some(pending_workspace) = resync.workspaces.get_mut(proxy)
+        {
+            if !pending_workspace.apply(event) {
+                resync.workspaces.remove(proxy);
+            }
+            return;
+        }
         if let Some((handle, mut pending_workspace)) = state.pending_workspaces.remove_entry(proxy)
         {
+            // every path puts the pending workspace somewhere, unless it was removed
+            if !pending_workspace.apply(event) {
+                tracing::info!(?pending_workspace, "remove pending workspace");
+                return;
+            }
+            match Workspace::try_from(pending_workspace) {
+                Ok(workspace) => {
+                    state.workspaces.insert(handle, workspace);
+                    state.changed = true;
+                }
+                Err(pending_workspace) => {
+                    tracing::info!(?pending_workspace);
+                    state.pending_workspaces.insert(handle, pending_workspace);
+                }
+            }
+            tracing::info!(pending_workspaces = state.pending_workspaces.len());
+        } else {
+            let Some(workspace) = state.workspaces.get_mut(proxy) else {
+                tracing::error!(?proxy, ?event, "A new event for non-existing workspace");
+                return;
+            };
             match event {
                 Event::Id { id } => {
                     tracing::info!(id);
-                    pending_workspace.id = Some(id);
+                    workspace.id = Some(id);
                 }
                 Event::Name { name } => {
                     tracing::info!(name);
-                    pending_workspace.name = Some(name);
+                    workspace.name = name;
                 }
                 Event::Coordinates { coordinates } => {
                     tracing::info!(?coordinates);
-                    pending_workspace.coordinates = Some(coordinates);
+                    workspace.coordinates = Some(coordinates);
                 }
                 Event::State { state } => {
                     let state = match state.into_result() {
@@ -410,7 +943,7 @@ impl Dispatch<ExtWorkspaceHandleV1, ()> for State {
                         }
                     };
                     tracing::info!(?state);
-                    pending_workspace.state = Some(state);
+                    workspace.state = state.into();
                 }
                 Event::Capabilities { capabilities } => {
                     let capabilities = match capabilities.into_result() {
@@ -421,47 +954,118 @@ impl Dispatch<ExtWorkspaceHandleV1, ()> for State {
                         }
                     };
                     tracing::info!(?capabilities);
-                    pending_workspace.capabilities = Some(capabilities);
+                    workspace.capabilities = capabilities.into();
                 }
                 Event::Removed => {
-                    tracing::info!(?pending_workspace, "remove pending workspace");
-                    return;
+                    state.workspaces.remove(proxy);
+                    tracing::info!(?proxy, "remove workspace");
                 }
                 _ => (),
             }
-
-            if let PendingWorkspace {
-                id,
-                name: Some(name),
-                coordinates,
-                state: Some(workspace_state),
-                capabilities: Some(capabilities),
-            } = pending_workspace
-            {
-                if let Err(e) = state.tx.unbounded_send(Update::NewWorkspace {
-                    handle,
-                    workspace: Workspace {
-                        id,
-                        name,
-                        coordinates,
-                        state: workspace_state.into(),
-                        capabilities: capabilities.into(),
-                    },
-                }) {
-                    tracing::error!(error = %e, "Failed to send update to ui thread");
-                }
-            } else {
-                tracing::info!(?pending_workspace);
-                state.pending_workspaces.insert(handle, pending_workspace);
-            }
-            tracing::info!(pending_workspaces = state.pending_workspaces.len());
-        } else {
-            if let Err(e) = state.tx.unbounded_send(Update::WorkspaceEvent {
-                handle: proxy.clone(),
-                event,
-            }) {
-                tracing::error!(error = %e, "Failed to send update to ui thread");
-            }
+            state.changed = true;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ext_workspace_handle_v1::{Event, State, WorkspaceCapabilities};
+    use wayland_client::WEnum;
+
+    use super::*;
+
+    #[test]
+    fn workspace_recovers_from_unknown_values() {
+        let mut pending = PendingWorkspace::default();
+        assert!(pending.apply(Event::Name {
+            name: "1".to_owned()
+        }));
+        assert!(pending.apply(Event::State {
+            state: WEnum::Unknown(u32::MAX)
+        }));
+        assert!(pending.apply(Event::Capabilities {
+            capabilities: WEnum::Unknown(u32::MAX)
+        }));
+        // the invalid events are ignored, so the workspace isn't complete yet
+        let Err(mut pending) = Workspace::try_from(pending) else {
+            panic!("the workspace has no state yet");
+        };
+        assert_eq!(pending.name.as_deref(), Some("1"));
+
+        assert!(pending.apply(Event::State {
+            state: WEnum::Value(State::Active)
+        }));
+        assert!(pending.apply(Event::Capabilities {
+            capabilities: WEnum::Value(WorkspaceCapabilities::Activate)
+        }));
+        let Ok(workspace) = Workspace::try_from(pending) else {
+            panic!("the workspace is complete");
+        };
+        assert_eq!(workspace.name, "1");
+        assert!(workspace.state.active);
+        assert!(!workspace.state.hidden);
+        assert!(workspace.capabilities.activate);
+    }
+
+    #[test]
+    fn removed_workspace() {
+        let mut pending = PendingWorkspace::default();
+        assert!(!pending.apply(Event::Removed));
+    }
+
+    #[test]
+    fn scroll_one_workspace_at_a_time() {
+        let mut config = WorkspacesConfig::default();
+        assert_eq!(config.scroll_target(1, 4, 0), None);
+        assert_eq!(config.scroll_target(1, 4, -3), Some(2));
+        assert_eq!(config.scroll_target(1, 4, 3), Some(0));
+        assert_eq!(config.scroll_target(0, 4, 1), None);
+        assert_eq!(config.scroll_target(3, 4, -1), None);
+        config.scroll_wrap = true;
+        assert_eq!(config.scroll_target(0, 4, 1), Some(3));
+        assert_eq!(config.scroll_target(3, 4, -1), Some(0));
+        assert_eq!(config.scroll_target(0, 0, 1), None);
+    }
+
+    #[test]
+    fn limit_visible_keeps_active_in_window() {
+        let mut config = WorkspacesConfig::default();
+        assert_eq!(config.visible_range(20, Some(10)), None);
+        config.max_visible = Some(5);
+        // they all fit
+        assert_eq!(config.visible_range(5, Some(4)), None);
+        assert_eq!(config.visible_range(0, None), None);
+        // overflow after, at the start without an active workspace
+        assert_eq!(config.visible_range(8, Some(1)), Some(0..5));
+        assert_eq!(config.visible_range(8, None), Some(0..5));
+        // overflow on both sides, the active one in the middle
+        assert_eq!(config.visible_range(20, Some(10)), Some(8..13));
+        // overflow before, the window stays full at the end
+        assert_eq!(config.visible_range(20, Some(19)), Some(15..20));
+        assert_eq!(config.visible_range(20, Some(17)), Some(15..20));
+        // at least one is shown
+        config.max_visible = Some(0);
+        assert_eq!(config.visible_range(3, Some(2)), Some(2..3));
+    }
+
+    #[test]
+    fn resync_keeps_complete_workspaces() {
+        let complete = PendingWorkspace {
+            name: Some("1".to_owned()),
+            state: Some(State::Active),
+            capabilities: Some(WorkspaceCapabilities::Activate),
+            ..PendingWorkspace::default()
+        };
+        // the compositor didn't send the state yet
+        let incomplete = PendingWorkspace {
+            name: Some("2".to_owned()),
+            ..PendingWorkspace::default()
+        };
+        let (workspaces, incomplete) =
+            complete_workspaces(HashMap::from([(1, complete), (2, incomplete)]));
+        assert_eq!(workspaces.len(), 1);
+        assert_eq!(workspaces[&1].name, "1");
+        assert!(workspaces[&1].state.active);
+        assert_eq!(incomplete, [2]);
+    }
+}